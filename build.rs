@@ -132,6 +132,55 @@ fn main() {
     fs::write(&dest, &generated).unwrap();
 
     generate_builtin_manifest(&out_dir);
+    generate_adapter_fingerprint(&out_dir);
+}
+
+/// Hash every source file of the tier-1 adapter generator into a
+/// string literal that `src/adapter/cache.rs` `include!`s as part of
+/// its cache key, so adapters cached by one build are never served by
+/// a build whose generator changed without a version bump (e.g. while
+/// developing splicer itself). `DefaultHasher` is only stable within a
+/// toolchain, which is fine: a toolchain change just misses the cache.
+fn generate_adapter_fingerprint(out_dir: &str) {
+    use std::hash::{Hash, Hasher};
+
+    let dest = Path::new(out_dir).join("adapter_fingerprint.rs");
+    let src_dir = Path::new("src/adapter");
+    println!("cargo::rerun-if-changed={}", src_dir.display());
+
+    let mut files = Vec::new();
+    collect_files(src_dir, &mut files);
+    files.sort();
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for file in &files {
+        file.hash(&mut hasher);
+        fs::read(file)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {e}", file.display()))
+            .hash(&mut hasher);
+    }
+    fs::write(
+        &dest,
+        format!(
+            "// Auto-generated by build.rs from src/adapter/. Do not edit.\n\"{:016x}\"\n",
+            hasher.finish()
+        ),
+    )
+    .unwrap();
+}
+
+/// Every file under `dir`, recursively.
+fn collect_files(dir: &Path, out: &mut Vec<std::path::PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, out);
+        } else {
+            out.push(path);
+        }
+    }
 }
 
 /// Scan `builtins/<name>/Cargo.toml` for every builtin crate and emit
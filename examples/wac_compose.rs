@@ -79,23 +79,20 @@ fn main() -> Result<()> {
     // Note: we explicitly alias the components so the WAC variable
     // names are deterministic (`provider-a`, `consumer`) regardless
     // of what the on-disk filenames look like.
-    let out = compose(ComposeRequest {
-        components: vec![
-            ComponentInput {
-                alias: Some("provider-a".to_string()),
-                path: provider_path.clone(),
-            },
-            ComponentInput {
-                alias: Some("consumer".to_string()),
-                path: consumer_path.clone(),
-            },
-        ],
-        package_name: "example:composition".to_string(),
-    })?;
+    let out = compose(ComposeRequest::new(vec![
+        ComponentInput {
+            alias: Some("provider-a".to_string()),
+            path: provider_path.clone(),
+        },
+        ComponentInput {
+            alias: Some("consumer".to_string()),
+            path: consumer_path.clone(),
+        },
+    ]))?;
 
     println!(
         "✓ splicer::compose produced {} bytes of WAC source, {} dep(s)",
-        out.wac.len(),
+        out.wac().len(),
         out.wac_deps.len()
     );
 
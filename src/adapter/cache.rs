@@ -0,0 +1,233 @@
+//! Content-addressed on-disk cache of generated tier-1 adapters.
+//!
+//! An adapter is fully determined by the target interface's WIT (plus
+//! every interface it `use`s types from), the set of tier-1 hooks the
+//! middleware exports, the tier-1/common WIT splicer embeds, and the
+//! generator itself. [`cache_key`] hashes exactly those, so the same
+//! adapter requested by any composition — in this run or a later one —
+//! is generated once and then copied out of
+//! `<user-cache>/splicer/adapters/<key>.wasm`.
+//!
+//! The cache is an optimization only: a miss, an unreadable entry or a
+//! failed write just means the adapter is generated as usual. Set
+//! `SPLICER_NO_ADAPTER_CACHE` to bypass it entirely.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+use wit_component::WitPrinter;
+use wit_parser::{InterfaceId, Resolve};
+
+use super::emit::find_target_interface;
+use super::{COMMON_WORLD_WIT, TIER1_WORLD_WIT};
+use crate::builtins::{cache_root, write_cache_atomically};
+
+/// Subdirectory of the splicer cache root holding generated adapters.
+const ADAPTER_SUBDIR: &str = "adapters";
+
+/// Env var that, if set, disables the adapter cache.
+const NO_CACHE_ENV: &str = "SPLICER_NO_ADAPTER_CACHE";
+
+/// Fingerprint of the adapter generator's sources, computed by
+/// `build.rs`. Invalidates the cache whenever the generator changes,
+/// even without a version bump.
+const GENERATOR_FINGERPRINT: &str = include!(concat!(env!("OUT_DIR"), "/adapter_fingerprint.rs"));
+
+/// Handle on the adapter cache directory.
+pub(crate) struct AdapterCache {
+    dir: PathBuf,
+}
+
+impl AdapterCache {
+    /// The user's adapter cache, or `None` when it's disabled through
+    /// `SPLICER_NO_ADAPTER_CACHE` or there's no user cache directory.
+    pub(crate) fn open() -> Option<Self> {
+        if std::env::var_os(NO_CACHE_ENV).is_some() {
+            return None;
+        }
+        Some(Self::at(cache_root()?.join(ADAPTER_SUBDIR)))
+    }
+
+    /// A cache rooted at `dir`.
+    pub(crate) fn at(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The cached adapter for `key`, if there is one.
+    pub(crate) fn get(&self, key: &str) -> Option<Vec<u8>> {
+        std::fs::read(self.path_for(key)).ok()
+    }
+
+    /// Store `bytes` under `key`. Failures are logged and otherwise
+    /// ignored.
+    pub(crate) fn put(&self, key: &str, bytes: &[u8]) {
+        let path = self.path_for(key);
+        let stored = std::fs::create_dir_all(&self.dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| write_cache_atomically(&path, bytes));
+        if let Err(e) = stored {
+            tracing::debug!("not caching adapter at {}: {e:#}", path.display());
+        }
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.wasm"))
+    }
+}
+
+/// Cache key for the adapter [`super::build_tier1_adapter`] would
+/// build from these inputs: a hex sha256 over the splicer version, the
+/// generator fingerprint, the embedded tier-1/common WIT, the hook set,
+/// and the WIT of `target_interface` and everything it depends on in
+/// the consumer split's decoded `split_wit`.
+///
+/// Fails when the split doesn't carry the target interface; the
+/// caller then builds without the cache and reports the builder's
+/// (more specific) error.
+pub(crate) fn cache_key(
+    target_interface: &str,
+    has_before: bool,
+    has_after: bool,
+    has_blocking: bool,
+    split_wit: &Resolve,
+) -> anyhow::Result<String> {
+    let target = find_target_interface(split_wit, target_interface)?;
+
+    let mut hasher = Sha256::new();
+    for part in [
+        env!("CARGO_PKG_VERSION"),
+        GENERATOR_FINGERPRINT,
+        COMMON_WORLD_WIT,
+        TIER1_WORLD_WIT,
+        target_interface,
+    ] {
+        hasher.update(part.len().to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.update([has_before as u8, has_after as u8, has_blocking as u8]);
+    hasher.update(interface_closure_wit(split_wit, target)?);
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// WIT text of `target` and every interface it transitively depends
+/// on, each headed by its fully-qualified name, in name order — so the
+/// result doesn't depend on arena ids or on which unrelated interfaces
+/// the split happens to carry.
+fn interface_closure_wit(resolve: &Resolve, target: InterfaceId) -> anyhow::Result<String> {
+    let mut seen = BTreeSet::new();
+    let mut stack = vec![target];
+    while let Some(id) = stack.pop() {
+        if seen.insert(id) {
+            stack.extend(resolve.interface_direct_deps(id));
+        }
+    }
+
+    let mut printed: Vec<(String, String)> = Vec::new();
+    for id in seen {
+        let mut printer = WitPrinter::default();
+        printer.emit_docs(false);
+        printer.print_interface(resolve, id)?;
+        let name = resolve.id_of(id).unwrap_or_default();
+        printed.push((name, printer.output.to_string()));
+    }
+    printed.sort();
+    Ok(printed
+        .into_iter()
+        .map(|(name, wit)| format!("interface {name} {{\n{wit}\n}}\n"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A consumer-style split importing `my:pkg/api` (which uses a
+    /// record from `my:pkg/types`) plus an unrelated interface.
+    fn split(extra_fn: &str, point_fields: &str) -> Resolve {
+        let bytes = wat::parse_str(format!(
+            r#"(component
+                (type $types (instance
+                    (type $point (record {point_fields}))
+                    (export "point" (type (eq $point)))
+                ))
+                (import "my:pkg/types" (instance $t (type $types)))
+                (alias export $t "point" (type $point))
+                (import "my:pkg/api" (instance
+                    (export "point" (type $p (eq $point)))
+                    (export "get" (func (result $p)))
+                ))
+                (import "my:other/iface" (instance
+                    (export "{extra_fn}" (func))
+                ))
+            )"#
+        ))
+        .unwrap();
+        crate::adapter::decode_component_wit(&bytes).unwrap().0
+    }
+
+    #[test]
+    fn key_ignores_unrelated_interfaces() {
+        let fields = r#"(field "x" u32)"#;
+        let a = cache_key("my:pkg/api", true, false, false, &split("ping", fields)).unwrap();
+        let b = cache_key("my:pkg/api", true, false, false, &split("pong", fields)).unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn key_covers_hooks_and_dependencies() {
+        let fields = r#"(field "x" u32)"#;
+        let base = cache_key("my:pkg/api", true, false, false, &split("ping", fields)).unwrap();
+        let hooks = cache_key("my:pkg/api", true, true, false, &split("ping", fields)).unwrap();
+        let dep = cache_key(
+            "my:pkg/api",
+            true,
+            false,
+            false,
+            &split("ping", r#"(field "x" u32) (field "y" u32)"#),
+        )
+        .unwrap();
+        assert_ne!(base, hooks);
+        assert_ne!(base, dep, "a changed `use`d type changes the key");
+    }
+
+    /// The second build of the same adapter is served from the cache.
+    #[test]
+    fn build_reuses_cached_adapter() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = AdapterCache::at(tmp.path().join("adapters"));
+        let split = split("ping", r#"(field "x" u32)"#);
+        let hooks = ["splicer:tier1/before@0.1.0".to_string()];
+        let build = || {
+            crate::adapter::build_tier1_adapter(
+                "mw",
+                "my:pkg/api",
+                &hooks,
+                "out",
+                &split,
+                Some(&cache),
+            )
+            .unwrap()
+            .1
+        };
+
+        let built = build();
+        let entries: Vec<_> = std::fs::read_dir(&cache.dir).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+
+        // Tamper with the entry: a cache hit returns it verbatim.
+        let entry = entries[0].as_ref().unwrap().path();
+        std::fs::write(&entry, b"cached").unwrap();
+        assert_eq!(build(), b"cached");
+        assert_ne!(built, b"cached");
+    }
+
+    #[test]
+    fn put_then_get() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cache = AdapterCache::at(tmp.path().join("adapters"));
+        assert_eq!(cache.get("abc"), None);
+        cache.put("abc", b"\0asm");
+        assert_eq!(cache.get("abc").as_deref(), Some(&b"\0asm"[..]));
+    }
+}
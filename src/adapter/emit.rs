@@ -29,15 +29,19 @@ use wit_parser::abi::{AbiVariant, FlatTypes, WasmSignature, WasmType};
 use wit_parser::{
     Function as WitFunction, Handle, InterfaceId, LiftLowerAbi, Mangling, ManglingAndAbi, Resolve,
     ResourceIntrinsic, SizeAlign, Type, TypeDefKind, TypeId, TypeOwner, WasmExport, WasmExportKind,
-    WasmImport, WorldItem, WorldKey,
+    WasmImport, WorldId, WorldItem, WorldKey,
 };
 
+use crate::error::Error;
+
 use super::abi::WasmEncoderBindgen;
 use super::indices::{DispatchIndices, FunctionIndices};
 use super::mem_layout::MemoryLayoutBuilder;
 
 /// Generate the adapter component bytes. `target_interface` is the
 /// fully-qualified interface name (`<ns>:<pkg>/<iface>[@<ver>]`);
+/// `split_wit` is the consumer split's decoded WIT (see
+/// [`decode_component_wit`]);
 /// `common_world_wit` is the contents of `wit/common/world.wit`
 /// (loaded first as a dependency); `tier1_world_wit` is the contents
 /// of `wit/tier1/world.wit` (which references `splicer:common`).
@@ -46,11 +50,11 @@ pub(crate) fn build_adapter(
     has_before: bool,
     has_after: bool,
     has_blocking: bool,
-    split_bytes: &[u8],
+    split_wit: &Resolve,
     common_world_wit: &str,
     tier1_world_wit: &str,
 ) -> Result<Vec<u8>> {
-    let mut resolve = decode_input_resolve(split_bytes)?;
+    let mut resolve = split_wit.clone();
     let target_iface = find_target_interface(&resolve, target_interface)?;
 
     require_supported_case(&resolve, target_iface, has_blocking)?;
@@ -91,13 +95,14 @@ pub(crate) fn build_adapter(
         .context("ComponentEncoder::encode")
 }
 
-/// Decode the input split's WIT into a [`Resolve`]; bail if the bytes
-/// decode to a WIT package rather than a component. `wit_component::decode`
-/// panics on splits that import + re-export a resource-bearing instance
+/// Decode a component's WIT into a [`Resolve`] and the world it
+/// targets; bail if the bytes decode to a WIT package rather than a
+/// component. `wit_component::decode` panics on splits that import +
+/// re-export a resource-bearing instance
 /// (https://github.com/bytecodealliance/wasm-tools/issues/2506); catch
 /// it and surface a structured error so the process doesn't die.
-fn decode_input_resolve(split_bytes: &[u8]) -> Result<Resolve> {
-    let decoded = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| decode(split_bytes)))
+pub(crate) fn decode_component_wit(bytes: &[u8]) -> Result<(Resolve, WorldId)> {
+    let decoded = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| decode(bytes)))
         .map_err(|_| {
             anyhow!(
                 "wit-parser panic during component decode — likely the import + re-export \
@@ -106,18 +111,21 @@ fn decode_input_resolve(split_bytes: &[u8]) -> Result<Resolve> {
                  path can't proceed until that's fixed upstream."
             )
         })?
-        .context("wit_component::decode split")?;
+        .context("wit_component::decode component")?;
     match decoded {
-        DecodedWasm::Component(resolve, _world) => Ok(resolve),
+        DecodedWasm::Component(resolve, world) => Ok((resolve, world)),
         DecodedWasm::WitPackage(_, _) => bail!(
-            "split bytes decoded to a WIT package; \
+            "component bytes decoded to a WIT package; \
              expected a component"
         ),
     }
 }
 
 /// Find the target interface by its fully-qualified name.
-fn find_target_interface(resolve: &Resolve, target_interface: &str) -> Result<InterfaceId> {
+pub(super) fn find_target_interface(
+    resolve: &Resolve,
+    target_interface: &str,
+) -> Result<InterfaceId> {
     resolve
         .interfaces
         .iter()
@@ -140,14 +148,25 @@ fn find_target_interface(resolve: &Resolve, target_interface: &str) -> Result<In
 /// functions need a different dispatch shape; tier-1 blocking on a
 /// non-void func is impossible (the adapter can't synthesize a return
 /// value when the call is skipped) — same constraint legacy enforces.
+///
+/// Failures are raised as [`Error::UnsupportedAdapter`] so API callers
+/// can tell them apart from transient adapter-build failures.
 fn require_supported_case(
     resolve: &Resolve,
     target_iface: InterfaceId,
     has_blocking: bool,
 ) -> Result<()> {
     let iface = &resolve.interfaces[target_iface];
+    let iface_name = resolve
+        .id_of(target_iface)
+        .unwrap_or_else(|| iface.name.clone().unwrap_or_default());
+    let unsupported = |function: Option<&str>, reason: String| Error::UnsupportedAdapter {
+        interface: iface_name.clone(),
+        function: function.map(str::to_string),
+        reason,
+    };
     if iface.functions.is_empty() {
-        bail!("interface has no functions");
+        return Err(unsupported(None, "interface has no functions".to_string()).into());
     }
     // Inline-resource interfaces (resources declared in the same
     // interface that uses them) can't survive splicer's wrapper
@@ -160,28 +179,33 @@ fn require_supported_case(
         if matches!(td.kind, TypeDefKind::Resource)
             && matches!(td.owner, TypeOwner::Interface(owner) if owner == target_iface)
         {
-            let iface_name = resolve
-                .id_of(target_iface)
-                .unwrap_or_else(|| iface.name.clone().unwrap_or_default());
-            bail!(
-                "interface `{iface_name}` declares resource `{ty_name}` inline. \
-                 Splicer's wrapper-component pattern can't preserve resource \
-                 type identity for inline resources — runtime handle traffic \
-                 between the import side and export side will be rejected. \
-                 Move `{ty_name}` into a sibling `types` interface and \
-                 reference it via `use types.{{{ty_name}}}` (the wasi-style \
-                 factored-types pattern)."
-            );
+            return Err(unsupported(
+                None,
+                format!(
+                    "interface `{iface_name}` declares resource `{ty_name}` inline. \
+                     Splicer's wrapper-component pattern can't preserve resource \
+                     type identity for inline resources — runtime handle traffic \
+                     between the import side and export side will be rejected. \
+                     Move `{ty_name}` into a sibling `types` interface and \
+                     reference it via `use types.{{{ty_name}}}` (the wasi-style \
+                     factored-types pattern)."
+                ),
+            )
+            .into());
         }
     }
     for (name, func) in &iface.functions {
         if has_blocking && func.result.is_some() {
-            bail!(
-                "Function '{name}' returns a value but the middleware exports \
-                 `should-block`. Tier-1 blocking is only supported for \
-                 void-returning functions because the adapter cannot synthesize \
-                 a return value when the call is blocked."
-            );
+            return Err(unsupported(
+                Some(name),
+                format!(
+                    "Function '{name}' returns a value but the middleware exports \
+                     `should-block`. Tier-1 blocking is only supported for \
+                     void-returning functions because the adapter cannot synthesize \
+                     a return value when the call is blocked."
+                ),
+            )
+            .into());
         }
         // Async funcs whose params overflow `MAX_FLAT_ASYNC_PARAMS = 4`
         // canon-lower with `indirect_params = true` — the handler takes a
@@ -194,11 +218,15 @@ fn require_supported_case(
         if func.kind.is_async() {
             let import_sig = resolve.wasm_signature(AbiVariant::GuestImportAsync, func);
             if import_sig.indirect_params {
-                bail!(
-                    "async function `{name}` has params that overflow \
-                     MAX_FLAT_ASYNC_PARAMS (4) and require lower-to-memory; \
-                     not yet implemented"
-                );
+                return Err(unsupported(
+                    Some(name),
+                    format!(
+                        "async function `{name}` has params that overflow \
+                         MAX_FLAT_ASYNC_PARAMS (4) and require lower-to-memory; \
+                         not yet implemented"
+                    ),
+                )
+                .into());
             }
         }
     }
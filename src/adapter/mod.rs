@@ -3,6 +3,8 @@
 //! target interface.
 //!
 //! Submodules:
+//! - [`cache`] — content-addressed on-disk cache of generated
+//!   adapters, consulted before building one.
 //! - [`emit`] — entry point ([`emit::build_adapter`]) that synthesizes
 //!   the adapter world's WIT, builds a dispatch core module, and
 //!   hands everything to `wit_component::ComponentEncoder`.
@@ -16,14 +18,19 @@
 
 use anyhow::Context;
 
+use crate::artifacts::Artifacts;
+
 mod abi;
+mod cache;
 mod emit;
 mod indices;
 mod mem_layout;
 #[cfg(test)]
 mod tests;
 
+pub(crate) use cache::AdapterCache;
 use emit::build_adapter;
+pub(crate) use emit::decode_component_wit;
 
 /// WIT/world definitions for the splicer:tier1 hook interfaces.
 /// Embedded directly into the generated adapter's WIT so
@@ -56,33 +63,94 @@ pub fn generate_tier1_adapter(
     splits_output_path: &str,
     split_path: &str,
 ) -> anyhow::Result<String> {
+    let mut artifacts = Artifacts::on_disk();
+    let split_bytes = artifacts
+        .read(split_path)
+        .with_context(|| format!("Failed to read split at '{split_path}'"))?;
+    let split_wit = artifacts.parsed().wit(&split_bytes)?;
+    let (out_path, bytes) = build_tier1_adapter(
+        middleware_name,
+        target_interface,
+        middleware_interfaces,
+        splits_output_path,
+        &split_wit.0,
+        AdapterCache::open().as_ref(),
+    )?;
+    drop(split_bytes);
+    artifacts
+        .write(&out_path, bytes)
+        .with_context(|| format!("Failed to write adapter component to '{}'", out_path))?;
+
+    Ok(out_path)
+}
+
+/// Byte-level core of [`generate_tier1_adapter`]: build the adapter
+/// from the consumer split's decoded WIT (see [`decode_component_wit`])
+/// and return `(path, bytes)`, where `path` is where the adapter
+/// belongs under `splits_output_path`.
+/// Nothing is written — the caller decides whether the adapter lands
+/// on disk or in an in-memory [`crate::artifacts::Artifacts`] store —
+/// except to `cache`, which is checked first and filled on a miss.
+pub(crate) fn build_tier1_adapter(
+    middleware_name: &str,
+    target_interface: &str,
+    middleware_interfaces: &[String],
+    splits_output_path: &str,
+    split_wit: &wit_parser::Resolve,
+    cache: Option<&AdapterCache>,
+) -> anyhow::Result<(String, Vec<u8>)> {
     let has_before = middleware_interfaces.iter().any(|i| i.contains("/before"));
     let has_after = middleware_interfaces.iter().any(|i| i.contains("/after"));
     let has_blocking = middleware_interfaces
         .iter()
         .any(|i| i.contains("/blocking"));
 
-    let split_bytes = std::fs::read(split_path)
-        .with_context(|| format!("Failed to read split at '{split_path}'"))?;
-    let bytes = build_adapter(
-        target_interface,
-        has_before,
-        has_after,
-        has_blocking,
-        &split_bytes,
-        COMMON_WORLD_WIT,
-        TIER1_WORLD_WIT,
-    )?;
+    let cached = cache.and_then(|cache| {
+        let key = cache::cache_key(
+            target_interface,
+            has_before,
+            has_after,
+            has_blocking,
+            split_wit,
+        )
+        .ok()?;
+        Some((cache, key))
+    });
+    let bytes = match cached.as_ref().and_then(|(cache, key)| cache.get(key)) {
+        Some(bytes) => bytes,
+        None => {
+            let bytes = build_adapter(
+                target_interface,
+                has_before,
+                has_after,
+                has_blocking,
+                split_wit,
+                COMMON_WORLD_WIT,
+                TIER1_WORLD_WIT,
+            )?;
+            if let Some((cache, key)) = &cached {
+                cache.put(key, &bytes);
+            }
+            bytes
+        }
+    };
+
+    let out_path = tier1_adapter_path(splits_output_path, middleware_name, target_interface);
+    Ok((out_path, bytes))
+}
 
-    let out_path = format!(
+/// Where [`build_tier1_adapter`] places the adapter wrapping
+/// `middleware_name` on `target_interface` under `splits_output_path`.
+pub(crate) fn tier1_adapter_path(
+    splits_output_path: &str,
+    middleware_name: &str,
+    target_interface: &str,
+) -> String {
+    format!(
         "{splits_output_path}/splicer_adapter_{}_{}.wasm",
         sanitize_name(middleware_name),
         sanitize_name(target_interface)
-    );
-    std::fs::write(&out_path, &bytes)
-        .with_context(|| format!("Failed to write adapter component to '{}'", out_path))?;
-
-    Ok(out_path)
+    )
 }
 
 fn sanitize_name(s: &str) -> String {
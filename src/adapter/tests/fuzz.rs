@@ -64,6 +64,14 @@ fn test_adapter_async_indirect_params_bails() {
         msg.contains("not yet implemented") && msg.contains("MAX_FLAT_ASYNC_PARAMS"),
         "bail should mention the limit and not-yet-implemented, got: {msg}"
     );
+    // Surfaced as a typed error naming the offending function.
+    assert!(
+        matches!(
+            err.downcast_ref::<crate::Error>(),
+            Some(crate::Error::UnsupportedAdapter { function: Some(f), .. }) if f == "many"
+        ),
+        "expected UnsupportedAdapter for `many`, got: {err:?}"
+    );
 }
 
 #[test]
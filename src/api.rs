@@ -2,34 +2,59 @@
 //! points. See the [crate-level docs](crate) for usage examples and
 //! the full API guide.
 
-use std::collections::{BTreeMap, HashMap};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use anyhow::{Context, Result};
-use cviz::parse::component::parse_component;
-use wac_graph::EncodeOptions;
+use anyhow::Context;
+use colored::Colorize;
+use cviz::model::CompositionGraph;
+use serde::{Deserialize, Serialize};
+use wac_graph::{EncodeOptions, Processor};
 use wac_parser::Document;
-use wac_resolver::{packages, FileSystemPackageResolver};
+use wac_resolver::packages;
 
+use crate::artifacts::{content_hash, Artifacts, IN_MEMORY_ROOT};
 use crate::builtins;
-use crate::compose::{build_graph_from_components, filename_from_path};
+use crate::compose::{build_graph_in, filename_from_path};
 use crate::contract::ContractResult;
-use crate::parse::config::{parse_yaml, SpliceRule};
-use crate::split::split_out_composition;
-use crate::wac::{generate_wac, GeneratedAdapter};
+use crate::error::{Error, Result};
+use crate::nested::{nested_split, nested_splits_dir, partition_rules, scope_to_root};
+use crate::packages::{package_key, PackageSource};
+use crate::parse::config::{self, validate_rules, SpliceRule};
+use crate::plan::CompositionPlan;
+use crate::provenance::Provenance;
+use crate::split::{rewrite_split, split_into, Nesting, SplitPaths, PATH_TO_SPLITS};
+use crate::strict::make_explicit;
+use crate::unsplice::{self as unsplice_graph, MiddlewareSelector};
+use crate::wac::{generate_wac_in, GeneratedAdapter, WacOutput, PATH_PLACEHOLDER};
+
+/// Package name [`splice_bytes`] and the request constructors write
+/// at the top of the generated WAC. Matches the CLI's `--package`
+/// default.
+const DEFAULT_PACKAGE_NAME: &str = "example:composition";
+
+/// Format version written by [`Bundle::save`]. Bumped whenever the
+/// manifest layout changes incompatibly.
+const MANIFEST_VERSION: u32 = 1;
 
 // ── Splice request / output ────────────────────────────────────────────────
 
-/// Inputs to [`splice`].
+/// Inputs to [`splice`]. Start from [`SpliceRequest::new`] and set
+/// the fields that differ from its defaults.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct SpliceRequest {
     /// Path to the pre-composed Wasm component to splice middleware into.
     /// The file is read from disk and parsed into a composition graph.
     pub composition_wasm: PathBuf,
 
-    /// Splice rules in YAML format. The caller is responsible for
-    /// loading the YAML — splicer will not read it from disk.
-    pub rules_yaml: String,
+    /// Splice rules to apply, in order. Build them in code with
+    /// [`crate::types::Rule`], or parse a YAML config with
+    /// [`crate::parse_yaml`]. Validated again by `splice()`, so
+    /// hand-constructed [`SpliceRule`]s get the same checks.
+    pub rules: Vec<SpliceRule>,
 
     /// Package name written to the top of the generated WAC source
     /// (e.g. `"example:composition"`).
@@ -43,6 +68,46 @@ pub struct SpliceRequest {
     /// warnings and `splice()` succeeds. When `false`, the function
     /// returns `Err` if any contract check fails.
     pub skip_type_check: bool,
+
+    /// Record a [`Provenance`] in [`Bundle::provenance`], so
+    /// [`Bundle::to_wasm`] embeds a `splicer-provenance` custom section
+    /// in the composed output. When the input already carries
+    /// provenance from an earlier splice, the new record extends it.
+    pub provenance: bool,
+
+    /// What to do when a rule would inject middleware the composition
+    /// already carries from an earlier splice (recognized through its
+    /// provenance, or through instance names when it has none). When
+    /// `false` the injection is skipped, so re-running the same config
+    /// on its own output is a no-op; when `true` `splice()` fails with
+    /// [`Error::AlreadyInjected`].
+    pub reject_existing: bool,
+
+    /// Wire every import of every instance explicitly instead of
+    /// ending each instantiation with WAC's `...`. Imports nothing in
+    /// the composition provides become top-level `import`s, typed by
+    /// WIT packages written to `splits_dir`, so the composed
+    /// component's imports are all spelled out in [`Bundle::wac`].
+    /// Every injected middleware needs a `path:`.
+    pub strict_wiring: bool,
+}
+
+impl SpliceRequest {
+    /// Splice `rules` into the composition at `composition_wasm`,
+    /// writing splits to `./splits` under the package
+    /// `example:composition`, with every check on and no provenance.
+    pub fn new(composition_wasm: impl Into<PathBuf>, rules: Vec<SpliceRule>) -> Self {
+        Self {
+            composition_wasm: composition_wasm.into(),
+            rules,
+            package_name: DEFAULT_PACKAGE_NAME.to_string(),
+            splits_dir: PathBuf::from(PATH_TO_SPLITS),
+            skip_type_check: false,
+            provenance: false,
+            reject_existing: false,
+            strict_wiring: false,
+        }
+    }
 }
 
 // ── Compose request ────────────────────────────────────────────────────────
@@ -60,8 +125,10 @@ pub struct ComponentInput {
     pub path: PathBuf,
 }
 
-/// Inputs to [`compose`].
+/// Inputs to [`compose`]. Start from [`ComposeRequest::new`] and set
+/// the fields that differ from its defaults.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct ComposeRequest {
     /// Two or more components to compose. Their import/export
     /// surfaces are matched automatically and the resulting
@@ -70,19 +137,97 @@ pub struct ComposeRequest {
 
     /// Package name written to the top of the generated WAC source.
     pub package_name: String,
+
+    /// Splice rules applied directly to the synthesized composition —
+    /// the one-shot equivalent of [`compose`] followed by [`splice`],
+    /// without splitting the composition that was just built. Rules
+    /// match nodes by their component name (alias or filename stem).
+    /// Leave empty for a plain composition.
+    pub rules: Vec<SpliceRule>,
+
+    /// Directory where generated tier-1 adapter components and
+    /// materialized builtins are written. Required when `rules` is
+    /// non-empty; created if it does not exist.
+    pub splits_dir: Option<PathBuf>,
+
+    /// As [`SpliceRequest::skip_type_check`]. Has no effect when
+    /// `rules` is empty.
+    pub skip_type_check: bool,
+
+    /// As [`SpliceRequest::provenance`]. Has no effect when `rules` is
+    /// empty.
+    pub provenance: bool,
+
+    /// As [`SpliceRequest::strict_wiring`]. Requires a `splits_dir`,
+    /// even without `rules`.
+    pub strict_wiring: bool,
+}
+
+impl ComposeRequest {
+    /// A plain composition of `components` under the package
+    /// `example:composition`: no rules and no splits directory.
+    pub fn new(components: Vec<ComponentInput>) -> Self {
+        Self {
+            components,
+            package_name: DEFAULT_PACKAGE_NAME.to_string(),
+            rules: Vec::new(),
+            splits_dir: None,
+            skip_type_check: false,
+            provenance: false,
+            strict_wiring: false,
+        }
+    }
+}
+
+// ── Unsplice request ───────────────────────────────────────────────────────
+
+/// Inputs to [`unsplice`]. Start from [`UnspliceRequest::new`] and
+/// set the fields that differ from its defaults.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct UnspliceRequest {
+    /// Path to a composition splicer produced earlier.
+    pub composition_wasm: PathBuf,
+
+    /// Which injected middleware to remove.
+    pub remove: MiddlewareSelector,
+
+    /// Package name written to the top of the generated WAC source.
+    pub package_name: String,
+
+    /// Directory where the remaining sub-components are written.
+    /// Created if it does not exist.
+    pub splits_dir: PathBuf,
+}
+
+impl UnspliceRequest {
+    /// Remove the middleware `remove` selects from the composition at
+    /// `composition_wasm`, writing splits to `./splits` under
+    /// the package `example:composition`.
+    pub fn new(composition_wasm: impl Into<PathBuf>, remove: MiddlewareSelector) -> Self {
+        Self {
+            composition_wasm: composition_wasm.into(),
+            remove,
+            package_name: DEFAULT_PACKAGE_NAME.to_string(),
+            splits_dir: PathBuf::from(PATH_TO_SPLITS),
+        }
+    }
 }
 
 // ── Bundle: shared output of splice and compose ────────────────────────────
 
-/// Output of [`splice`] and [`compose`]: the generated WAC source,
+/// Output of [`splice`], [`compose`] and [`unsplice`]: the generated WAC source,
 /// the dep map it references, contract diagnostics, and any tier-1
 /// adapter components splicer wrote to disk. Most callers reach for
 /// [`Bundle::to_wasm`] to go straight to a composed component.
-#[derive(Debug, Clone)]
+///
+/// A bundle serializes to a JSON manifest ([`Bundle::save`]) that can
+/// be reloaded with [`Bundle::load`] and composed later — in a later
+/// pipeline step, or on another machine that has the same packages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bundle {
-    /// The generated WAC source. Pass to [`Bundle::to_wasm`], or
-    /// write it to disk and run `wac compose` yourself.
-    pub wac: String,
+    /// The generated WAC source; see [`Bundle::wac`].
+    wac: String,
 
     /// Per-dependency `package_key → wasm_path` map. Keys are
     /// fully-qualified WAC package keys (e.g. `"my:srv-a"`); the
@@ -94,13 +239,13 @@ pub struct Bundle {
 
     /// Contract validation diagnostics. On `Ok` from `splice`, holds
     /// only `Ok`/`Warn` entries; `Error` entries fail `splice` unless
-    /// `skip_type_check` was set. `compose` does not run contract
-    /// checks, so its bundles ship empty.
+    /// `skip_type_check` was set. `compose` only runs contract checks
+    /// for its `rules`, so plain compositions ship empty.
     pub diagnostics: Vec<ContractResult>,
 
     /// Tier-1 adapter components splicer generated. Populated by
-    /// `splice` when a rule wraps a tier-1 type-erased middleware;
-    /// `compose` leaves it empty. Each entry carries the on-disk
+    /// `splice` (or `compose` with rules) when a rule wraps a tier-1
+    /// type-erased middleware. Each entry carries the on-disk
     /// path, the wrapped middleware name, the target interface, and
     /// which `splicer:tier1/*` hook interfaces it exports.
     ///
@@ -108,9 +253,57 @@ pub struct Bundle {
     /// adapter package key — this field is for callers who want the
     /// metadata too.
     pub generated_adapters: Vec<GeneratedAdapter>,
+
+    /// Content hash (`sha256:<hex>`) of every package in
+    /// [`Bundle::wac_deps`], taken when the bundle was produced.
+    /// [`Bundle::verify`] checks the packages against these, so a
+    /// manifest loaded after its splits were regenerated is rejected
+    /// instead of silently composing different bytes.
+    pub dep_hashes: BTreeMap<String, String>,
+
+    /// What splicer did to produce this bundle, when the request asked
+    /// for it. [`Bundle::to_wasm`] embeds it as a `splicer-provenance`
+    /// custom section; set to `None` to compose without it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+
+    /// Where the splits / adapters / builtins named in `wac_deps` live.
+    /// On-disk for [`splice`] and [`compose`]; an in-memory package map
+    /// for [`splice_bytes`], whose `wac_deps` paths are only keys.
+    #[serde(skip)]
+    artifacts: Artifacts,
+
+    /// The plan [`Bundle::wac`] was rendered from. Not part of the
+    /// manifest, and dropped by [`Bundle::set_wac`]; without it a
+    /// bundle composes from its WAC text.
+    #[serde(skip)]
+    plan: Option<CompositionPlan>,
+}
+
+/// On-disk form of a [`Bundle`]: the bundle's fields plus a format
+/// version.
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    #[serde(flatten)]
+    bundle: Bundle,
 }
 
 impl Bundle {
+    /// The generated WAC source. Pass to [`Bundle::to_wasm`], or
+    /// write it to disk and run `wac compose` yourself.
+    pub fn wac(&self) -> &str {
+        &self.wac
+    }
+
+    /// Replace the WAC source with `wac`. [`Bundle::to_wasm`] then
+    /// composes `wac`, and [`Bundle::plan`] becomes `None`: the plan
+    /// no longer describes the composition.
+    pub fn set_wac(&mut self, wac: impl Into<String>) {
+        self.wac = wac.into();
+        self.plan = None;
+    }
+
     /// Format a `wac compose <wac_path> --dep ...` shell command,
     /// where `wac_path` is where you wrote [`Bundle::wac`] to disk.
     pub fn wac_compose_cmd(&self, wac_path: &str) -> String {
@@ -120,9 +313,141 @@ impl Bundle {
     /// Compose this bundle into a single Wasm component, in-process.
     /// Equivalent to `wac compose` on [`Bundle::wac`] with every dep
     /// from [`Bundle::wac_deps`]; the result is wasmparser-validated
-    /// before return.
+    /// before return. Embeds [`Bundle::provenance`] when set.
     pub fn to_wasm(&self) -> Result<Vec<u8>> {
-        compose_wac(&self.wac, &self.wac_deps)
+        self.compose_from(&BundlePackages(self))
+    }
+
+    /// [`Bundle::to_wasm`], looking up packages the bundle doesn't
+    /// carry — e.g. middleware injected by name only, with no `path:`
+    /// — in `fallback`.
+    pub fn to_wasm_with(&self, fallback: &dyn PackageSource) -> Result<Vec<u8>> {
+        let own: &dyn PackageSource = &BundlePackages(self);
+        self.compose_from(&[own, fallback])
+    }
+
+    /// The typed plan splicer generated [`Bundle::wac`] from — each
+    /// instance tagged with the rule that produced it. `None` for a
+    /// bundle loaded from a manifest, or whose WAC was replaced with
+    /// [`Bundle::set_wac`].
+    pub fn plan(&self) -> Option<&CompositionPlan> {
+        self.plan.as_ref()
+    }
+
+    fn compose_from(&self, source: &dyn PackageSource) -> Result<Vec<u8>> {
+        let composed = match &self.plan {
+            // Build the graph straight from the plan; `set_wac` drops
+            // it, so the caller's text is composed instead.
+            Some(plan) => compose_plan_with(plan, source)?,
+            None => compose_wac_with(&self.wac, source)?,
+        };
+        match &self.provenance {
+            Some(provenance) => provenance.embed(composed).map_err(Error::WacEncode),
+            None => Ok(composed),
+        }
+    }
+
+    /// Check every package in [`Bundle::wac_deps`] against its
+    /// recorded [`Bundle::dep_hashes`] entry.
+    ///
+    /// Fails with [`Error::StaleDependency`] when a package's bytes
+    /// changed, [`Error::Io`] when one can't be read, and
+    /// [`Error::Manifest`] when a package has no recorded hash.
+    pub fn verify(&self) -> Result<()> {
+        for (key, path) in &self.wac_deps {
+            let expected = self.dep_hashes.get(key).ok_or_else(|| {
+                Error::Manifest(anyhow::anyhow!(
+                    "no content hash recorded for package '{key}'"
+                ))
+            })?;
+            let actual = content_hash(&read_dep(&self.artifacts, key, path)?);
+            if &actual != expected {
+                return Err(Error::StaleDependency {
+                    package: key.clone(),
+                    path: path.clone(),
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Write this bundle to `manifest_path` as a JSON manifest, for
+    /// [`Bundle::load`] to pick up later.
+    ///
+    /// Package and adapter paths under the manifest's directory are
+    /// stored relative to it, so a directory holding the manifest and
+    /// its splits can be moved or copied to another machine as a
+    /// unit. Paths elsewhere are stored as-is.
+    ///
+    /// Fails with [`Error::Io`] when the manifest can't be written,
+    /// and [`Error::Manifest`] for bundles whose packages only exist
+    /// in memory.
+    pub fn save(&self, manifest_path: &Path) -> Result<()> {
+        if self.artifacts.is_in_memory() {
+            return Err(Error::Manifest(anyhow::anyhow!(
+                "bundle packages are held in memory and have no paths to record"
+            )));
+        }
+        let base = manifest_dir(manifest_path, true)?;
+
+        let mut bundle = self.clone();
+        for path in bundle.wac_deps.values_mut() {
+            *path = relative_to(path, &base);
+        }
+        for adapter in &mut bundle.generated_adapters {
+            let path = relative_to(Path::new(&adapter.adapter_path), &base);
+            adapter.adapter_path = path.to_string_lossy().into_owned();
+        }
+
+        let json = serde_json::to_string_pretty(&Manifest {
+            version: MANIFEST_VERSION,
+            bundle,
+        })
+        .context("Failed to serialize bundle manifest")
+        .map_err(Error::Manifest)?;
+        std::fs::write(manifest_path, json).map_err(|error| Error::Io {
+            path: manifest_path.to_path_buf(),
+            error,
+        })
+    }
+
+    /// Load a bundle written by [`Bundle::save`] and [`Bundle::verify`]
+    /// its packages, ready for [`Bundle::to_wasm`]. Relative paths in
+    /// the manifest resolve against the manifest's directory.
+    ///
+    /// Fails with [`Error::Io`] when the manifest can't be read,
+    /// [`Error::Manifest`] when it can't be parsed or was written by
+    /// an incompatible splicer, and any error from [`Bundle::verify`].
+    pub fn load(manifest_path: &Path) -> Result<Bundle> {
+        let json = std::fs::read_to_string(manifest_path).map_err(|error| Error::Io {
+            path: manifest_path.to_path_buf(),
+            error,
+        })?;
+        let Manifest {
+            version,
+            mut bundle,
+        } = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse '{}'", manifest_path.display()))
+            .map_err(Error::Manifest)?;
+        if version != MANIFEST_VERSION {
+            return Err(Error::Manifest(anyhow::anyhow!(
+                "unsupported manifest version {version} (expected {MANIFEST_VERSION})"
+            )));
+        }
+
+        let base = manifest_dir(manifest_path, false)?;
+        for path in bundle.wac_deps.values_mut() {
+            *path = base.join(&*path);
+        }
+        for adapter in &mut bundle.generated_adapters {
+            let path = base.join(&adapter.adapter_path);
+            adapter.adapter_path = path.to_string_lossy().into_owned();
+        }
+
+        bundle.verify()?;
+        Ok(bundle)
     }
 }
 
@@ -131,79 +456,363 @@ impl Bundle {
 /// Splice middleware into a pre-composed Wasm component.
 ///
 /// Equivalent to the `splicer splice` CLI subcommand. Reads
-/// `req.composition_wasm`, parses `req.rules_yaml`, splits the
+/// `req.composition_wasm`, validates `req.rules`, splits the
 /// composition into sub-components under `req.splits_dir`, runs
 /// contract validation on the configured middleware, generates an
 /// adapter component for any tier-1 type-erased middleware, and
 /// returns the resulting WAC source.
 ///
 /// Returns `Err` when:
-/// - The composition wasm cannot be read or parsed.
-/// - The YAML rules are malformed.
-/// - The splitter fails to write split sub-components.
+/// - The composition wasm cannot be read ([`Error::Io`]) or parsed
+///   ([`Error::GraphParse`]).
+/// - The rules fail validation ([`Error::Config`]; see
+///   [`crate::lowlevel::validate_rules`]).
+/// - The splitter fails to write split sub-components ([`Error::Split`]).
+/// - A builtin can't be resolved ([`Error::Builtin`]).
+/// - A tier-1 adapter can't be generated ([`Error::UnsupportedAdapter`],
+///   or [`Error::WacGenerate`] for other failures).
 /// - Contract validation produces an `Error` diagnostic and
-///   `req.skip_type_check` is `false`.
+///   `req.skip_type_check` is `false` ([`Error::Contract`]).
 pub fn splice(req: SpliceRequest) -> Result<Bundle> {
     let SpliceRequest {
         composition_wasm,
-        rules_yaml,
+        rules,
         package_name,
         splits_dir,
         skip_type_check,
+        provenance,
+        reject_existing,
+        strict_wiring,
     } = req;
 
-    let mut cfg = parse_yaml(&rules_yaml).context("Failed to parse splice rules YAML")?;
-
-    let bytes = std::fs::read(&composition_wasm).with_context(|| {
-        format!(
-            "Failed to read composition wasm: {}",
-            composition_wasm.display()
-        )
-    })?;
-    let graph = parse_component(&bytes).with_context(|| {
-        format!(
-            "Failed to parse composition graph from: {}",
-            composition_wasm.display()
-        )
+    let bytes = std::fs::read(&composition_wasm).map_err(|error| Error::Io {
+        path: composition_wasm.clone(),
+        error,
     })?;
 
     let splits_dir_str = splits_dir
         .to_str()
         .ok_or_else(|| {
-            anyhow::anyhow!(
+            Error::Split(anyhow::anyhow!(
                 "splits_dir contains non-UTF-8 bytes: {}",
                 splits_dir.display()
-            )
+            ))
         })?
         .to_string();
-    let (splits_path, shim_comps) =
-        split_out_composition(&composition_wasm, &Some(splits_dir_str))?;
+    std::fs::create_dir_all(&splits_dir).map_err(|error| Error::Io {
+        path: splits_dir.clone(),
+        error,
+    })?;
+
+    let mut bundle = splice_in(
+        &bytes,
+        rules,
+        &package_name,
+        &splits_dir_str,
+        SpliceFlags {
+            skip_type_check,
+            provenance,
+            reject_existing,
+            strict_wiring,
+        },
+        Artifacts::on_disk(),
+    )?;
+    canonicalize_wac_deps(&mut bundle.wac_deps)?;
+    Ok(bundle)
+}
+
+/// Splice middleware into a pre-composed Wasm component entirely in
+/// memory, returning the composed component's bytes.
+///
+/// The in-memory counterpart of [`splice`] followed by
+/// [`Bundle::to_wasm`]: splits, generated tier-1 adapters and
+/// materialized builtins are kept in an in-memory package map rather
+/// than written under a splits directory, and splicer's on-disk
+/// adapter and builtin caches are neither read nor filled, so no temp
+/// directories are created and splicer writes nothing to disk.
+/// Middleware referenced by `path`, and builtins under
+/// `SPLICER_BUILTINS_DIR`, are still read from disk; other builtins
+/// are pulled from the registry on every call.
+///
+/// Returns `Err` under the same conditions as [`splice`] (with
+/// type-check errors never skipped), plus any failure to compose the
+/// result.
+pub fn splice_bytes(composition: &[u8], rules: &[SpliceRule]) -> Result<Vec<u8>> {
+    let bundle = splice_in(
+        composition,
+        rules.to_vec(),
+        DEFAULT_PACKAGE_NAME,
+        IN_MEMORY_ROOT,
+        SpliceFlags::default(),
+        Artifacts::in_memory(),
+    )?;
+    bundle.to_wasm()
+}
+
+/// The boolean switches of a [`SpliceRequest`], as threaded through
+/// [`splice_in`]. [`splice_bytes`] runs with all of them off.
+#[derive(Clone, Copy, Default)]
+struct SpliceFlags {
+    skip_type_check: bool,
+    provenance: bool,
+    reject_existing: bool,
+    strict_wiring: bool,
+}
+
+/// Shared body of [`splice`] and [`splice_bytes`]: validate the rules,
+/// drop injections the composition already carries, split
+/// `composition` into `splits_dir` via `artifacts`, materialize
+/// builtins, splice the nested compositions rules lead into, generate
+/// the WAC, and gate on contract errors.
+fn splice_in(
+    composition: &[u8],
+    mut cfg: Vec<SpliceRule>,
+    package_name: &str,
+    splits_dir: &str,
+    flags: SpliceFlags,
+    mut artifacts: Artifacts,
+) -> Result<Bundle> {
+    validate_rules(&cfg).map_err(Error::Config)?;
+
+    let prior = crate::read_provenance(composition)?;
+    skip_existing(
+        &mut cfg,
+        &*parse_graph(composition, &artifacts)?,
+        prior.as_ref(),
+        flags.reject_existing,
+    )?;
+
+    let nesting = split_into(composition, splits_dir, &mut artifacts)
+        .map_err(|e| Error::classify(e, Error::Split))?;
+    let mut graph = take_graph(composition, &artifacts)?;
+    scope_to_root(&mut graph, &nesting);
 
-    // Materialize builtin middleware bytes to disk now that splits_dir
-    // is established. Stamps `injection.path` so the rest of the
+    // Materialize builtin middleware bytes now that splits_dir is
+    // established. Stamps `injection.path` so the rest of the
     // pipeline (contract validation, tier-1 detection, adapter
     // generation, WAC) treats builtins as ordinary path-backed
     // middleware.
-    materialize_builtins(&mut cfg, std::path::Path::new(&splits_path))?;
+    let requested_rules = flags.provenance.then(|| cfg.clone());
+    materialize_builtins(&mut cfg, Path::new(splits_dir), &mut artifacts)?;
 
-    let out = generate_wac(shim_comps, &splits_path, &graph, &cfg, None, &package_name)?;
+    let (own_rules, nested_rules) = partition_rules(cfg.clone());
+    let nested = splice_nested(
+        &graph,
+        &nesting,
+        nested_rules,
+        package_name,
+        splits_dir,
+        &flags,
+        &mut artifacts,
+    )?;
 
-    if !skip_type_check {
-        for diag in &out.diagnostics {
-            if let ContractResult::Error(msg) = diag {
-                anyhow::bail!("Contract type-check error: {msg}");
-            }
-        }
+    let mut out = generate_wac_in(
+        nesting.shims,
+        splits_dir,
+        &graph,
+        &own_rules,
+        None,
+        package_name,
+        &mut artifacts,
+    )
+    .map_err(|e| Error::classify(e, Error::WacGenerate))?;
+    out.diagnostics.extend(nested.diagnostics);
+    out.generated_adapters.extend(nested.generated_adapters);
+    if flags.strict_wiring {
+        wire_strictly(&mut out, splits_dir, &mut artifacts)?;
     }
+    drop_placeholder_deps(&mut out.wac_deps);
+
+    if !flags.skip_type_check {
+        check_contracts(&out.diagnostics)?;
+    }
+
+    let provenance = requested_rules
+        .map(|rules| {
+            let record = Provenance::record(
+                Some(composition),
+                rules,
+                &cfg,
+                &out.generated_adapters,
+                &artifacts,
+            )?;
+            Ok(match prior {
+                Some(prior) => record.carry_over(prior),
+                None => record,
+            })
+        })
+        .transpose()
+        .map_err(Error::WacGenerate)?;
+    let dep_hashes = hash_deps(&out.wac_deps, &artifacts)?;
+    Ok(Bundle {
+        wac: out.wac,
+        plan: Some(out.plan),
+        wac_deps: out.wac_deps,
+        diagnostics: out.diagnostics,
+        generated_adapters: out.generated_adapters,
+        dep_hashes,
+        provenance,
+        artifacts,
+    })
+}
+
+/// What splicing the nested compositions of a composition produced,
+/// for the outer splice to report as its own.
+#[derive(Default)]
+struct NestedSplices {
+    diagnostics: Vec<ContractResult>,
+    generated_adapters: Vec<GeneratedAdapter>,
+}
+
+/// Apply `rules` — grouped by the instance of `graph` whose nested
+/// composition they lead into (see [`partition_rules`]) — to each of
+/// those compositions, recursively, composing each result over the
+/// composition's split so `graph`'s WAC instantiates it. Nested splits
+/// go in a directory per instance under `splits_dir`.
+fn splice_nested(
+    graph: &CompositionGraph,
+    nesting: &Nesting,
+    rules: BTreeMap<String, Vec<SpliceRule>>,
+    package_name: &str,
+    splits_dir: &str,
+    flags: &SpliceFlags,
+    artifacts: &mut Artifacts,
+) -> Result<NestedSplices> {
+    let mut nested = NestedSplices::default();
+    for (name, rules) in rules {
+        let Some(split) = nested_split(graph, nesting, &name)
+            .map_err(|e| Error::classify(e, Error::WacGenerate))?
+        else {
+            continue;
+        };
+        let path = SplitPaths::load(splits_dir, artifacts)
+            .map_err(Error::Split)?
+            .path(split);
+        let bytes = artifacts
+            .read(&path)
+            .with_context(|| format!("Failed to read nested composition `{name}`"))
+            .map_err(Error::Split)?
+            .into_owned();
+        let inner = splice_in(
+            &bytes,
+            rules,
+            package_name,
+            &nested_splits_dir(splits_dir, &name),
+            *flags,
+            artifacts.clone(),
+        )?;
+        let composed = inner.to_wasm()?;
+        nested.diagnostics.extend(inner.diagnostics);
+        nested.generated_adapters.extend(inner.generated_adapters);
+        *artifacts = inner.artifacts;
+        rewrite_split(splits_dir, split, composed, artifacts)
+            .with_context(|| format!("Failed to write spliced nested composition `{name}`"))
+            .map_err(Error::Split)?;
+    }
+    Ok(nested)
+}
+
+/// The composition graph of `composition`, parsed through `artifacts`'
+/// cache so splitting it afterwards doesn't parse it again.
+fn parse_graph(composition: &[u8], artifacts: &Artifacts) -> Result<Arc<CompositionGraph>> {
+    artifacts
+        .parsed()
+        .graph(composition)
+        .map_err(Error::GraphParse)
+}
+
+/// The composition graph of `composition`, taken out of `artifacts`'
+/// cache to be rewritten in place.
+fn take_graph(composition: &[u8], artifacts: &Artifacts) -> Result<CompositionGraph> {
+    artifacts
+        .parsed()
+        .take_graph(composition)
+        .map_err(Error::GraphParse)
+}
+
+/// Rebuild a composition splicer produced without some (or all) of
+/// the middleware it injected.
+///
+/// Equivalent to the `splicer unsplice` CLI subcommand. Middleware is
+/// found through the composition's embedded [`crate::types::Provenance`]
+/// when present; without it only [`MiddlewareSelector::Named`] and,
+/// for tier-1 middleware, [`MiddlewareSelector::All`] work. Every
+/// consumer of a removed instance is rewired to that instance's own
+/// upstream, and the remaining instances are split into
+/// `req.splits_dir` and re-emitted as WAC. The returned bundle carries
+/// the input's provenance minus the removed middleware.
+///
+/// Returns `Err` when:
+/// - The composition wasm cannot be read ([`Error::Io`]) or parsed
+///   ([`Error::GraphParse`]), or its provenance can't be decoded
+///   ([`Error::Provenance`]).
+/// - No middleware matches `req.remove`, or a removed instance
+///   provides an interface something else still imports
+///   ([`Error::Unsplice`]).
+/// - Splitting or WAC generation fails, as for [`splice`].
+pub fn unsplice(req: UnspliceRequest) -> Result<Bundle> {
+    let UnspliceRequest {
+        composition_wasm,
+        remove,
+        package_name,
+        splits_dir,
+    } = req;
+
+    let bytes = std::fs::read(&composition_wasm).map_err(|error| Error::Io {
+        path: composition_wasm.clone(),
+        error,
+    })?;
+    let splits_dir_str = splits_dir
+        .to_str()
+        .ok_or_else(|| {
+            Error::Split(anyhow::anyhow!(
+                "splits_dir contains non-UTF-8 bytes: {}",
+                splits_dir.display()
+            ))
+        })?
+        .to_string();
+    std::fs::create_dir_all(&splits_dir).map_err(|error| Error::Io {
+        path: splits_dir.clone(),
+        error,
+    })?;
+
+    let mut artifacts = Artifacts::on_disk();
+    let provenance = crate::read_provenance(&bytes)?;
+    let removed = unsplice_graph::select(
+        &*parse_graph(&bytes, &artifacts)?,
+        provenance.as_ref(),
+        &remove,
+    )
+    .map_err(Error::Unsplice)?;
+
+    let nesting = split_into(&bytes, &splits_dir_str, &mut artifacts)
+        .map_err(|e| Error::classify(e, Error::Split))?;
+    let mut graph = take_graph(&bytes, &artifacts)?;
+    unsplice_graph::strip(&mut graph, &removed).map_err(Error::Unsplice)?;
+    scope_to_root(&mut graph, &nesting);
+    let out = generate_wac_in(
+        nesting.shims,
+        &splits_dir_str,
+        &graph,
+        &[],
+        None,
+        &package_name,
+        &mut artifacts,
+    )
+    .map_err(|e| Error::classify(e, Error::WacGenerate))?;
 
     let mut wac_deps = out.wac_deps;
     canonicalize_wac_deps(&mut wac_deps)?;
-
+    let dep_hashes = hash_deps(&wac_deps, &artifacts)?;
     Ok(Bundle {
         wac: out.wac,
+        plan: Some(out.plan),
         wac_deps,
         diagnostics: out.diagnostics,
         generated_adapters: out.generated_adapters,
+        dep_hashes,
+        provenance: provenance.and_then(|p| unsplice_graph::retain_provenance(p, &removed)),
+        artifacts,
     })
 }
 
@@ -211,25 +820,70 @@ pub fn splice(req: SpliceRequest) -> Result<Bundle> {
 ///
 /// Equivalent to the `splicer compose` CLI subcommand. Reads each
 /// component file, builds a composition graph by matching their
-/// import/export surfaces, and emits the WAC source.
+/// import/export surfaces, and emits the WAC source. When
+/// `req.rules` is non-empty they are applied to that graph as
+/// [`splice`] would, using each component's original `.wasm` in place
+/// of a split.
 ///
 /// Returns `Err` when:
-/// - Two `ComponentInput`s resolve to the same name.
-/// - A component file cannot be read.
-/// - Graph synthesis fails (e.g. unresolved imports, cycles, etc.).
+/// - The rules fail validation, or are given without a `splits_dir`
+///   ([`Error::Config`]).
+/// - Two `ComponentInput`s resolve to the same name ([`Error::Compose`]).
+/// - A component file cannot be read ([`Error::Io`]).
+/// - Graph synthesis fails (e.g. unresolved imports, cycles, etc.;
+///   [`Error::Compose`]).
+/// - Applying the rules fails, under the same conditions as [`splice`].
 pub fn compose(req: ComposeRequest) -> Result<Bundle> {
     let ComposeRequest {
         components,
         package_name,
+        mut rules,
+        splits_dir,
+        skip_type_check,
+        provenance,
+        strict_wiring,
     } = req;
 
+    validate_rules(&rules).map_err(Error::Config)?;
+    let splits_dir_str = match &splits_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir).map_err(|error| Error::Io {
+                path: dir.clone(),
+                error,
+            })?;
+            dir.to_str()
+                .ok_or_else(|| {
+                    Error::Config(anyhow::anyhow!(
+                        "splits_dir contains non-UTF-8 bytes: {}",
+                        dir.display()
+                    ))
+                })?
+                .to_string()
+        }
+        None if !rules.is_empty() => {
+            return Err(Error::Config(anyhow::anyhow!(
+                "composing with splice rules requires a `splits_dir` for generated \
+                 adapters and builtins"
+            )));
+        }
+        None if strict_wiring => {
+            return Err(Error::Config(anyhow::anyhow!(
+                "strict wiring requires a `splits_dir` for the WIT packages its imports \
+                 are typed by"
+            )));
+        }
+        None => String::new(),
+    };
+
     // Resolve aliases (or filename stems), read each file's bytes,
     // and check for name conflicts before any composition work.
     let mut resolved: Vec<(String, PathBuf, Vec<u8>)> = Vec::with_capacity(components.len());
     for ComponentInput { alias, path } in &components {
         let name = alias.clone().unwrap_or_else(|| filename_from_path(path));
-        let bytes = std::fs::read(path)
-            .with_context(|| format!("Failed to read Wasm component: {}", path.display()))?;
+        let bytes = std::fs::read(path).map_err(|error| Error::Io {
+            path: path.clone(),
+            error,
+        })?;
         resolved.push((name, path.clone(), bytes));
     }
 
@@ -239,7 +893,7 @@ pub fn compose(req: ComposeRequest) -> Result<Bundle> {
         let mut seen: HashMap<&str, &PathBuf> = HashMap::new();
         for (name, path, _) in &resolved {
             if let Some(prev) = seen.insert(name.as_str(), path) {
-                anyhow::bail!(
+                return Err(Error::Compose(anyhow::anyhow!(
                     "Name conflict: '{}' and '{}' both resolve to the name '{}'.\n\
                      Use aliases to disambiguate, e.g.:\n\
                      \t{}0={} {}1={}",
@@ -250,30 +904,57 @@ pub fn compose(req: ComposeRequest) -> Result<Bundle> {
                     prev.display(),
                     name,
                     path.display(),
-                );
+                )));
             }
         }
     }
 
-    let (graph, node_paths) = build_graph_from_components(&resolved)?;
+    let mut artifacts = Artifacts::on_disk();
+    let (graph, node_paths) =
+        build_graph_in(&resolved, artifacts.parsed()).map_err(Error::Compose)?;
+
+    let requested_rules = (provenance && !rules.is_empty()).then(|| rules.clone());
+    materialize_builtins(&mut rules, Path::new(&splits_dir_str), &mut artifacts)?;
 
-    let out = generate_wac(
+    let mut out = generate_wac_in(
         HashMap::new(),
-        "",
+        &splits_dir_str,
         &graph,
-        &[],
+        &rules,
         Some(&node_paths),
         &package_name,
-    )?;
+        &mut artifacts,
+    )
+    .map_err(|e| Error::classify(e, Error::WacGenerate))?;
+    if strict_wiring {
+        wire_strictly(&mut out, &splits_dir_str, &mut artifacts)?;
+    }
+    drop_placeholder_deps(&mut out.wac_deps);
+
+    if !skip_type_check {
+        check_contracts(&out.diagnostics)?;
+    }
+
+    let provenance = requested_rules
+        .map(|requested| {
+            Provenance::record(None, requested, &rules, &out.generated_adapters, &artifacts)
+        })
+        .transpose()
+        .map_err(Error::WacGenerate)?;
 
     let mut wac_deps = out.wac_deps;
     canonicalize_wac_deps(&mut wac_deps)?;
 
+    let dep_hashes = hash_deps(&wac_deps, &artifacts)?;
     Ok(Bundle {
         wac: out.wac,
+        plan: Some(out.plan),
         wac_deps,
         diagnostics: out.diagnostics,
         generated_adapters: out.generated_adapters,
+        dep_hashes,
+        provenance,
+        artifacts,
     })
 }
 
@@ -286,57 +967,202 @@ pub fn compose(req: ComposeRequest) -> Result<Bundle> {
 /// Equivalent to [`Bundle::to_wasm`] when called on a splicer-emitted
 /// bundle; expose this directly when you've assembled `wac` and
 /// `wac_deps` from somewhere other than [`splice`] / [`compose`].
+///
+/// Fails with [`Error::WacResolve`] when the WAC can't be parsed or a
+/// package can't be resolved, and [`Error::WacEncode`] when the
+/// resolved document can't be encoded into a valid component.
 pub fn compose_wac(wac: &str, wac_deps: &BTreeMap<String, PathBuf>) -> Result<Vec<u8>> {
-    let doc = Document::parse(wac).context("Failed to parse generated WAC source")?;
-    let keys = packages(&doc).context("Failed to discover packages from WAC")?;
-
-    // `disable_filesystem: true` means the resolver only consults
-    // `overrides`, so the first arg (the on-disk search base) is
-    // never read. Hardcoding "." just keeps the type happy.
-    let overrides: HashMap<String, PathBuf> = wac_deps.clone().into_iter().collect();
-    let resolver = FileSystemPackageResolver::new(Path::new("."), overrides, true);
-    let pkgs = resolver
-        .resolve(&keys)
-        .context("Failed to resolve WAC packages")?;
+    compose_wac_with(wac, wac_deps)
+}
+
+/// In-memory counterpart of [`compose_wac`]: every package the WAC
+/// references is looked up in `package_bytes` (keyed by the same
+/// fully-qualified WAC package keys as [`Bundle::wac_deps`]) instead
+/// of being read from disk.
+pub fn compose_wac_bytes(wac: &str, package_bytes: &BTreeMap<String, Vec<u8>>) -> Result<Vec<u8>> {
+    compose_wac_with(wac, package_bytes)
+}
+
+/// [`compose_wac`] with every package the WAC references looked up in
+/// `source` — in-memory bytes, search directories, a local OCI cache,
+/// or several of those in turn (see [`crate::packages`]).
+///
+/// Fails like [`compose_wac`]; a package no source provides is an
+/// [`Error::WacResolve`].
+pub fn compose_wac_with(wac: &str, source: &dyn PackageSource) -> Result<Vec<u8>> {
+    let doc = Document::parse(wac)
+        .context("Failed to parse generated WAC source")
+        .map_err(Error::WacResolve)?;
+    let keys = packages(&doc)
+        .context("Failed to discover packages from WAC")
+        .map_err(Error::WacResolve)?;
+
+    let pkgs = keys
+        .into_iter()
+        .map(|(key, _span)| {
+            let bytes = source
+                .package(key.name, key.version)
+                .with_context(|| format!("Failed to load WAC package `{key}`"))?
+                .ok_or_else(|| anyhow::anyhow!("no package found for WAC package `{key}`"))?;
+            Ok((key, bytes))
+        })
+        .collect::<anyhow::Result<_>>()
+        .context("Failed to resolve WAC packages")
+        .map_err(Error::WacResolve)?;
 
     let resolution = doc
         .resolve(pkgs)
-        .context("Failed to resolve WAC document")?;
-    let composed: Vec<u8> = resolution
-        .encode(EncodeOptions::default())
-        .context("Failed to encode composed component")?;
+        .context("Failed to resolve WAC document")
+        .map_err(Error::WacResolve)?;
+    let composed = resolution
+        .encode(encode_options())
+        .context("Failed to encode composed component")
+        .map_err(Error::WacEncode)?;
+    validate_composed(composed)
+}
+
+/// [`compose_wac_with`] for a typed plan: build its composition graph
+/// directly, with no WAC text to parse.
+fn compose_plan_with(plan: &CompositionPlan, source: &dyn PackageSource) -> Result<Vec<u8>> {
+    let graph = plan
+        .build_graph(source)
+        .context("Failed to build composition graph")
+        .map_err(Error::WacResolve)?;
+    let composed = graph
+        .encode(encode_options())
+        .context("Failed to encode composed component")
+        .map_err(Error::WacEncode)?;
+    validate_composed(composed)
+}
+
+/// Encoding options for both composition paths. They name splicer as
+/// the processor (wac-parser would otherwise name itself), so a plan
+/// and its rendered WAC compose to the same bytes.
+fn encode_options() -> EncodeOptions<'static> {
+    EncodeOptions {
+        processor: Some(Processor {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+        }),
+        ..EncodeOptions::default()
+    }
+}
 
+/// wasmparser-validate freshly composed bytes.
+fn validate_composed(composed: Vec<u8>) -> Result<Vec<u8>> {
     let mut validator = wasmparser::Validator::new_with_features(wasmparser::WasmFeatures::all());
     validator
         .validate_all(&composed)
-        .context("Composed component bytes failed wasmparser validation")?;
+        .context("Composed component bytes failed wasmparser validation")
+        .map_err(Error::WacEncode)?;
 
     Ok(composed)
 }
 
+/// Parse a YAML splice configuration (see `docs/splice-config.md`)
+/// into validated [`SpliceRule`]s, ready for [`SpliceRequest::rules`]
+/// or [`splice_bytes`]. Fails with [`Error::Config`].
+pub fn parse_yaml(yaml: &str) -> Result<Vec<SpliceRule>> {
+    config::parse_yaml(yaml).map_err(Error::Config)
+}
+
 // ── Helpers ────────────────────────────────────────────────────────────────
 
+/// Drop every injection in `rules` that `graph` already carries from
+/// an earlier splice (see [`unsplice_graph::is_injected`]), along with
+/// any rule left with nothing to inject — or, with `reject`, fail on
+/// the first one found. Fails with [`Error::Config`] when an
+/// injection's name is taken by an instance that can't be told apart
+/// from it.
+fn skip_existing(
+    rules: &mut Vec<SpliceRule>,
+    graph: &CompositionGraph,
+    prior: Option<&Provenance>,
+    reject: bool,
+) -> Result<()> {
+    for (rule_idx, rule) in rules.iter_mut().enumerate() {
+        let Some(interface) = rule.interface().map(str::to_string) else {
+            continue;
+        };
+        let Some(inject) = rule.inject_mut() else {
+            continue;
+        };
+        let mut kept = Vec::new();
+        for inj in std::mem::take(inject) {
+            let injected = unsplice_graph::is_injected(graph, prior, &inj.name, &interface)
+                .with_context(|| format!("rule {}", rule_idx + 1))
+                .map_err(Error::Config)?;
+            if !injected {
+                kept.push(inj);
+            } else if reject {
+                return Err(Error::AlreadyInjected {
+                    name: inj.name,
+                    interface,
+                });
+            } else {
+                eprintln!(
+                    "{}: rule {} — middleware '{}' is already injected on '{}'; skipping.",
+                    "WARN".yellow().bold(),
+                    rule_idx + 1,
+                    inj.name,
+                    interface
+                );
+            }
+        }
+        *inject = kept;
+    }
+    rules.retain(|rule| rule.on_exports() || !rule.inject().is_empty());
+    Ok(())
+}
+
+/// Fail with [`Error::Contract`] if any diagnostic is a contract
+/// violation.
+fn check_contracts(diagnostics: &[ContractResult]) -> Result<()> {
+    let violations: Vec<_> = diagnostics
+        .iter()
+        .filter_map(|diag| match diag {
+            ContractResult::Error(violation) => Some(violation.clone()),
+            _ => None,
+        })
+        .collect();
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Contract { violations })
+    }
+}
+
 /// Walk every injection in `rules`; for builtin-form entries, write
-/// the embedded bytes from [`crate::builtins`] to disk under
-/// `splits_dir/builtins/` and stamp the resulting absolute path onto
-/// the injection. After this runs, every injection that came in via
+/// the embedded bytes from [`crate::builtins`] through `artifacts`
+/// under `splits_dir/builtins/` and stamp the resulting path onto the
+/// injection. After this runs, every injection that came in via
 /// `builtin: ...` looks identical to a path-backed user middleware
 /// from the rest of the pipeline's perspective.
-fn materialize_builtins(rules: &mut [SpliceRule], splits_dir: &std::path::Path) -> Result<()> {
+fn materialize_builtins(
+    rules: &mut [SpliceRule],
+    splits_dir: &Path,
+    artifacts: &mut Artifacts,
+) -> Result<()> {
     for rule in rules.iter_mut() {
-        for inj in rule.inject_mut().iter_mut() {
+        for inj in rule.inject_mut().into_iter().flatten() {
             let Some(builtin) = inj.builtin.as_deref() else {
                 continue;
             };
-            let path = builtins::materialize_into(splits_dir, builtin)
-                .with_context(|| format!("Failed to materialize builtin '{builtin}'"))?;
+            let path =
+                builtins::materialize_into(artifacts, splits_dir, builtin).map_err(|error| {
+                    Error::Builtin {
+                        name: builtin.to_string(),
+                        error,
+                    }
+                })?;
             let path_str = path
                 .to_str()
-                .ok_or_else(|| {
-                    anyhow::anyhow!(
+                .ok_or_else(|| Error::Builtin {
+                    name: builtin.to_string(),
+                    error: anyhow::anyhow!(
                         "materialized builtin path contains non-UTF-8 bytes: {}",
                         path.display()
-                    )
+                    ),
                 })?
                 .to_string();
             inj.path = Some(path_str);
@@ -345,6 +1171,24 @@ fn materialize_builtins(rules: &mut [SpliceRule], splits_dir: &std::path::Path)
     Ok(())
 }
 
+/// Make every import of `out`'s plan explicit, for
+/// [`SpliceRequest::strict_wiring`], writing the WIT packages the
+/// imports are typed by to `splits_dir`.
+fn wire_strictly(out: &mut WacOutput, splits_dir: &str, artifacts: &mut Artifacts) -> Result<()> {
+    make_explicit(&mut out.plan, &mut out.wac_deps, splits_dir, artifacts)
+        .map_err(|e| Error::classify(e, Error::WacGenerate))?;
+    out.wac = out.plan.to_wac();
+    Ok(())
+}
+
+/// Drop the placeholder paths [`generate_wac_in`] records for
+/// middleware injected by name only (no `path:`). The bundle has no
+/// package for them; [`Bundle::to_wasm_with`] finds them through the
+/// caller's fallback [`PackageSource`] instead.
+fn drop_placeholder_deps(deps: &mut BTreeMap<String, PathBuf>) {
+    deps.retain(|_, path| path.as_path() != Path::new(PATH_PLACEHOLDER));
+}
+
 /// Rewrite every path in `wac_deps` to its canonical absolute form.
 /// Splicer always returns absolute paths in [`Bundle::wac_deps`] so
 /// that downstream consumers (`Bundle::to_wasm`, the printed
@@ -355,31 +1199,118 @@ fn materialize_builtins(rules: &mut [SpliceRule], splits_dir: &std::path::Path)
 /// generated adapters, materialized builtins, and user-supplied
 /// injection wasms are all written or required to exist before this
 /// runs. A missing path here means an upstream pipeline stage
-/// produced a bogus reference; we surface that as a clear error.
+/// produced a bogus reference; we surface that as a clear
+/// [`Error::WacGenerate`].
 fn canonicalize_wac_deps(deps: &mut BTreeMap<String, PathBuf>) -> Result<()> {
     for (key, path) in deps.iter_mut() {
-        let canonical = std::fs::canonicalize(&*path).with_context(|| {
-            format!(
-                "Failed to canonicalize wac_deps path for '{key}': {}",
-                path.display()
-            )
-        })?;
+        let canonical = std::fs::canonicalize(&*path)
+            .with_context(|| {
+                format!(
+                    "Failed to canonicalize wac_deps path for '{key}': {}",
+                    path.display()
+                )
+            })
+            .map_err(Error::WacGenerate)?;
         *path = canonical;
     }
     Ok(())
 }
 
+/// Read the bytes of package `key` at `path` through `artifacts`.
+fn read_dep<'a>(artifacts: &'a Artifacts, key: &str, path: &Path) -> Result<Cow<'a, [u8]>> {
+    if artifacts.is_in_memory() {
+        return artifacts
+            .read(&path.to_string_lossy())
+            .with_context(|| format!("Failed to load package '{key}'"))
+            .map_err(Error::WacResolve);
+    }
+    std::fs::read(path)
+        .map(Cow::Owned)
+        .map_err(|error| Error::Io {
+            path: path.to_path_buf(),
+            error,
+        })
+}
+
+/// A bundle's own packages — [`Bundle::wac_deps`], read through its
+/// artifact store — as a [`PackageSource`].
+struct BundlePackages<'a>(&'a Bundle);
+
+impl PackageSource for BundlePackages<'_> {
+    fn package(
+        &self,
+        name: &str,
+        version: Option<&semver::Version>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let key = package_key(name, version);
+        let Some(path) = self.0.wac_deps.get(&key) else {
+            return Ok(None);
+        };
+        Ok(Some(read_dep(&self.0.artifacts, &key, path)?.into_owned()))
+    }
+}
+
+/// Content-hash every package in `wac_deps` for [`Bundle::dep_hashes`].
+fn hash_deps(
+    wac_deps: &BTreeMap<String, PathBuf>,
+    artifacts: &Artifacts,
+) -> Result<BTreeMap<String, String>> {
+    wac_deps
+        .iter()
+        .map(|(key, path)| Ok((key.clone(), content_hash(&read_dep(artifacts, key, path)?))))
+        .collect()
+}
+
+/// The canonical directory a manifest at `manifest_path` lives in,
+/// which its relative paths are resolved against. `create` makes the
+/// directory first (for writing).
+fn manifest_dir(manifest_path: &Path, create: bool) -> Result<PathBuf> {
+    let dir = match manifest_path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let io_err = |error| Error::Io {
+        path: dir.to_path_buf(),
+        error,
+    };
+    if create {
+        std::fs::create_dir_all(dir).map_err(io_err)?;
+    }
+    std::fs::canonicalize(dir).map_err(io_err)
+}
+
+/// `path` relative to `base` when it lives underneath it, otherwise
+/// `path` unchanged.
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    match canonical.strip_prefix(base) {
+        Ok(rel) => rel.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
 /// Format a `wac compose <wac_path> --dep ...` shell command line
 /// from the wac source path and the per-dependency `(package_key,
 /// wasm_path)` map returned by [`splice`] or [`compose`].
 pub fn format_wac_compose_cmd(wac_path: &str, deps: &BTreeMap<String, PathBuf>) -> String {
     let mut cmd = format!("wac compose {wac_path} ");
+    // `wac` applies `--dep` to unversioned references only; versioned
+    // packages (strict wiring's WIT packages) are found under a
+    // `--deps-dir`, as `<ns>/<name>/<version>.wasm`.
+    let mut deps_dirs = BTreeSet::new();
     for (pkg_key, pkg_path) in deps {
+        if pkg_key.contains('@') {
+            deps_dirs.extend(pkg_path.ancestors().nth(3));
+            continue;
+        }
         cmd.push_str(&format!(
             "\\\n    --dep {pkg_key}=\"{}\" ",
             pkg_path.display()
         ));
     }
+    for dir in deps_dirs {
+        cmd.push_str(&format!("\\\n    --deps-dir \"{}\" ", dir.display()));
+    }
     cmd
 }
 
@@ -387,7 +1318,9 @@ pub fn format_wac_compose_cmd(wac_path: &str, deps: &BTreeMap<String, PathBuf>)
 mod tests {
     use super::*;
     use crate::builtins::with_fake_builtins;
-    use crate::parse::config::parse_yaml;
+    use crate::parse::builder::Rule;
+    use crate::parse::config::Injection;
+    use cviz::parse::component::parse_component;
     use std::path::Path;
 
     /// `materialize_builtins` should resolve every builtin-form
@@ -407,7 +1340,8 @@ rules:
         with_fake_builtins(&["hello-tier1"], || {
             let mut rules = parse_yaml(yaml).expect("parse");
             let tmp = tempfile::tempdir().unwrap();
-            materialize_builtins(&mut rules, tmp.path()).expect("materialize");
+            materialize_builtins(&mut rules, tmp.path(), &mut Artifacts::on_disk())
+                .expect("materialize");
 
             let inj = &rules[0].inject()[0];
             assert_eq!(inj.builtin.as_deref(), Some("hello-tier1"));
@@ -439,7 +1373,8 @@ rules:
 "#;
         let mut rules = parse_yaml(yaml).expect("parse");
         let tmp = tempfile::tempdir().unwrap();
-        materialize_builtins(&mut rules, tmp.path()).expect("materialize");
+        materialize_builtins(&mut rules, tmp.path(), &mut Artifacts::on_disk())
+            .expect("materialize");
 
         let inj = &rules[0].inject()[0];
         assert!(inj.builtin.is_none());
@@ -466,7 +1401,8 @@ rules:
         with_fake_builtins(&["hello-tier1"], || {
             let mut rules = parse_yaml(yaml).expect("parse");
             let tmp = tempfile::tempdir().unwrap();
-            materialize_builtins(&mut rules, tmp.path()).expect("materialize");
+            materialize_builtins(&mut rules, tmp.path(), &mut Artifacts::on_disk())
+                .expect("materialize");
 
             let inject = &rules[0].inject();
             assert_eq!(inject[0].path.as_deref(), Some("./tracing.wasm"));
@@ -493,7 +1429,8 @@ rules:
         with_fake_builtins(&["hello-tier1"], || {
             let mut rules = parse_yaml(yaml).expect("parse");
             let tmp = tempfile::tempdir().unwrap();
-            materialize_builtins(&mut rules, tmp.path()).expect("materialize");
+            materialize_builtins(&mut rules, tmp.path(), &mut Artifacts::on_disk())
+                .expect("materialize");
 
             let inj = &rules[0].inject()[0];
             assert_eq!(inj.name, "greeter");
@@ -552,22 +1489,758 @@ rules:
         // Construct rules directly — parse_yaml can't produce an
         // unknown builtin, since the registry isn't consulted at
         // parse time.
-        use crate::parse::config::{Injection, SpliceRule};
         let mut rules = vec![SpliceRule::Before {
             interface: "wasi:logging/log@0.1.0".into(),
             provider_name: None,
+            provider_instance: None,
+            consumer_name: None,
             provider_alias: None,
             inject: vec![Injection {
                 name: "ghost".into(),
                 path: None,
                 builtin: Some("does-not-exist".into()),
+                instance: None,
+                deps: Vec::new(),
                 adapter_info: None,
             }],
         }];
         let tmp = tempfile::tempdir().unwrap();
-        let err = materialize_builtins(&mut rules, tmp.path()).unwrap_err();
+        let err =
+            materialize_builtins(&mut rules, tmp.path(), &mut Artifacts::on_disk()).unwrap_err();
         let msg = format!("{err:#}");
         assert!(msg.contains("does-not-exist"), "error names builtin: {msg}");
         assert!(msg.contains("hello-tier1"), "error lists available: {msg}");
     }
+
+    // ── In-memory splice ──────────────────────────────────────────
+
+    const WAT_PROVIDER: &str = r#"(component
+        (import "host:env/dep@0.1.0" (instance $dep
+            (export "get" (func (result u32)))
+        ))
+        (core module $m (func (export "run") (result i32) i32.const 0))
+        (core instance $mi (instantiate $m))
+        (alias export $dep "get" (func $f))
+        (instance $out (export "get" (func $f)))
+        (export "my:providers/a@0.1.0" (instance $out))
+    )"#;
+
+    const WAT_CONSUMER: &str = r#"(component
+        (import "my:providers/a@0.1.0" (instance $a
+            (export "get" (func (result u32)))
+        ))
+        (core module $m (func (export "run") (result i32) i32.const 0))
+        (core instance $mi (instantiate $m))
+        (alias export $a "get" (func $f))
+        (instance $out (export "get" (func $f)))
+        (export "my:consumer/app@0.1.0" (instance $out))
+    )"#;
+
+    /// Pass-through middleware: imports and re-exports the provider
+    /// interface unchanged.
+    const WAT_MIDDLEWARE: &str = r#"(component
+        (import "my:providers/a@0.1.0" (instance $a
+            (export "get" (func (result u32)))
+        ))
+        (core module $m (func (export "run") (result i32) i32.const 0))
+        (core instance $mi (instantiate $m))
+        (alias export $a "get" (func $f))
+        (instance $out (export "get" (func $f)))
+        (export "my:providers/a@0.1.0" (instance $out))
+    )"#;
+
+    /// Compose provider + consumer into a real composition and return
+    /// its bytes, so `splice_bytes` has something to split. Each
+    /// fixture carries a core module so the splitter doesn't treat it
+    /// as a shim.
+    fn composed_fixture(dir: &Path) -> Vec<u8> {
+        compose(ComposeRequest {
+            components: fixture_components(dir),
+            package_name: "test:pkg".into(),
+            rules: Vec::new(),
+            splits_dir: None,
+            skip_type_check: false,
+            provenance: false,
+            strict_wiring: false,
+        })
+        .and_then(|bundle| bundle.to_wasm())
+        .expect("compose fixture")
+    }
+
+    /// Write the provider + consumer fixtures into `dir` and return
+    /// them as compose inputs.
+    fn fixture_components(dir: &Path) -> Vec<ComponentInput> {
+        let provider = dir.join("provider.wasm");
+        let consumer = dir.join("consumer.wasm");
+        std::fs::write(&provider, wat::parse_str(WAT_PROVIDER).unwrap()).unwrap();
+        std::fs::write(&consumer, wat::parse_str(WAT_CONSUMER).unwrap()).unwrap();
+        vec![
+            ComponentInput {
+                alias: None,
+                path: provider,
+            },
+            ComponentInput {
+                alias: None,
+                path: consumer,
+            },
+        ]
+    }
+
+    /// Replacing a bundle's WAC drops its plan, so the new text is
+    /// what gets composed.
+    #[test]
+    fn set_wac_composes_the_new_text() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut bundle = compose(ComposeRequest {
+            components: fixture_components(tmp.path()),
+            package_name: "test:pkg".into(),
+            rules: Vec::new(),
+            splits_dir: None,
+            skip_type_check: false,
+            provenance: false,
+            strict_wiring: false,
+        })
+        .unwrap();
+        assert!(bundle.plan().is_some());
+
+        bundle.set_wac("not wac");
+        assert!(bundle.plan().is_none());
+        assert!(matches!(bundle.to_wasm(), Err(Error::WacResolve(_))));
+    }
+
+    /// With no rules, `splice_bytes` round-trips the composition to a
+    /// valid component without writing a splits dir anywhere.
+    #[test]
+    fn splice_bytes_without_rules_roundtrips() {
+        let tmp = tempfile::tempdir().unwrap();
+        let composed = composed_fixture(tmp.path());
+
+        let out = splice_bytes(&composed, &[]).expect("splice_bytes");
+        assert!(out.starts_with(b"\0asm"));
+        assert!(!Path::new(IN_MEMORY_ROOT).exists());
+    }
+
+    /// A path-backed middleware is read from disk, but the splits the
+    /// run produces stay in memory.
+    #[test]
+    fn splice_bytes_injects_middleware_in_memory() {
+        let tmp = tempfile::tempdir().unwrap();
+        let composed = composed_fixture(tmp.path());
+        let mw = tmp.path().join("mw.wasm");
+        std::fs::write(&mw, wat::parse_str(WAT_MIDDLEWARE).unwrap()).unwrap();
+
+        let rules = vec![
+            Rule::between("my:providers/a@0.1.0", "provider", "consumer")
+                .inject(Injection::from_path("mw", mw.to_str().unwrap()))
+                .build()
+                .unwrap(),
+        ];
+        let out = splice_bytes(&composed, &rules).expect("splice_bytes");
+
+        let graph = parse_component(&out).expect("parse spliced output");
+        assert!(
+            graph.nodes.values().any(|n| n.display_label() == "mw"),
+            "middleware instance should be present in the spliced graph"
+        );
+        let entries: Vec<_> = std::fs::read_dir(tmp.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(entries.len(), 3, "only the fixtures on disk: {entries:?}");
+    }
+
+    /// Each component's graph is parsed once per splice: the
+    /// re-splice check and the splitter share the composition's, and
+    /// the contract check parses the middleware's.
+    #[test]
+    fn splice_parses_the_composition_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let composed = composed_fixture(tmp.path());
+        let mw = tmp.path().join("mw.wasm");
+        std::fs::write(&mw, wat::parse_str(WAT_MIDDLEWARE).unwrap()).unwrap();
+
+        let bundle = splice_in(
+            &composed,
+            vec![mw_rule(&mw)],
+            DEFAULT_PACKAGE_NAME,
+            IN_MEMORY_ROOT,
+            SpliceFlags::default(),
+            Artifacts::in_memory(),
+        )
+        .unwrap();
+        assert_eq!(bundle.artifacts.parsed().graph_parses(), 2);
+    }
+
+    /// Imports the consumer fixture's interface; the outer half of
+    /// [`nested_fixture`].
+    const WAT_APP: &str = r#"(component
+        (import "my:consumer/app@0.1.0" (instance $app
+            (export "get" (func (result u32)))
+        ))
+        (core module $m (func (export "run") (result i32) i32.const 0))
+        (core instance $mi (instantiate $m))
+        (alias export $app "get" (func $f))
+        (instance $out (export "get" (func $f)))
+        (export "my:app/main@0.1.0" (instance $out))
+    )"#;
+
+    /// A composition of the provider + consumer composition, as `sub`,
+    /// and an app using it.
+    fn nested_fixture(dir: &Path) -> Vec<u8> {
+        let sub = dir.join("sub.wasm");
+        let app = dir.join("app.wasm");
+        std::fs::write(&sub, composed_fixture(dir)).unwrap();
+        std::fs::write(&app, wat::parse_str(WAT_APP).unwrap()).unwrap();
+        compose(ComposeRequest {
+            components: vec![
+                ComponentInput {
+                    alias: None,
+                    path: sub,
+                },
+                ComponentInput {
+                    alias: None,
+                    path: app,
+                },
+            ],
+            package_name: "test:outer".into(),
+            rules: Vec::new(),
+            splits_dir: None,
+            skip_type_check: false,
+            provenance: false,
+            strict_wiring: false,
+        })
+        .and_then(|bundle| bundle.to_wasm())
+        .expect("compose nested fixture")
+    }
+
+    /// A nested composition is instantiated whole rather than taken
+    /// for a shim, and a path-qualified rule splices inside it.
+    #[test]
+    fn splice_bytes_reaches_into_nested_compositions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let outer = nested_fixture(tmp.path());
+        let mw = tmp.path().join("mw.wasm");
+        std::fs::write(&mw, wat::parse_str(WAT_MIDDLEWARE).unwrap()).unwrap();
+
+        splice_bytes(&outer, &[]).expect("nested composition roundtrips");
+
+        let rules = vec![
+            Rule::between("my:providers/a@0.1.0", "sub/provider", "sub/consumer")
+                .inject(Injection::from_path("mw", mw.to_str().unwrap()))
+                .build()
+                .unwrap(),
+        ];
+        let out = splice_bytes(&outer, &rules).expect("splice_bytes");
+
+        let mut artifacts = Artifacts::in_memory();
+        let nesting = split_into(&out, IN_MEMORY_ROOT, &mut artifacts).unwrap();
+        let mut graph = parse_component(&out).expect("parse spliced output");
+        let mw_split = graph
+            .nodes
+            .iter()
+            .find(|(_, n)| n.display_label() == "mw")
+            .map(|(id, _)| crate::wac::node_split_num(*id, &graph))
+            .expect("middleware instance in the spliced graph");
+        assert!(nesting.inside_composition(mw_split));
+
+        scope_to_root(&mut graph, &nesting);
+        let mut labels: Vec<_> = graph.nodes.values().map(|n| n.display_label()).collect();
+        labels.sort();
+        assert_eq!(labels, ["app", "sub"]);
+    }
+
+    /// A nested splice runs with the outer splice's flags: with
+    /// provenance on, the nested composition records its own
+    /// middleware, so re-splicing it recognizes them.
+    #[test]
+    fn nested_splices_keep_the_request_flags() {
+        let tmp = tempfile::tempdir().unwrap();
+        let outer = nested_fixture(tmp.path());
+        let mw = tmp.path().join("mw.wasm");
+        std::fs::write(&mw, wat::parse_str(WAT_MIDDLEWARE).unwrap()).unwrap();
+        let rules = vec![
+            Rule::between("my:providers/a@0.1.0", "sub/provider", "sub/consumer")
+                .inject(Injection::from_path("mw", mw.to_str().unwrap()))
+                .build()
+                .unwrap(),
+        ];
+
+        let out = splice_in(
+            &outer,
+            rules,
+            DEFAULT_PACKAGE_NAME,
+            IN_MEMORY_ROOT,
+            SpliceFlags {
+                provenance: true,
+                strict_wiring: true,
+                ..SpliceFlags::default()
+            },
+            Artifacts::in_memory(),
+        )
+        .and_then(|bundle| bundle.to_wasm())
+        .expect("nested splice");
+
+        let mut artifacts = Artifacts::in_memory();
+        split_into(&out, IN_MEMORY_ROOT, &mut artifacts).unwrap();
+        let graph = parse_component(&out).unwrap();
+        let sub = graph
+            .nodes
+            .iter()
+            .find(|(_, n)| n.display_label() == "sub")
+            .map(|(id, _)| crate::wac::node_split_num(*id, &graph))
+            .unwrap();
+        let path = SplitPaths::load(IN_MEMORY_ROOT, &artifacts)
+            .unwrap()
+            .path(sub);
+        let inner = crate::read_provenance(&artifacts.read(&path).unwrap())
+            .unwrap()
+            .expect("the nested composition records its provenance");
+        assert_eq!(inner.middleware[0].name, "mw");
+    }
+
+    /// Rules given to `compose` are applied while the composition is
+    /// built — no intermediate composition is split.
+    #[test]
+    fn compose_applies_rules() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mw = tmp.path().join("mw.wasm");
+        std::fs::write(&mw, wat::parse_str(WAT_MIDDLEWARE).unwrap()).unwrap();
+        let splits = tmp.path().join("splits");
+
+        let bundle = compose(ComposeRequest {
+            components: fixture_components(tmp.path()),
+            package_name: "test:pkg".into(),
+            rules: vec![
+                Rule::between("my:providers/a@0.1.0", "provider", "consumer")
+                    .inject(Injection::from_path("mw", mw.to_str().unwrap()))
+                    .build()
+                    .unwrap(),
+            ],
+            splits_dir: Some(splits.clone()),
+            skip_type_check: false,
+            provenance: false,
+            strict_wiring: false,
+        })
+        .expect("compose with rules");
+        assert!(
+            bundle.wac().contains("let mw = new"),
+            "wac:\n{}",
+            bundle.wac()
+        );
+
+        let graph = parse_component(&bundle.to_wasm().unwrap()).expect("parse composed output");
+        assert!(
+            graph.nodes.values().any(|n| n.display_label() == "mw"),
+            "middleware instance should be present in the composed graph"
+        );
+        let split_files = std::fs::read_dir(&splits).unwrap().count();
+        assert_eq!(split_files, 0, "composition should not be split");
+    }
+
+    /// Middleware injected by name only has no package in the bundle;
+    /// a fallback source supplies it at compose time.
+    #[test]
+    fn to_wasm_with_resolves_placeholder_injections() {
+        let tmp = tempfile::tempdir().unwrap();
+        let bundle = compose(ComposeRequest {
+            components: fixture_components(tmp.path()),
+            package_name: "test:pkg".into(),
+            rules: vec![
+                Rule::between("my:providers/a@0.1.0", "provider", "consumer")
+                    .inject(Injection::from_name("mw"))
+                    .build()
+                    .unwrap(),
+            ],
+            splits_dir: Some(tmp.path().join("splits")),
+            skip_type_check: true,
+            provenance: false,
+            strict_wiring: false,
+        })
+        .expect("compose with placeholder");
+        assert!(matches!(bundle.to_wasm(), Err(Error::WacResolve(_))));
+
+        let deps = tmp.path().join("deps");
+        std::fs::create_dir_all(deps.join("my")).unwrap();
+        std::fs::write(
+            deps.join("my/mw.wasm"),
+            wat::parse_str(WAT_MIDDLEWARE).unwrap(),
+        )
+        .unwrap();
+        let composed = bundle
+            .to_wasm_with(&crate::packages::SearchPaths::new([&deps]))
+            .expect("fallback resolves the middleware");
+        let graph = parse_component(&composed).unwrap();
+        assert!(graph.nodes.values().any(|n| n.display_label() == "mw"));
+    }
+
+    /// Rules need somewhere to write generated adapters and builtins.
+    #[test]
+    fn compose_with_rules_requires_splits_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let err = compose(ComposeRequest {
+            components: fixture_components(tmp.path()),
+            package_name: "test:pkg".into(),
+            rules: vec![
+                Rule::between("my:providers/a@0.1.0", "provider", "consumer")
+                    .inject(Injection::from_name("mw"))
+                    .build()
+                    .unwrap(),
+            ],
+            splits_dir: None,
+            skip_type_check: false,
+            provenance: false,
+            strict_wiring: false,
+        })
+        .unwrap_err();
+        assert!(matches!(err, Error::Config(_)), "got: {err:?}");
+    }
+
+    /// Strict wiring spells the splits' host import out as a WAC
+    /// `import`, typed by a WIT package among the deps; the saved
+    /// manifest's WAC composes to the same bytes as the plan.
+    #[test]
+    fn strict_splice_imports_explicitly() {
+        let tmp = tempfile::tempdir().unwrap();
+        let input = tmp.path().join("in.wasm");
+        std::fs::write(&input, composed_fixture(tmp.path())).unwrap();
+        let bundle = splice(SpliceRequest {
+            composition_wasm: input,
+            rules: Vec::new(),
+            package_name: "test:pkg".into(),
+            splits_dir: tmp.path().join("splits"),
+            skip_type_check: false,
+            provenance: false,
+            reject_existing: false,
+            strict_wiring: true,
+        })
+        .expect("strict splice");
+        assert!(
+            bundle
+                .wac()
+                .contains("import host-env-dep: host:env/dep@0.1.0;"),
+            "wac:\n{}",
+            bundle.wac()
+        );
+        assert!(!bundle.wac().contains("..."), "wac:\n{}", bundle.wac());
+        assert!(bundle.wac_deps.contains_key("host:env@0.1.0"));
+        // `wac` finds versioned packages under a deps dir, not `--dep`.
+        let cmd = bundle.wac_compose_cmd("out.wac");
+        assert!(!cmd.contains("--dep host:env"), "{cmd}");
+        assert!(
+            cmd.contains(&format!(
+                "--deps-dir \"{}\"",
+                tmp.path()
+                    .join("splits/wit")
+                    .canonicalize()
+                    .unwrap()
+                    .display()
+            )),
+            "{cmd}"
+        );
+
+        let manifest = tmp.path().join("bundle.json");
+        bundle.save(&manifest).unwrap();
+        let loaded = Bundle::load(&manifest).unwrap();
+        assert_eq!(loaded.to_wasm().unwrap(), bundle.to_wasm().unwrap());
+    }
+
+    /// A saved manifest loads back from a moved directory — paths
+    /// under the manifest's dir are stored relative to it — and
+    /// composes to the same bytes.
+    #[test]
+    fn manifest_roundtrips_after_move() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("out");
+        std::fs::create_dir(&dir).unwrap();
+        let bundle = compose(ComposeRequest {
+            components: fixture_components(&dir),
+            package_name: "test:pkg".into(),
+            rules: Vec::new(),
+            splits_dir: None,
+            skip_type_check: false,
+            provenance: false,
+            strict_wiring: false,
+        })
+        .unwrap();
+        bundle.save(&dir.join("bundle.json")).unwrap();
+        let composed = bundle.to_wasm().unwrap();
+
+        let moved = tmp.path().join("moved");
+        std::fs::rename(&dir, &moved).unwrap();
+        let loaded = Bundle::load(&moved.join("bundle.json")).expect("load manifest");
+        assert_eq!(loaded.wac(), bundle.wac());
+        assert_eq!(loaded.dep_hashes, bundle.dep_hashes);
+        assert!(loaded.wac_deps.values().all(|p| p.starts_with(&moved)));
+        assert_eq!(loaded.to_wasm().unwrap(), composed);
+    }
+
+    /// A package rewritten after the manifest was saved is caught on
+    /// load rather than silently composed.
+    #[test]
+    fn manifest_detects_stale_packages() {
+        let tmp = tempfile::tempdir().unwrap();
+        let bundle = compose(ComposeRequest {
+            components: fixture_components(tmp.path()),
+            package_name: "test:pkg".into(),
+            rules: Vec::new(),
+            splits_dir: None,
+            skip_type_check: false,
+            provenance: false,
+            strict_wiring: false,
+        })
+        .unwrap();
+        let manifest = tmp.path().join("bundle.json");
+        bundle.save(&manifest).unwrap();
+
+        std::fs::write(
+            tmp.path().join("provider.wasm"),
+            wat::parse_str(WAT_MIDDLEWARE).unwrap(),
+        )
+        .unwrap();
+        let err = Bundle::load(&manifest).unwrap_err();
+        assert!(
+            matches!(&err, Error::StaleDependency { path, .. } if path.ends_with("provider.wasm")),
+            "got: {err:?}"
+        );
+
+        std::fs::write(&manifest, r#"{"version": 99}"#).unwrap();
+        assert!(matches!(Bundle::load(&manifest), Err(Error::Manifest(_))));
+    }
+
+    /// `provenance: true` embeds a record of the splice — input hash,
+    /// rules and middleware digests — that `read_provenance` decodes.
+    #[test]
+    fn splice_embeds_provenance() {
+        let tmp = tempfile::tempdir().unwrap();
+        let composed = composed_fixture(tmp.path());
+        let input = tmp.path().join("composed.wasm");
+        std::fs::write(&input, &composed).unwrap();
+        let mw_bytes = wat::parse_str(WAT_MIDDLEWARE).unwrap();
+        let mw = tmp.path().join("mw.wasm");
+        std::fs::write(&mw, &mw_bytes).unwrap();
+        let rules = vec![
+            Rule::between("my:providers/a@0.1.0", "provider", "consumer")
+                .inject(Injection::from_path("mw", mw.to_str().unwrap()))
+                .build()
+                .unwrap(),
+        ];
+
+        let bundle = splice(SpliceRequest {
+            composition_wasm: input,
+            rules: rules.clone(),
+            package_name: "test:pkg".into(),
+            splits_dir: tmp.path().join("splits"),
+            skip_type_check: false,
+            provenance: true,
+            reject_existing: false,
+            strict_wiring: false,
+        })
+        .unwrap();
+        let out = bundle.to_wasm().unwrap();
+
+        let provenance = crate::read_provenance(&out).unwrap().expect("provenance");
+        assert_eq!(provenance.splicer_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(provenance.input_hash, Some(content_hash(&composed)));
+        assert_eq!(provenance.rules, rules);
+        assert_eq!(provenance.middleware.len(), 1);
+        assert_eq!(provenance.middleware[0].name, "mw");
+        assert_eq!(provenance.middleware[0].interface, "my:providers/a@0.1.0");
+        assert_eq!(
+            provenance.middleware[0].digest,
+            Some(content_hash(&mw_bytes))
+        );
+
+        // Without the flag, nothing is embedded.
+        assert_eq!(crate::read_provenance(&composed).unwrap(), None);
+    }
+
+    /// Splice `mw` between the fixture's provider and consumer, write
+    /// the composed output to `dir`, and return its path.
+    fn spliced_fixture(dir: &Path, provenance: bool) -> PathBuf {
+        let input = dir.join("composed.wasm");
+        std::fs::write(&input, composed_fixture(dir)).unwrap();
+        let mw = dir.join("mw.wasm");
+        std::fs::write(&mw, wat::parse_str(WAT_MIDDLEWARE).unwrap()).unwrap();
+        let bundle = splice(SpliceRequest {
+            composition_wasm: input,
+            rules: vec![mw_rule(&mw)],
+            package_name: "test:pkg".into(),
+            splits_dir: dir.join("splits"),
+            skip_type_check: false,
+            provenance,
+            reject_existing: false,
+            strict_wiring: false,
+        })
+        .unwrap();
+        let spliced = dir.join("spliced.wasm");
+        std::fs::write(&spliced, bundle.to_wasm().unwrap()).unwrap();
+        spliced
+    }
+
+    /// Inject the middleware at `mw` between the fixture's provider
+    /// and consumer.
+    fn mw_rule(mw: &Path) -> SpliceRule {
+        Rule::between("my:providers/a@0.1.0", "provider", "consumer")
+            .inject(Injection::from_path("mw", mw.to_str().unwrap()))
+            .build()
+            .unwrap()
+    }
+
+    /// Re-running a splice on its own output skips the middleware it
+    /// already injected — recognized through provenance, or through the
+    /// instance name without it — and `reject_existing` turns that into
+    /// an error.
+    #[test]
+    fn resplice_skips_existing_middleware() {
+        for provenance in [true, false] {
+            let tmp = tempfile::tempdir().unwrap();
+            let spliced = spliced_fixture(tmp.path(), provenance);
+            let request = |reject_existing| SpliceRequest {
+                composition_wasm: spliced.clone(),
+                rules: vec![mw_rule(&tmp.path().join("mw.wasm"))],
+                package_name: "test:pkg".into(),
+                splits_dir: tmp.path().join("resplit"),
+                skip_type_check: false,
+                provenance,
+                reject_existing,
+                strict_wiring: false,
+            };
+
+            let bundle = splice(request(false)).unwrap();
+            let out = bundle.to_wasm().unwrap();
+            let graph = parse_graph(&out, &Artifacts::in_memory()).unwrap();
+            assert_eq!(graph.nodes.len(), 3, "mw injected once");
+            if provenance {
+                let record = crate::read_provenance(&out).unwrap().unwrap();
+                assert_eq!(record.middleware.len(), 1, "{record:?}");
+                assert_eq!(record.middleware[0].name, "mw");
+            }
+
+            let err = splice(request(true)).unwrap_err();
+            assert!(
+                matches!(&err, Error::AlreadyInjected { name, .. } if name == "mw"),
+                "{err}"
+            );
+        }
+    }
+
+    /// Removing every injection restores the original wiring and
+    /// drops the provenance along with the middleware.
+    #[test]
+    fn unsplice_all_restores_original_wiring() {
+        let tmp = tempfile::tempdir().unwrap();
+        let spliced = spliced_fixture(tmp.path(), true);
+
+        let bundle = unsplice(UnspliceRequest {
+            composition_wasm: spliced,
+            remove: MiddlewareSelector::All,
+            package_name: "test:pkg".into(),
+            splits_dir: tmp.path().join("unspliced"),
+        })
+        .unwrap();
+        assert!(!bundle.wac().contains("let mw ="), "{}", bundle.wac());
+        assert!(bundle.provenance.is_none());
+
+        let out = bundle.to_wasm().unwrap();
+        let graph = parse_graph(&out, &Artifacts::in_memory()).unwrap();
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(crate::read_provenance(&out).unwrap(), None);
+    }
+
+    /// Without provenance, middleware can still be removed by name.
+    #[test]
+    fn unsplice_named_without_provenance() {
+        let tmp = tempfile::tempdir().unwrap();
+        let spliced = spliced_fixture(tmp.path(), false);
+        let request = |remove| UnspliceRequest {
+            composition_wasm: spliced.clone(),
+            remove,
+            package_name: "test:pkg".into(),
+            splits_dir: tmp.path().join("unspliced"),
+        };
+
+        let bundle = unsplice(request(MiddlewareSelector::Named(vec!["mw".into()]))).unwrap();
+        assert!(!bundle.wac().contains("let mw ="), "{}", bundle.wac());
+        bundle.to_wasm().unwrap();
+
+        // Plain middleware is invisible without provenance.
+        let err = unsplice(request(MiddlewareSelector::All)).unwrap_err();
+        assert!(matches!(err, Error::Unsplice(_)), "{err}");
+    }
+
+    /// Cross-rule checks the builder can't see (here, a duplicate
+    /// injection name across two rules) are enforced when the rule
+    /// list reaches the splice pipeline.
+    #[test]
+    fn splice_bytes_rejects_invalid_rules() {
+        let rules = vec![
+            Rule::before("wasi:http/handler")
+                .inject(Injection::from_name("mw"))
+                .build()
+                .unwrap(),
+            Rule::before("wasi:logging/log")
+                .inject(Injection::from_name("mw"))
+                .build()
+                .unwrap(),
+        ];
+        let err = splice_bytes(b"not wasm", &rules).unwrap_err();
+        assert!(matches!(err, Error::Config(_)), "got: {err:?}");
+        assert!(
+            err.to_string()
+                .contains("injection name 'mw' is used in rule 2"),
+            "unexpected error: {err}"
+        );
+    }
+
+    /// Each pipeline stage surfaces as its own `Error` variant.
+    #[test]
+    fn errors_are_classified_by_stage() {
+        assert!(matches!(
+            parse_yaml("version: 99\nrules: []\n"),
+            Err(Error::Config(_))
+        ));
+        assert!(matches!(
+            splice_bytes(b"not wasm", &[]),
+            Err(Error::GraphParse(_))
+        ));
+        assert!(matches!(
+            compose_wac_bytes(
+                "package a:b;\nlet x = new my:missing {};\n",
+                &BTreeMap::new()
+            ),
+            Err(Error::WacResolve(_))
+        ));
+
+        let tmp = tempfile::tempdir().unwrap();
+        let err = splice(SpliceRequest {
+            composition_wasm: tmp.path().join("missing.wasm"),
+            rules: vec![],
+            package_name: "test:pkg".into(),
+            splits_dir: tmp.path().join("splits"),
+            skip_type_check: false,
+            provenance: false,
+            reject_existing: false,
+            strict_wiring: false,
+        })
+        .unwrap_err();
+        assert!(
+            matches!(&err, Error::Io { path, .. } if path.ends_with("missing.wasm")),
+            "got: {err:?}"
+        );
+    }
+
+    /// Unknown builtins surface as `Error::Builtin` naming the builtin.
+    #[test]
+    fn unknown_builtin_is_a_builtin_error() {
+        let rules = vec![Rule::before("wasi:logging/log@0.1.0")
+            .inject(Injection::from_builtin("does-not-exist"))
+            .build()
+            .unwrap()];
+        let tmp = tempfile::tempdir().unwrap();
+        let err = splice_bytes(&composed_fixture(tmp.path()), &rules).unwrap_err();
+        assert!(
+            matches!(&err, Error::Builtin { name, .. } if name == "does-not-exist"),
+            "got: {err:?}"
+        );
+    }
 }
@@ -0,0 +1,207 @@
+//! Store for the `.wasm` artifacts a splice run produces.
+//!
+//! Splits, tier-1 adapters and materialized builtins are all written
+//! through an [`Artifacts`] store under the path they occupy in the
+//! splits directory (e.g. `<splits_dir>/srv-b.wasm`). The store comes
+//! in two flavours:
+//!
+//! - **on disk** — writes land on the filesystem immediately. This is
+//!   what [`crate::splice`] and the lower-level entry points use.
+//! - **in memory** — writes are kept in a package map and never touch
+//!   the filesystem. [`crate::splice_bytes`] runs the whole pipeline
+//!   against one of these, so no temp directories are created.
+//!
+//! Reads always check the in-memory map first and fall back to the
+//! filesystem, so inputs splicer didn't produce (user middleware
+//! referenced by `path:`) resolve the same way in both modes.
+//!
+//! Every store also carries a [`ParseCache`], so the stages that read
+//! a component back share one parse of it.
+
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::parse_cache::ParseCache;
+
+/// Splits-dir prefix used for artifacts that only live in memory.
+/// Paths under this root never exist on disk; they only serve as
+/// stable keys in [`Artifacts`] and in the returned `wac_deps`.
+pub(crate) const IN_MEMORY_ROOT: &str = "<in-memory>";
+
+/// Path-keyed store of generated `.wasm` artifacts. See the
+/// [module docs](self) for the on-disk vs in-memory split.
+#[derive(Clone, Default)]
+pub(crate) struct Artifacts {
+    in_memory: bool,
+    files: BTreeMap<String, Vec<u8>>,
+    /// Shared with clones of the store.
+    parsed: Arc<ParseCache>,
+}
+
+impl Artifacts {
+    /// Store whose writes go straight to the filesystem.
+    pub(crate) fn on_disk() -> Self {
+        Self::default()
+    }
+
+    /// Store whose writes are kept in memory.
+    pub(crate) fn in_memory() -> Self {
+        Self {
+            in_memory: true,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn is_in_memory(&self) -> bool {
+        self.in_memory
+    }
+
+    /// Parsed forms of the components read through this store.
+    pub(crate) fn parsed(&self) -> &ParseCache {
+        &self.parsed
+    }
+
+    /// Record `bytes` under `path`. On-disk stores create the parent
+    /// directory if needed and write the file; in-memory stores only
+    /// update the map.
+    pub(crate) fn write(&mut self, path: &str, bytes: Vec<u8>) -> Result<()> {
+        if !self.in_memory {
+            return write_file(path, &bytes);
+        }
+        self.files.insert(path.to_string(), bytes);
+        Ok(())
+    }
+
+    /// [`Artifacts::write`] every `(path, bytes)` pair; when a path
+    /// repeats, the last pair wins. On-disk stores write the files
+    /// concurrently and report the first failure in path order.
+    pub(crate) fn write_all(&mut self, files: Vec<(String, Vec<u8>)>) -> Result<()> {
+        let files: BTreeMap<String, Vec<u8>> = files.into_iter().collect();
+        if self.in_memory {
+            self.files.extend(files);
+            return Ok(());
+        }
+        files
+            .par_iter()
+            .map(|(path, bytes)| write_file(path, bytes))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Read the bytes stored under `path`, falling back to the
+    /// filesystem when the store doesn't hold it.
+    pub(crate) fn read(&self, path: &str) -> Result<Cow<'_, [u8]>> {
+        if let Some(bytes) = self.files.get(path) {
+            return Ok(Cow::Borrowed(bytes));
+        }
+        let bytes = std::fs::read(path).with_context(|| format!("failed to read '{path}'"))?;
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+/// Write `bytes` to `path` on disk, creating the parent directory if
+/// needed.
+fn write_file(path: &str, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = Path::new(path).parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+    }
+    std::fs::write(path, bytes).with_context(|| format!("Failed to write '{path}'"))
+}
+
+/// Content hash of a package's bytes, as recorded in a bundle
+/// manifest: `sha256:<lowercase hex>`.
+pub(crate) fn content_hash(bytes: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(bytes))
+}
+
+impl fmt::Debug for Artifacts {
+    // Print paths and sizes only — the byte payloads are megabytes.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Artifacts")
+            .field("in_memory", &self.in_memory)
+            .field(
+                "files",
+                &self
+                    .files
+                    .iter()
+                    .map(|(path, bytes)| (path.as_str(), bytes.len()))
+                    .collect::<BTreeMap<_, _>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_memory_writes_never_touch_disk() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("split0.wasm");
+        let path = path.to_str().unwrap();
+
+        let mut store = Artifacts::in_memory();
+        store.write(path, b"\0asm".to_vec()).unwrap();
+
+        assert!(!Path::new(path).exists());
+        assert_eq!(&*store.read(path).unwrap(), b"\0asm");
+    }
+
+    #[test]
+    fn on_disk_writes_create_parent_dirs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("nested/builtins/mw.wasm");
+        let path = path.to_str().unwrap();
+
+        let mut store = Artifacts::on_disk();
+        store.write(path, b"\0asm".to_vec()).unwrap();
+
+        assert_eq!(std::fs::read(path).unwrap(), b"\0asm");
+    }
+
+    #[test]
+    fn write_all_last_write_wins() {
+        let tmp = tempfile::tempdir().unwrap();
+        let a = tmp.path().join("a/split0.wasm");
+        let b = tmp.path().join("b/split1.wasm");
+        let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+        let files = vec![
+            (a.to_string(), b"first".to_vec()),
+            (b.to_string(), b"other".to_vec()),
+            (a.to_string(), b"second".to_vec()),
+        ];
+
+        let mut memory = Artifacts::in_memory();
+        memory.write_all(files.clone()).unwrap();
+        assert_eq!(&*memory.read(a).unwrap(), b"second");
+        assert!(!Path::new(a).exists());
+
+        Artifacts::on_disk().write_all(files).unwrap();
+        assert_eq!(std::fs::read(a).unwrap(), b"second");
+        assert_eq!(std::fs::read(b).unwrap(), b"other");
+    }
+
+    /// Reads fall back to the filesystem for inputs the store didn't
+    /// produce (e.g. user middleware referenced by path).
+    #[test]
+    fn read_falls_back_to_disk() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("mw.wasm");
+        std::fs::write(&path, b"\0asm").unwrap();
+
+        let store = Artifacts::in_memory();
+        assert_eq!(&*store.read(path.to_str().unwrap()).unwrap(), b"\0asm");
+        assert!(store.read("/definitely/not/here.wasm").is_err());
+    }
+}
@@ -12,14 +12,19 @@
 //!   2. On-disk cache at `<user-cache>/splicer/builtins/<name>@<version>.wasm`.
 //!   3. OCI pull from ghcr, populating the cache for next time.
 //!
+//! An in-memory splice ([`crate::splice_bytes`]) skips step 2: it
+//! neither reads nor fills the cache.
+//!
 //! Builtins are referenced from the splice config YAML as
 //! `inject: [{ builtin: <name> }]`. The parser populates
 //! [`crate::parse::config::Injection::builtin`] with the name; the
 //! splice pipeline then calls [`materialize_into`] before contract
-//! validation runs to write the resolved bytes to disk under the
-//! splits dir, after which the rest of the pipeline treats the
-//! injection like any other path-backed middleware.
+//! validation runs to stage the resolved bytes under the splits dir
+//! (on disk, or in the in-memory package map for
+//! [`crate::splice_bytes`]), after which the rest of the pipeline
+//! treats the injection like any other path-backed middleware.
 
+use crate::artifacts::Artifacts;
 use anyhow::{Context, Result};
 use std::path::{Path, PathBuf};
 
@@ -106,9 +111,15 @@ pub fn known_names() -> Vec<&'static str> {
 }
 
 /// Resolve the named builtin's bytes (override → cache → OCI pull) and
-/// write them to `<splits_dir>/builtins/<name>.wasm`. Returns the
-/// resulting absolute path.
-pub fn materialize_into(splits_dir: &Path, name: &str) -> Result<PathBuf> {
+/// write them through `artifacts` to `<splits_dir>/builtins/<name>.wasm`.
+/// Returns that path. An in-memory store skips the user cache too —
+/// the bytes come from the override or the registry — and the path is
+/// just the builtin's key in the package map.
+pub fn materialize_into(
+    artifacts: &mut Artifacts,
+    splits_dir: &Path,
+    name: &str,
+) -> Result<PathBuf> {
     let version = version_for(name).ok_or_else(|| {
         anyhow::anyhow!(
             "unknown builtin '{name}'. Available: [{}]",
@@ -116,19 +127,22 @@ pub fn materialize_into(splits_dir: &Path, name: &str) -> Result<PathBuf> {
         )
     })?;
 
-    let bytes = load_bytes(name, version)
+    let bytes = load_bytes(name, version, !artifacts.is_in_memory())
         .with_context(|| format!("Failed to load builtin '{name}@{version}'"))?;
 
-    let dir = splits_dir.join(BUILTIN_SUBDIR);
-    std::fs::create_dir_all(&dir)
-        .with_context(|| format!("Failed to create builtins dir: {}", dir.display()))?;
-    let out = dir.join(format!("{name}.wasm"));
-    std::fs::write(&out, &bytes)
+    let out = splits_dir.join(BUILTIN_SUBDIR).join(format!("{name}.wasm"));
+    let out_str = out.to_str().ok_or_else(|| {
+        anyhow::anyhow!("builtin path contains non-UTF-8 bytes: {}", out.display())
+    })?;
+    artifacts
+        .write(out_str, bytes)
         .with_context(|| format!("Failed to write builtin to: {}", out.display()))?;
     Ok(out)
 }
 
-fn load_bytes(name: &str, version: &str) -> Result<Vec<u8>> {
+/// The builtin's bytes, through the user cache when `use_cache` is
+/// set.
+fn load_bytes(name: &str, version: &str, use_cache: bool) -> Result<Vec<u8>> {
     if let Some(dir) = std::env::var_os(LOCAL_OVERRIDE_ENV) {
         let path = Path::new(&dir).join(format!("{name}.wasm"));
         return std::fs::read(&path).with_context(|| {
@@ -140,6 +154,16 @@ fn load_bytes(name: &str, version: &str) -> Result<Vec<u8>> {
         });
     }
 
+    if !use_cache {
+        return pull_from_registry(name, version).with_context(|| {
+            format!(
+                "could not fetch builtin '{name}@{version}' from {}. \
+                 To use a local build instead, set {LOCAL_OVERRIDE_ENV}=<dir-of-wasm>.",
+                oci_reference(name, version),
+            )
+        });
+    }
+
     let cache_path = cache_path_for(name, version)?;
     if cache_path.exists() {
         return std::fs::read(&cache_path)
@@ -161,7 +185,7 @@ fn load_bytes(name: &str, version: &str) -> Result<Vec<u8>> {
 /// concurrent splicer processes that both miss the cache can't observe
 /// a torn write. The `.<pid>.tmp` suffix isolates per-process tempfiles
 /// in case the rename races; the loser's tempfile is harmless leftover.
-fn write_cache_atomically(path: &Path, bytes: &[u8]) -> Result<()> {
+pub(crate) fn write_cache_atomically(path: &Path, bytes: &[u8]) -> Result<()> {
     let tmp = path.with_extension(format!("wasm.{}.tmp", std::process::id()));
     std::fs::write(&tmp, bytes)
         .with_context(|| format!("Failed to write cache tempfile: {}", tmp.display()))?;
@@ -176,16 +200,21 @@ fn write_cache_atomically(path: &Path, bytes: &[u8]) -> Result<()> {
 }
 
 fn cache_path_for(name: &str, version: &str) -> Result<PathBuf> {
-    let base = user_cache_dir().context(
+    let base = cache_root().context(
         "no user cache directory available; \
          set SPLICER_BUILTINS_DIR to a directory of pre-built .wasm files",
     )?;
     Ok(base
-        .join("splicer")
         .join(BUILTIN_SUBDIR)
         .join(format!("{name}@{version}.wasm")))
 }
 
+/// Root of splicer's on-disk caches: `<user-cache>/splicer`. Builtins
+/// live under `builtins/`, generated adapters under `adapters/`.
+pub(crate) fn cache_root() -> Option<PathBuf> {
+    user_cache_dir().map(|base| base.join("splicer"))
+}
+
 /// User cache directory: `$XDG_CACHE_HOME` or `~/.cache` on Unix,
 /// `%LOCALAPPDATA%` on Windows.
 fn user_cache_dir() -> Option<PathBuf> {
@@ -261,7 +290,7 @@ mod tests {
     #[test]
     fn unknown_builtin_lists_available() {
         let tmp = tempfile::tempdir().unwrap();
-        let err = materialize_into(tmp.path(), "no-such").unwrap_err();
+        let err = materialize_into(&mut Artifacts::on_disk(), tmp.path(), "no-such").unwrap_err();
         let msg = format!("{err:#}");
         assert!(msg.contains("unknown builtin 'no-such'"), "{msg}");
         assert!(msg.contains("hello-tier1"), "{msg}");
@@ -274,7 +303,7 @@ mod tests {
     fn local_override_short_circuits_fetch() {
         let splits_dir = tempfile::tempdir().unwrap();
         let path = with_fake_builtins(&["hello-tier1"], || {
-            materialize_into(splits_dir.path(), "hello-tier1")
+            materialize_into(&mut Artifacts::on_disk(), splits_dir.path(), "hello-tier1")
         })
         .expect("materialize");
 
@@ -282,11 +311,30 @@ mod tests {
         assert_eq!(std::fs::read(&path).unwrap(), FAKE_BUILTIN_WASM);
     }
 
+    /// With an in-memory store the builtin lands in the package map
+    /// only — nothing is written under splits_dir.
+    #[test]
+    fn in_memory_materialize_skips_disk() {
+        let splits_dir = tempfile::tempdir().unwrap();
+        let mut artifacts = Artifacts::in_memory();
+        let path = with_fake_builtins(&["hello-tier1"], || {
+            materialize_into(&mut artifacts, splits_dir.path(), "hello-tier1")
+        })
+        .expect("materialize");
+
+        assert!(!path.exists());
+        assert!(!splits_dir.path().join(BUILTIN_SUBDIR).exists());
+        let bytes = artifacts.read(path.to_str().unwrap()).unwrap();
+        assert_eq!(&*bytes, FAKE_BUILTIN_WASM);
+    }
+
     #[test]
     fn local_override_missing_file_errors_clearly() {
         let splits_dir = tempfile::tempdir().unwrap();
-        let err = with_fake_builtins(&[], || materialize_into(splits_dir.path(), "hello-tier1"))
-            .unwrap_err();
+        let err = with_fake_builtins(&[], || {
+            materialize_into(&mut Artifacts::on_disk(), splits_dir.path(), "hello-tier1")
+        })
+        .unwrap_err();
         let msg = format!("{err:#}");
         assert!(msg.contains(LOCAL_OVERRIDE_ENV), "{msg}");
         assert!(msg.contains("hello-tier1.wasm"), "{msg}");
@@ -299,7 +347,8 @@ mod tests {
     #[ignore = "hits ghcr.io"]
     fn fetch_from_registry_smoke() {
         let splits = tempfile::tempdir().unwrap();
-        let path = materialize_into(splits.path(), "hello-tier1").expect("fetch");
+        let path = materialize_into(&mut Artifacts::on_disk(), splits.path(), "hello-tier1")
+            .expect("fetch");
         let bytes = std::fs::read(&path).unwrap();
         assert!(
             bytes.starts_with(b"\0asm"),
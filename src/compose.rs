@@ -1,9 +1,10 @@
 use anyhow::{bail, Result};
 use cviz::model::{ComponentNode, CompositionGraph, ExportInfo, InterfaceConnection};
-use cviz::parse::component::{parse_component, parse_component_imports};
-use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 
+use crate::parse_cache::ParseCache;
+
 /// Parse N individual Wasm components and synthesize a [`CompositionGraph`] by
 /// matching their exports to each other's imports.
 ///
@@ -19,6 +20,15 @@ use std::path::{Path, PathBuf};
 ///   `wac compose --dep` arguments without needing a split pass.
 pub fn build_graph_from_components(
     components: &[(String, PathBuf, Vec<u8>)],
+) -> Result<(CompositionGraph, HashMap<u32, PathBuf>)> {
+    build_graph_in(components, &ParseCache::default())
+}
+
+/// [`build_graph_from_components`], parsing each component through
+/// `parsed` so later stages of the same run reuse the parses.
+pub(crate) fn build_graph_in(
+    components: &[(String, PathBuf, Vec<u8>)],
+    parsed: &ParseCache,
 ) -> Result<(CompositionGraph, HashMap<u32, PathBuf>)> {
     let n = components.len();
 
@@ -48,8 +58,9 @@ pub fn build_graph_from_components(
         path: PathBuf,
         /// Variable-friendly name (alias or stem).
         name: String,
-        /// Interface names this component exports.
-        exports: Vec<String>,
+        /// Interface names this component exports, with their type
+        /// fingerprints (used to type-check the importers).
+        exports: BTreeMap<String, Option<String>>,
         /// (interface_name, fingerprint) pairs for each instance-kind import.
         imports: Vec<(String, Option<String>)>,
     }
@@ -57,10 +68,14 @@ pub fn build_graph_from_components(
     let mut comp_infos: Vec<CompInfo> = Vec::with_capacity(n);
 
     for (name, path, bytes) in components {
-        let graph = parse_component(bytes)?;
-        let imports = parse_component_imports(bytes)?;
+        let graph = parsed.graph(bytes)?;
+        let imports = parsed.imports(bytes)?.as_ref().clone();
 
-        let exports: Vec<String> = graph.component_exports.keys().cloned().collect();
+        let exports = graph
+            .component_exports
+            .iter()
+            .map(|(name, export)| (name.clone(), export.fingerprint.clone()))
+            .collect();
 
         comp_infos.push(CompInfo {
             path: path.clone(),
@@ -73,7 +88,7 @@ pub fn build_graph_from_components(
     // ── 2. Build export index: interface → component index ───────────────────
     let mut export_index: HashMap<String, usize> = HashMap::new();
     for (comp_idx, info) in comp_infos.iter().enumerate() {
-        for export in &info.exports {
+        for export in info.exports.keys() {
             if export_index.insert(export.clone(), comp_idx).is_some() {
                 bail!(
                     "Ambiguous composition: multiple components export '{}'. \
@@ -95,13 +110,6 @@ pub fn build_graph_from_components(
     let mut resolved: Vec<Vec<ResolvedImport>> = (0..n).map(|_| Vec::new()).collect();
     let mut unresolved: Vec<Vec<String>> = (0..n).map(|_| Vec::new()).collect();
 
-    // We need per-component export fingerprints for type checking here.
-    // Parse once up-front so we can look up fingerprints without re-parsing later.
-    let parsed_graphs: Vec<CompositionGraph> = components
-        .iter()
-        .map(|(_, _, bytes)| parse_component(bytes))
-        .collect::<Result<_>>()?;
-
     for (comp_idx, info) in comp_infos.iter().enumerate() {
         for (import_name, import_fp) in &info.imports {
             match export_index.get(import_name) {
@@ -109,10 +117,7 @@ pub fn build_graph_from_components(
                     // Type-check: compare the importer's fingerprint against the
                     // exporter's fingerprint.  Both must be Some for a hard error;
                     // if either is None we emit a warning but still proceed.
-                    let export_fp = parsed_graphs[provider_idx]
-                        .component_exports
-                        .get(import_name)
-                        .and_then(|e| e.fingerprint.clone());
+                    let export_fp = comp_infos[provider_idx].exports[import_name].clone();
 
                     match (import_fp, &export_fp) {
                         (Some(ifp), Some(efp)) if ifp != efp => {
@@ -207,10 +212,7 @@ pub fn build_graph_from_components(
             // Use the import-side fingerprint — already verified to match the exporter's.
             // Fall back to the exporter's fingerprint if the importer's was None.
             let fingerprint = res_import.import_fingerprint.clone().or_else(|| {
-                parsed_graphs[res_import.provider_comp_idx]
-                    .component_exports
-                    .get(&res_import.interface_name)
-                    .and_then(|e| e.fingerprint.clone())
+                comp_infos[res_import.provider_comp_idx].exports[&res_import.interface_name].clone()
             });
 
             node.add_import(InterfaceConnection {
@@ -246,18 +248,13 @@ pub fn build_graph_from_components(
 
     for (topo_pos, &comp_idx) in topo_order.iter().enumerate() {
         let node_id = topo_pos as u32;
-        for export_name in &comp_infos[comp_idx].exports {
+        for (export_name, fingerprint) in &comp_infos[comp_idx].exports {
             if !internally_consumed.contains(export_name.as_str()) {
-                let fingerprint = parsed_graphs[comp_idx]
-                    .component_exports
-                    .get(export_name)
-                    .and_then(|e| e.fingerprint.clone());
-
                 graph.component_exports.insert(
                     export_name.clone(),
                     ExportInfo {
                         source_instance: node_id,
-                        fingerprint,
+                        fingerprint: fingerprint.clone(),
                         ty: None,
                     },
                 );
@@ -594,6 +591,22 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn parses_land_in_the_shared_cache() -> anyhow::Result<()> {
+        let comps = vec![
+            mk("provider-a.wasm", WAT_PROVIDER_A),
+            mk("consumer.wasm", WAT_SIMPLE_CONSUMER),
+        ];
+        let parsed = ParseCache::default();
+        build_graph_in(&comps, &parsed)?;
+        // Later stages asking for the same components reuse these parses.
+        for (_, _, bytes) in &comps {
+            parsed.graph(bytes)?;
+        }
+        assert_eq!(parsed.graph_parses(), 2);
+        Ok(())
+    }
+
     // ── Roundtrip tests ───────────────────────────────────────────────────────
     // Feed build_graph_from_components output into generate_wac, verifying the
     // full compose → WAC pipeline end-to-end.
@@ -788,9 +801,13 @@ mod tests {
         let rules = vec![SpliceRule::Before {
             interface: "my:providers/a@0.1.0".to_string(),
             provider_name: Some("provider-a".to_string()),
+            provider_instance: None,
+            consumer_name: None,
             provider_alias: None,
             inject: vec![Injection {
                 name: "a-middleware".to_string(),
+                instance: None,
+                deps: Vec::new(),
                 adapter_info: None,
                 builtin: None,
                 path: None,
@@ -862,11 +879,15 @@ mod tests {
         let mk_rule = |iface: &str, provider: &str, adapter_path: &str| SpliceRule::Before {
             interface: iface.to_string(),
             provider_name: Some(provider.to_string()),
+            provider_instance: None,
+            consumer_name: None,
             provider_alias: None,
             inject: vec![Injection {
                 name: "tracing".to_string(),
                 path: Some(mdl_path.clone()),
                 builtin: None,
+                instance: None,
+                deps: Vec::new(),
                 adapter_info: Some(AdapterInjectionInfo {
                     adapter_path: adapter_path.to_string(),
                     tier1_interfaces: vec![
@@ -1,9 +1,10 @@
+use crate::artifacts::Artifacts;
 use crate::parse::config::Injection;
 use anyhow::Context;
 use cviz::model::{compatible_fingerprints, ExportInfo};
-use cviz::parse::component::{parse_component, parse_component_imports};
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
-use std::fs;
+use std::fmt;
 
 // Generated by build.rs from wit/tier1/world.wit — single source of truth.
 include!(concat!(env!("OUT_DIR"), "/tier_interfaces.rs"));
@@ -16,7 +17,7 @@ pub fn versioned_interface(iface: &str, version: &str) -> String {
 }
 
 /// The outcome of a single middleware contract check.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ContractResult {
     /// The middleware exports the interface and the type fingerprints match.
     Ok,
@@ -26,7 +27,7 @@ pub enum ContractResult {
     Warn(String),
     /// The middleware exports the interface but with an incompatible type
     /// fingerprint.  Injection should be blocked.
-    Error(String),
+    Error(ContractViolation),
     /// The middleware does not export the target interface but does export at
     /// least one tier-1 type-erased interface (`splicer:tier1/{before,after,blocking}`).
     /// The inner list names the matched interfaces so the adapter generator knows
@@ -34,6 +35,35 @@ pub enum ContractResult {
     Tier1Compatible(Vec<String>),
 }
 
+/// A middleware whose export of the contracted interface doesn't match
+/// the target's type fingerprint. Carried by [`ContractResult::Error`]
+/// and [`crate::Error::Contract`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractViolation {
+    /// Name of the offending middleware injection.
+    pub middleware: String,
+    /// The interface being contracted on.
+    pub interface: String,
+    /// Fingerprint of the middleware's export of `interface`.
+    pub middleware_fingerprint: Option<String>,
+    /// Fingerprint the target provider exports.
+    pub target_fingerprint: Option<String>,
+}
+
+impl fmt::Display for ContractViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "incompatible type signatures for middleware '{}' on interface '{}'\n\t{}:\t{:?}\n\ttarget: {:?}",
+            self.middleware,
+            self.interface,
+            self.middleware,
+            self.middleware_fingerprint,
+            self.target_fingerprint
+        )
+    }
+}
+
 /// Check that every middleware in `to_inject` is type-compatible with the
 /// interface being contracted on.
 ///
@@ -44,11 +74,29 @@ pub fn validate_contract(
     interface_name: &str,
     contract_fingerprint: &Option<String>,
     checked_middlewares: &mut HashMap<String, BTreeMap<String, ExportInfo>>,
+) -> Vec<ContractResult> {
+    validate_contract_in(
+        to_inject,
+        interface_name,
+        contract_fingerprint,
+        checked_middlewares,
+        &Artifacts::on_disk(),
+    )
+}
+
+/// [`validate_contract`], reading middleware bytes through
+/// `artifacts` so in-memory builtins resolve without touching disk.
+pub(crate) fn validate_contract_in(
+    to_inject: &[Injection],
+    interface_name: &str,
+    contract_fingerprint: &Option<String>,
+    checked_middlewares: &mut HashMap<String, BTreeMap<String, ExportInfo>>,
+    artifacts: &Artifacts,
 ) -> Vec<ContractResult> {
     let mut results = vec![];
     for Injection { name, path, .. } in to_inject.iter() {
         if !checked_middlewares.contains_key(name.as_str()) {
-            match discover_middleware_exports(path) {
+            match discover_middleware_exports(path, artifacts) {
                 Ok(exports) => {
                     checked_middlewares.insert(name.clone(), exports);
                 }
@@ -64,14 +112,17 @@ pub fn validate_contract(
 
         if let Some(ExportInfo { fingerprint, .. }) = exports.get(interface_name) {
             if !compatible_fingerprints(contract_fingerprint, fingerprint) {
-                results.push(ContractResult::Error(format!(
-                    "incompatible type signatures for middleware '{}' on interface '{}'\n\t{name}:\t{fingerprint:?}\n\ttarget: {contract_fingerprint:?}",
-                    name, interface_name
-                )));
+                results.push(ContractResult::Error(ContractViolation {
+                    middleware: name.clone(),
+                    interface: interface_name.to_string(),
+                    middleware_fingerprint: fingerprint.clone(),
+                    target_fingerprint: contract_fingerprint.clone(),
+                }));
             } else {
                 results.push(ContractResult::Ok);
             }
-        } else if let Some(matched) = is_tier1_compatible(exports, path, interface_name) {
+        } else if let Some(matched) = is_tier1_compatible(exports, path, interface_name, artifacts)
+        {
             results.push(ContractResult::Tier1Compatible(matched));
         } else {
             results.push(ContractResult::Warn(format!(
@@ -142,6 +193,7 @@ fn is_tier1_compatible(
     exports: &BTreeMap<String, ExportInfo>,
     middleware_path: &Option<String>,
     target_interface: &str,
+    artifacts: &Artifacts,
 ) -> Option<Vec<String>> {
     // Match tier-1 interface names with semver-compatible version
     // checking. Export keys may be versioned (e.g.
@@ -164,10 +216,10 @@ fn is_tier1_compatible(
     let Some(path) = middleware_path else {
         return None;
     };
-    let Ok(buff) = fs::read(path) else {
+    let Ok(buff) = artifacts.read(path) else {
         return None;
     };
-    let Ok(imports) = parse_component_imports(&buff) else {
+    let Ok(imports) = artifacts.parsed().imports(&buff) else {
         return None;
     };
     if imports.iter().any(|(name, _)| name == target_interface) {
@@ -178,20 +230,36 @@ fn is_tier1_compatible(
 
 fn discover_middleware_exports(
     wasm_path: &Option<String>,
+    artifacts: &Artifacts,
 ) -> anyhow::Result<BTreeMap<String, ExportInfo>> {
     let Some(path) = wasm_path else {
         return Ok(BTreeMap::default());
     };
-    let buff = fs::read(path).with_context(|| format!("failed to read '{path}'"))?;
-    let graph = parse_component(&buff)
+    let buff = artifacts.read(path)?;
+    let graph = artifacts
+        .parsed()
+        .graph(&buff)
         .with_context(|| format!("failed to parse Wasm component '{path}'"))?;
-    Ok(graph.component_exports)
+    // `ExportInfo` isn't `Clone`; copy it out of the shared parse.
+    Ok(graph
+        .component_exports
+        .iter()
+        .map(|(name, export)| {
+            let export = ExportInfo {
+                source_instance: export.source_instance,
+                fingerprint: export.fingerprint.clone(),
+                ty: export.ty,
+            };
+            (name.clone(), export)
+        })
+        .collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use cviz::model::ExportInfo;
+    use cviz::parse::component::parse_component;
 
     // ── is_compatible_interface tests ─────────────────────────────────
 
@@ -270,6 +338,8 @@ mod tests {
     fn injection(name: &str) -> Injection {
         Injection {
             name: name.to_string(),
+            instance: None,
+            deps: Vec::new(),
             adapter_info: None,
             builtin: None,
             path: None,
@@ -492,6 +562,8 @@ mod tests {
 
         let inj = Injection {
             name: "mw".to_string(),
+            instance: None,
+            deps: Vec::new(),
             adapter_info: None,
             builtin: None,
             path: None,
@@ -535,6 +607,8 @@ mod tests {
 
         let inj = Injection {
             name: "mw".to_string(),
+            instance: None,
+            deps: Vec::new(),
             adapter_info: None,
             builtin: None,
             path: None,
@@ -0,0 +1,189 @@
+//! The typed [`Error`] returned by splicer's top-level entry points.
+//!
+//! Internally the pipeline threads `anyhow::Error` with context; the
+//! entry points in [`crate::api`] classify failures by pipeline stage
+//! on the way out. Stages that already know exactly what went wrong
+//! (e.g. the adapter generator rejecting a function shape) raise a
+//! typed variant at the source, which survives the trip back up
+//! through any `anyhow` context layers.
+//!
+//! Every variant's `Display` renders the full cause chain, so printing
+//! an [`Error`] is enough for user-facing reports. Match on the
+//! variant to decide whether a failure is worth retrying.
+
+use std::path::PathBuf;
+
+use crate::contract::ContractViolation;
+
+/// Convenience alias for results carrying a splicer [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Why a splicer entry point failed.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The splice rules are invalid: malformed YAML, or a rule that
+    /// fails validation.
+    #[error("invalid splice rules: {0:#}")]
+    Config(anyhow::Error),
+
+    /// The input bytes could not be parsed into a composition graph.
+    #[error("failed to parse composition graph: {0:#}")]
+    GraphParse(anyhow::Error),
+
+    /// Splitting the composition into sub-components failed, either
+    /// decoding the nested components or writing the splits out.
+    #[error("failed to split composition: {0:#}")]
+    Split(anyhow::Error),
+
+    /// An input file could not be read, or an output directory could
+    /// not be created.
+    #[error("failed to access '{}': {error}", path.display())]
+    Io {
+        /// The path splicer was reading or creating.
+        path: PathBuf,
+        /// The underlying I/O error.
+        error: std::io::Error,
+    },
+
+    /// One or more middleware export the target interface with an
+    /// incompatible type signature. Only raised when type checks are
+    /// not skipped.
+    #[error("contract type-check error: {}", .violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"))]
+    Contract {
+        /// Every violation found, in rule order.
+        violations: Vec<ContractViolation>,
+    },
+
+    /// The tier-1 adapter generator doesn't support the target
+    /// interface's shape.
+    #[error(
+        "cannot generate tier-1 adapter for {}`{interface}`: {reason}",
+        .function.as_deref().map(|f| format!("`{f}` on ")).unwrap_or_default()
+    )]
+    UnsupportedAdapter {
+        /// The interface the adapter would wrap.
+        interface: String,
+        /// The function that can't be adapted, when the problem is
+        /// specific to one.
+        function: Option<String>,
+        /// Human-readable explanation and suggested fix.
+        reason: String,
+    },
+
+    /// A builtin middleware could not be resolved to bytes (unknown
+    /// name, or its bytes could not be loaded).
+    #[error("failed to resolve builtin '{name}': {error:#}")]
+    Builtin {
+        /// The builtin name from the rule.
+        name: String,
+        /// What went wrong.
+        error: anyhow::Error,
+    },
+
+    /// Synthesizing a composition from individual components failed
+    /// (e.g. alias collisions or unresolvable imports).
+    #[error("failed to build composition: {0:#}")]
+    Compose(anyhow::Error),
+
+    /// Generating the WAC for the spliced composition failed (e.g. an
+    /// adapter component could not be built).
+    #[error("failed to generate WAC: {0:#}")]
+    WacGenerate(anyhow::Error),
+
+    /// The generated WAC could not be parsed or its packages could not
+    /// be resolved.
+    #[error("failed to resolve WAC: {0:#}")]
+    WacResolve(anyhow::Error),
+
+    /// The resolved WAC could not be encoded into a valid component.
+    #[error("failed to encode composed component: {0:#}")]
+    WacEncode(anyhow::Error),
+
+    /// A bundle manifest could not be written, or could not be parsed
+    /// when loaded back.
+    #[error("invalid bundle manifest: {0:#}")]
+    Manifest(anyhow::Error),
+
+    /// A `splicer-provenance` custom section could not be decoded.
+    #[error("invalid splicer provenance: {0:#}")]
+    Provenance(anyhow::Error),
+
+    /// A rule would inject middleware the composition already carries
+    /// from an earlier splice. Only raised when
+    /// [`crate::SpliceRequest::reject_existing`] is set; otherwise the
+    /// injection is skipped.
+    #[error("middleware '{name}' is already injected on `{interface}`")]
+    AlreadyInjected {
+        /// The injection's name.
+        name: String,
+        /// The interface the rule targets.
+        interface: String,
+    },
+
+    /// The selected middleware couldn't be removed from a composition
+    /// (none found, an unknown name, or an instance that provides an
+    /// interface rather than forwarding it).
+    #[error("failed to unsplice composition: {0:#}")]
+    Unsplice(anyhow::Error),
+
+    /// A package a bundle references no longer has the content it had
+    /// when the bundle was produced (e.g. the splits dir was
+    /// regenerated since the manifest was written).
+    #[error(
+        "stale package '{package}' at '{}': expected {expected}, found {actual}",
+        path.display()
+    )]
+    StaleDependency {
+        /// The WAC package key.
+        package: String,
+        /// Where the package's bytes were read from.
+        path: PathBuf,
+        /// The content hash the bundle recorded.
+        expected: String,
+        /// The content hash of the bytes on disk now.
+        actual: String,
+    },
+}
+
+impl Error {
+    /// Recover a typed [`Error`] raised deeper in the pipeline, or
+    /// classify `err` with `stage` when none was raised.
+    pub(crate) fn classify(err: anyhow::Error, stage: fn(anyhow::Error) -> Error) -> Error {
+        match err.downcast::<Error>() {
+            Ok(typed) => typed,
+            Err(err) => stage(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    /// A typed error raised deep in the pipeline survives `anyhow`
+    /// context layers and is recovered as-is.
+    #[test]
+    fn classify_recovers_typed_errors_through_context() {
+        let raised: anyhow::Result<()> = Err(Error::UnsupportedAdapter {
+            interface: "my:pkg/api".into(),
+            function: Some("run".into()),
+            reason: "nope".into(),
+        }
+        .into());
+        let err = raised.context("outer layer").unwrap_err();
+        assert!(matches!(
+            Error::classify(err, Error::WacGenerate),
+            Error::UnsupportedAdapter { function: Some(f), .. } if f == "run"
+        ));
+    }
+
+    #[test]
+    fn classify_falls_back_to_stage() {
+        let err = anyhow::anyhow!("disk full");
+        let typed = Error::classify(err, Error::Split);
+        assert!(matches!(typed, Error::Split(_)));
+        assert_eq!(typed.to_string(), "failed to split composition: disk full");
+    }
+}
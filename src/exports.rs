@@ -0,0 +1,118 @@
+//! Rules on the composition's outer surface: hiding an export,
+//! renaming one, and holding what's left to an allowlist.
+//!
+//! These don't touch any edge inside the composition, so like
+//! replacements they run on the finished [`CompositionPlan`], once
+//! every injection is planned. `hide_export` and `rename_export` apply
+//! in rule order, each matching an export by its name at that point;
+//! `allow_exports` checks the exports they leave behind.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+
+use crate::parse::config::SpliceRule;
+use crate::plan::{CompositionPlan, PlanItem};
+
+/// Apply the export rules in `rules` to `plan`, whose component
+/// instances are bound as in `instance_vars`. A component that only a
+/// hidden export kept in use is dropped, and removed from
+/// `used_comp_nodes`.
+///
+/// Fails when a rename would export two things under one name, or
+/// when an export outside an `allow_exports` list remains.
+pub(crate) fn apply_export_rules(
+    plan: &mut CompositionPlan,
+    rules: &[SpliceRule],
+    instance_vars: &HashMap<u32, String>,
+    used_comp_nodes: &mut HashMap<u32, String>,
+) -> Result<()> {
+    for (rule_idx, rule) in rules.iter().enumerate() {
+        match rule {
+            SpliceRule::HideExport { interface } => {
+                let mut sources = Vec::new();
+                plan.items.retain(|item| match item {
+                    PlanItem::Export(export) if export.exported_name() == interface => {
+                        sources.push(export.source.clone());
+                        false
+                    }
+                    _ => true,
+                });
+                if sources.is_empty() {
+                    warn_unmatched(plan, rule_idx, interface, "hidden");
+                }
+                plan.prune_unused(sources);
+            }
+            SpliceRule::RenameExport { interface, to } => {
+                if exported_names(plan).any(|name| name == to) {
+                    bail!(
+                        "rule {}: can't rename export `{interface}` to `{to}`, which the \
+                         composition already exports",
+                        rule_idx + 1
+                    );
+                }
+                let export = plan.items.iter_mut().find_map(|item| match item {
+                    PlanItem::Export(export) if export.exported_name() == interface => Some(export),
+                    _ => None,
+                });
+                match export {
+                    Some(export) => {
+                        export.rename = (*to != export.name).then(|| to.clone());
+                    }
+                    None => warn_unmatched(plan, rule_idx, interface, "renamed"),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (rule_idx, rule) in rules.iter().enumerate() {
+        let SpliceRule::AllowExports { interfaces } = rule else {
+            continue;
+        };
+        let exposed: Vec<&str> = exported_names(plan)
+            .filter(|name| !interfaces.iter().any(|allowed| allowed == name))
+            .collect();
+        if !exposed.is_empty() {
+            bail!(
+                "rule {}: the composition still exports {}, which `allow_exports` doesn't \
+                 list; hide them with `hide_export` or add them to the allowlist",
+                rule_idx + 1,
+                exposed
+                    .iter()
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    used_comp_nodes.retain(|node, _| {
+        instance_vars
+            .get(node)
+            .is_some_and(|var| plan.has_instance(var))
+    });
+    Ok(())
+}
+
+/// The names the composition in `plan` exports.
+fn exported_names(plan: &CompositionPlan) -> impl Iterator<Item = &str> {
+    plan.items.iter().filter_map(|item| match item {
+        PlanItem::Export(export) => Some(export.exported_name()),
+        _ => None,
+    })
+}
+
+/// Warn that the rule at `rule_idx` found no export named `interface`
+/// to leave `done`.
+fn warn_unmatched(plan: &CompositionPlan, rule_idx: usize, interface: &str, done: &str) {
+    eprintln!(
+        "{}: rule {} — the composition doesn't export '{}'; nothing was {done}.\n\
+         \t  Exports: [{}]",
+        "WARN".yellow().bold(),
+        rule_idx + 1,
+        interface,
+        exported_names(plan).collect::<Vec<_>>().join(", ")
+    );
+}
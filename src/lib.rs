@@ -18,14 +18,8 @@
 //!
 //! ```no_run
 //! # fn main() -> anyhow::Result<()> {
-//! let rules_yaml = std::fs::read_to_string("splice.yaml")?;
-//! let bundle = splicer::splice(splicer::SpliceRequest {
-//!     composition_wasm: "composition.wasm".into(),
-//!     rules_yaml,
-//!     package_name: "example:composition".into(),
-//!     splits_dir: "./splits".into(),
-//!     skip_type_check: false,
-//! })?;
+//! let rules = splicer::parse_yaml(&std::fs::read_to_string("splice.yaml")?)?;
+//! let bundle = splicer::splice(splicer::SpliceRequest::new("composition.wasm", rules))?;
 //!
 //! // Compose to a single Wasm component, in-process — no shelling out.
 //! let composed: Vec<u8> = bundle.to_wasm()?;
@@ -41,6 +35,27 @@
 //! # }
 //! ```
 //!
+//! # Building rules in code
+//!
+//! Rules don't have to come from YAML. [`types::Rule`] builds a
+//! [`types::SpliceRule`] fluently and runs the same validation a YAML
+//! config goes through:
+//!
+//! ```
+//! use splicer::types::{Injection, Rule};
+//!
+//! let rules = vec![
+//!     Rule::before("wasi:http/handler@0.3.0-rc-2026-01-06")
+//!         .provider("auth")
+//!         .inject(Injection::from_builtin("otel-bare-spans"))
+//!         .build()?,
+//!     Rule::between("wasi:http/handler@0.3.0-rc-2026-01-06", "auth-backend", "auth")
+//!         .inject(Injection::from_path("tracing", "./tracing.wasm"))
+//!         .build()?,
+//! ];
+//! # Ok::<(), splicer::Error>(())
+//! ```
+//!
 //! # Want to drive `wac compose` yourself?
 //!
 //! [`Bundle::wac`] and [`Bundle::wac_deps`] expose the raw inputs.
@@ -51,9 +66,84 @@
 //! — the keys are fully-qualified WAC package keys, the values are
 //! `PathBuf`s, no translation step required.
 //!
-//! For finer control over the in-process path (e.g. a custom
-//! filesystem search base for unresolved package references), reach
-//! for [`compose_wac`].
+//! The WAC is rendered from a typed plan, [`Bundle::plan`]
+//! ([`lowlevel::CompositionPlan`]): the same instances and wiring,
+//! each tagged with the input node or rule that produced it.
+//! [`Bundle::to_wasm`] builds the composition graph from the plan
+//! directly rather than re-parsing the WAC, so a wiring error names
+//! the rule behind it; it falls back to the WAC text if you've edited
+//! [`Bundle::wac`]. For auditing,
+//! [`CompositionPlan::to_annotated_wac`](lowlevel::CompositionPlan::to_annotated_wac)
+//! renders the WAC with a comment above each instance saying where it
+//! came from (the CLI's `--annotate-wac`). With
+//! [`SpliceRequest::strict_wiring`] the plan lists every import of
+//! every instance instead of ending each with WAC's `...`; imports
+//! nothing provides become explicit top-level `import`s.
+//!
+//! For finer control over the in-process path (e.g. search
+//! directories or a local OCI cache for package references the bundle
+//! doesn't cover), reach for [`Bundle::to_wasm_with`] or
+//! [`compose_wac_with`] and a [`packages::PackageSource`].
+//!
+//! # Deferred composition
+//!
+//! A [`Bundle`] can be saved as a JSON manifest with [`Bundle::save`]
+//! and composed later with [`Bundle::load`] + [`Bundle::to_wasm`]
+//! (the `splicer apply` subcommand). The manifest records a content
+//! hash per package, so a bundle whose splits changed since it was
+//! saved fails with [`Error::StaleDependency`] instead of composing
+//! different bytes.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! # let bundle: splicer::Bundle = unimplemented!();
+//! bundle.save("out/bundle.json".as_ref())?;
+//! // ...later, possibly elsewhere:
+//! let composed = splicer::Bundle::load("out/bundle.json".as_ref())?.to_wasm()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Provenance
+//!
+//! Set [`SpliceRequest::provenance`] (or [`ComposeRequest::provenance`])
+//! and [`Bundle::to_wasm`] embeds a `splicer-provenance` custom section
+//! in the outer component: the splicer version, the input's hash, the
+//! normalized rules, and a digest of every injected middleware and
+//! generated adapter. [`read_provenance`] decodes it again.
+//!
+//! # Removing middleware
+//!
+//! [`unsplice`] rebuilds a spliced composition without some or all of
+//! its middleware (the `splicer unsplice` subcommand), rewiring each
+//! consumer back to the removed instance's upstream. Provenance is how
+//! it finds injections by name or builtin; without it, only tier-1
+//! middleware (recognized by its adapters' instance names) is found
+//! by [`MiddlewareSelector::All`].
+//!
+//! # Errors
+//!
+//! The top-level entry points return [`Error`], which classifies the
+//! failure by pipeline stage — bad rules, an unparseable composition,
+//! split I/O, contract violations (with both type fingerprints),
+//! unsupported tier-1 adapter shapes, builtin resolution, and WAC
+//! resolve/encode failures:
+//!
+//! ```no_run
+//! # let composition: Vec<u8> = Vec::new();
+//! # let rules: Vec<splicer::types::SpliceRule> = Vec::new();
+//! match splicer::splice_bytes(&composition, &rules) {
+//!     Ok(composed) => { /* ... */ }
+//!     Err(splicer::Error::Contract { violations }) => {
+//!         for v in &violations {
+//!             eprintln!("{} can't wrap {}", v.middleware, v.interface);
+//!         }
+//!     }
+//!     Err(other) => eprintln!("{other}"),
+//! }
+//! ```
+//!
+//! The [`lowlevel`] building blocks still return `anyhow::Result`.
 //!
 //! # Side effects on disk
 //!
@@ -64,17 +154,51 @@
 //!   `splicer_adapter_*.wasm` files alongside them (the adapter
 //!   generator). Adapter paths are surfaced in
 //!   [`Bundle::generated_adapters`] and [`Bundle::wac_deps`].
+//! - [`compose`] writes nothing unless [`ComposeRequest::rules`] is
+//!   non-empty, in which case generated adapters and materialized
+//!   builtins go to [`ComposeRequest::splits_dir`].
 //! - Neither function writes the generated WAC source — that's
 //!   returned in [`Bundle::wac`] for the caller to use however they
 //!   like (typically by passing the bundle to [`Bundle::to_wasm`]).
+//!
+//! # In-memory splicing
+//!
+//! Services that splice on the fly can skip the filesystem entirely:
+//! [`splice_bytes`] takes the composition bytes plus typed rules and
+//! returns the composed component. Splits, adapters and builtins stay
+//! in an in-memory package map; only middleware referenced by `path:`
+//! is read from disk. [`compose_wac_bytes`] is the matching
+//! counterpart to [`compose_wac`] for callers that already hold every
+//! package's bytes.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! # let composition: Vec<u8> = Vec::new();
+//! # let rules: Vec<splicer::types::SpliceRule> = Vec::new();
+//! let composed: Vec<u8> = splicer::splice_bytes(&composition, &rules)?;
+//! # Ok(())
+//! # }
+//! ```
 
 mod adapter;
 mod api;
+mod artifacts;
 mod builtins;
 mod compose;
 mod contract;
+mod error;
+mod exports;
+mod middleware_deps;
+mod nested;
+pub mod packages;
 mod parse;
+mod parse_cache;
+mod plan;
+mod provenance;
+mod replace;
 mod split;
+mod strict;
+mod unsplice;
 mod wac;
 
 #[cfg(test)]
@@ -82,9 +206,13 @@ mod tests;
 
 // ── Top-level entry points ────────────────────────────────────────
 pub use api::{
-    compose, compose_wac, format_wac_compose_cmd, splice, Bundle, ComponentInput, ComposeRequest,
-    SpliceRequest,
+    compose, compose_wac, compose_wac_bytes, compose_wac_with, format_wac_compose_cmd, parse_yaml,
+    splice, splice_bytes, unsplice, Bundle, ComponentInput, ComposeRequest, SpliceRequest,
+    UnspliceRequest,
 };
+pub use error::{Error, Result};
+pub use provenance::read_provenance;
+pub use unsplice::MiddlewareSelector;
 
 /// Re-export so consumers pick up the exact cviz version splicer
 /// links against, avoiding version-skew on shared types.
@@ -96,10 +224,14 @@ pub use ::cviz;
 /// import directly.
 pub mod types {
     pub use crate::contract::{
-        ContractResult, TIER1_AFTER, TIER1_BEFORE, TIER1_BLOCKING, TIER1_INTERFACES, TIER1_PACKAGE,
-        TIER1_VERSION,
+        ContractResult, ContractViolation, TIER1_AFTER, TIER1_BEFORE, TIER1_BLOCKING,
+        TIER1_INTERFACES, TIER1_PACKAGE, TIER1_VERSION,
+    };
+    pub use crate::parse::builder::{BeforeRule, BetweenRule, ExportRule, ReplaceRule, Rule};
+    pub use crate::parse::config::{Injection, InstanceMode, MiddlewareDep, SpliceRule};
+    pub use crate::provenance::{
+        AdapterProvenance, InjectedMiddleware, Provenance, PROVENANCE_SECTION,
     };
-    pub use crate::parse::config::{Injection, SpliceRule};
     pub use crate::wac::GeneratedAdapter;
 }
 
@@ -118,7 +250,15 @@ pub mod lowlevel {
     pub use crate::contract::{
         validate_contract, versioned_interface, ContractResult, TIER1_INTERFACES,
     };
-    pub use crate::parse::config::{parse_yaml, Injection, SpliceRule};
-    pub use crate::split::{gen_split_path, split_out_composition, PATH_TO_SPLITS};
+    pub use crate::parse::config::{
+        parse_yaml, validate_rules, Injection, InstanceMode, MiddlewareDep, SpliceRule,
+    };
+    pub use crate::plan::{
+        CompositionPlan, InstanceOrigin, PlanItem, PlannedArg, PlannedExport, PlannedImport,
+        PlannedInstance,
+    };
+    #[allow(deprecated)]
+    pub use crate::split::gen_split_path;
+    pub use crate::split::{split_out_composition, SplitPaths, PATH_TO_SPLITS, SPLIT_MANIFEST};
     pub use crate::wac::{generate_wac, GeneratedAdapter, WacOutput, INST_PREFIX};
 }
@@ -1,15 +1,20 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Parser, Subcommand};
 use colored::Colorize;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use splicer::types::ContractResult;
-use splicer::{compose, splice, Bundle, ComponentInput, ComposeRequest, SpliceRequest};
+use splicer::packages::SearchPaths;
+use splicer::types::{ContractResult, SpliceRule, PROVENANCE_SECTION};
+use splicer::{
+    compose, parse_yaml, read_provenance, splice, unsplice, Bundle, ComponentInput, ComposeRequest,
+    MiddlewareSelector, SpliceRequest, UnspliceRequest,
+};
 
 const DEFAULT_PKG: &str = "example:composition";
 const DEFAULT_OUTPUT_WASM: &str = "composed.wasm";
 const DEFAULT_OUTPUT_WAC: &str = "output.wac";
+const DEFAULT_MANIFEST: &str = "bundle.json";
 const DEFAULT_SPLITS_DIR: &str = "./splits";
 
 #[derive(Parser, Debug)]
@@ -54,11 +59,35 @@ enum Command {
         )]
         emit_wac: Option<PathBuf>,
 
+        /// Comment the emitted WAC for auditing: which rule and
+        /// injection produced each middleware/adapter instance, which
+        /// node and split each component instance came from.
+        #[arg(long, default_value_t = false)]
+        annotate_wac: bool,
+
+        /// Save the bundle as a JSON manifest that `splicer apply` can
+        /// compose later. Bare flag uses ./bundle.json; pass a path to
+        /// override. Implies keeping generated files on disk.
+        #[arg(
+            long = "emit-manifest",
+            value_name = "PATH",
+            num_args = 0..=1,
+            default_missing_value = DEFAULT_MANIFEST,
+        )]
+        emit_manifest: Option<PathBuf>,
+
         /// Skip in-process compose. Persist the WAC + splits and print
         /// the equivalent `wac compose ...` shell command to stdout.
         #[arg(long)]
         plan: bool,
 
+        /// Directory to search for packages the bundle doesn't provide
+        /// (middleware injected without a `path:`), laid out as
+        /// `DIR/<ns>/<name>.wasm` like `wac compose --deps-dir`.
+        /// Repeatable; searched in order.
+        #[arg(long = "deps-dir", value_name = "DIR")]
+        deps_dirs: Vec<PathBuf>,
+
         /// Directory where split sub-components are written. When
         /// omitted, splits go to a tempdir (cleaned up on success);
         /// passing this flag persists them on disk.
@@ -74,6 +103,21 @@ enum Command {
         /// verified.
         #[arg(long, default_value_t = false)]
         skip_type_check: bool,
+
+        /// Embed a `splicer-provenance` custom section recording the
+        /// rules and middleware digests (see `splicer inspect`).
+        #[arg(long, default_value_t = false)]
+        provenance: bool,
+
+        /// Fail instead of skipping when a rule would inject middleware
+        /// the composition already carries from an earlier splice.
+        #[arg(long, default_value_t = false)]
+        reject_existing: bool,
+
+        /// Wire every import explicitly in the generated WAC (no `...`);
+        /// unsatisfied imports become top-level `import`s.
+        #[arg(long, default_value_t = false)]
+        strict_wiring: bool,
     },
 
     /// Synthesize a composition from N individual Wasm components.
@@ -107,15 +151,185 @@ enum Command {
         )]
         emit_wac: Option<PathBuf>,
 
+        /// Comment the emitted WAC for auditing: which rule and
+        /// injection produced each middleware/adapter instance, which
+        /// node and split each component instance came from.
+        #[arg(long, default_value_t = false)]
+        annotate_wac: bool,
+
+        /// Save the bundle as a JSON manifest that `splicer apply` can
+        /// compose later. Bare flag uses ./bundle.json; pass a path to
+        /// override. Implies keeping generated files on disk.
+        #[arg(
+            long = "emit-manifest",
+            value_name = "PATH",
+            num_args = 0..=1,
+            default_missing_value = DEFAULT_MANIFEST,
+        )]
+        emit_manifest: Option<PathBuf>,
+
         /// Skip in-process compose. Persist the WAC and print the
         /// equivalent `wac compose ...` shell command to stdout.
         #[arg(long)]
         plan: bool,
 
+        /// Directory to search for packages the bundle doesn't provide
+        /// (middleware injected without a `path:`), laid out as
+        /// `DIR/<ns>/<name>.wasm` like `wac compose --deps-dir`.
+        /// Repeatable; searched in order.
+        #[arg(long = "deps-dir", value_name = "DIR")]
+        deps_dirs: Vec<PathBuf>,
+
+        /// Package name written at the top of the generated WAC.
+        #[arg(long, default_value = DEFAULT_PKG)]
+        package: String,
+
+        /// Splice configuration (YAML) to apply to the composition as
+        /// it's built — one-shot `compose` + `splice`, without
+        /// splitting the intermediate composition.
+        #[arg(long = "rules", value_name = "SPLICE_CFG")]
+        rules: Option<PathBuf>,
+
+        /// Directory where generated adapters and builtins are written
+        /// when `--rules` is given. When omitted, they go to a tempdir
+        /// (cleaned up on success).
+        #[arg(short = 'd', long = "splits-dir", value_name = "DIR")]
+        splits_dir: Option<PathBuf>,
+
+        /// Demote type-incompatibility errors from `--rules` to warnings.
+        #[arg(long, default_value_t = false)]
+        skip_type_check: bool,
+
+        /// Embed a `splicer-provenance` custom section recording the
+        /// `--rules` applied (see `splicer inspect`).
+        #[arg(long, default_value_t = false)]
+        provenance: bool,
+
+        /// Wire every import explicitly in the generated WAC (no `...`);
+        /// unsatisfied imports become top-level `import`s.
+        #[arg(long, default_value_t = false)]
+        strict_wiring: bool,
+    },
+
+    /// Remove previously injected middleware from a spliced composition.
+    ///
+    /// Finds the middleware through the `splicer-provenance` section
+    /// when the composition has one (otherwise only `--middleware`, or
+    /// `--all` for tier-1 middleware, can find it), rewires each
+    /// consumer back to the middleware's upstream, and re-composes.
+    #[command(group(
+        ArgGroup::new("remove")
+            .required(true)
+            .args(["all", "middleware", "builtin"]),
+    ))]
+    Unsplice {
+        /// Spliced Wasm component to remove middleware from.
+        #[arg(value_name = "COMP_WASM")]
+        comp_wasm: PathBuf,
+
+        /// Remove every injected middleware, restoring the original
+        /// wiring.
+        #[arg(long)]
+        all: bool,
+
+        /// Remove the injections with these names.
+        #[arg(long, value_name = "NAME", num_args = 1..)]
+        middleware: Vec<String>,
+
+        /// Remove every injection of this builtin (needs provenance).
+        #[arg(long, value_name = "NAME")]
+        builtin: Option<String>,
+
+        /// Path for the composed Wasm output (default: composed.wasm).
+        #[arg(short = 'o', long = "output", value_name = "PATH")]
+        output: Option<PathBuf>,
+
+        /// Persist the intermediate WAC source for debugging or auditing.
+        /// Bare flag uses ./output.wac; pass a path to override.
+        #[arg(
+            long = "emit-wac",
+            value_name = "PATH",
+            num_args = 0..=1,
+            default_missing_value = DEFAULT_OUTPUT_WAC,
+        )]
+        emit_wac: Option<PathBuf>,
+
+        /// Comment the emitted WAC for auditing: which rule and
+        /// injection produced each middleware/adapter instance, which
+        /// node and split each component instance came from.
+        #[arg(long, default_value_t = false)]
+        annotate_wac: bool,
+
+        /// Save the bundle as a JSON manifest that `splicer apply` can
+        /// compose later. Bare flag uses ./bundle.json; pass a path to
+        /// override. Implies keeping generated files on disk.
+        #[arg(
+            long = "emit-manifest",
+            value_name = "PATH",
+            num_args = 0..=1,
+            default_missing_value = DEFAULT_MANIFEST,
+        )]
+        emit_manifest: Option<PathBuf>,
+
+        /// Skip in-process compose. Persist the WAC + splits and print
+        /// the equivalent `wac compose ...` shell command to stdout.
+        #[arg(long)]
+        plan: bool,
+
+        /// Directory to search for packages the bundle doesn't provide
+        /// (middleware injected without a `path:`), laid out as
+        /// `DIR/<ns>/<name>.wasm` like `wac compose --deps-dir`.
+        /// Repeatable; searched in order.
+        #[arg(long = "deps-dir", value_name = "DIR")]
+        deps_dirs: Vec<PathBuf>,
+
+        /// Directory where split sub-components are written. When
+        /// omitted, splits go to a tempdir (cleaned up on success);
+        /// passing this flag persists them on disk.
+        #[arg(short = 'd', long = "splits-dir", value_name = "DIR")]
+        splits_dir: Option<PathBuf>,
+
         /// Package name written at the top of the generated WAC.
         #[arg(long, default_value = DEFAULT_PKG)]
         package: String,
     },
+
+    /// Compose a bundle saved with `--emit-manifest`.
+    ///
+    /// Checks every package the manifest references against the
+    /// content hash recorded when it was written, then composes them
+    /// into a single `.wasm`.
+    Apply {
+        /// Path to the bundle manifest (JSON).
+        #[arg(value_name = "MANIFEST")]
+        manifest: PathBuf,
+
+        /// Path for the composed Wasm output (default: composed.wasm).
+        #[arg(short = 'o', long = "output", value_name = "PATH")]
+        output: Option<PathBuf>,
+
+        /// Directory to search for packages the bundle doesn't provide
+        /// (middleware injected without a `path:`), laid out as
+        /// `DIR/<ns>/<name>.wasm` like `wac compose --deps-dir`.
+        /// Repeatable; searched in order.
+        #[arg(long = "deps-dir", value_name = "DIR")]
+        deps_dirs: Vec<PathBuf>,
+    },
+
+    /// Show the provenance splicer embedded in a composed component.
+    ///
+    /// Decodes the `splicer-provenance` custom section written by
+    /// `--provenance`: the splicer version, input hash, and every
+    /// injected middleware and generated adapter with its digest.
+    Inspect {
+        /// Composed Wasm component to inspect.
+        #[arg(value_name = "COMP_WASM")]
+        comp_wasm: PathBuf,
+
+        /// Print the raw provenance record as JSON.
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 fn main() -> Result<()> {
@@ -137,68 +351,156 @@ fn main() -> Result<()> {
             comp_wasm,
             output,
             emit_wac,
+            annotate_wac,
+            emit_manifest,
             plan,
+            deps_dirs,
             splits_dir,
             package,
             skip_type_check,
-        } => run_splice(
-            splice_cfg_file,
-            comp_wasm,
+            provenance,
+            reject_existing,
+            strict_wiring,
+        } => {
+            let outputs = Outputs {
+                output,
+                emit_wac,
+                annotate_wac: annotate_wac.then(|| comp_wasm.display().to_string()),
+                emit_manifest,
+                plan,
+                deps_dirs,
+            };
+            run_splice(
+                splice_cfg_file,
+                comp_wasm,
+                outputs,
+                splits_dir,
+                package,
+                SpliceFlags {
+                    skip_type_check,
+                    provenance,
+                    reject_existing,
+                    strict_wiring,
+                },
+            )
+        }
+
+        Command::Compose {
+            wasms,
             output,
             emit_wac,
+            annotate_wac,
+            emit_manifest,
             plan,
-            splits_dir,
+            deps_dirs,
             package,
+            rules,
+            splits_dir,
             skip_type_check,
-        ),
+            provenance,
+            strict_wiring,
+        } => {
+            let outputs = Outputs {
+                output,
+                emit_wac,
+                annotate_wac: annotate_wac.then(|| wasms.join(" ")),
+                emit_manifest,
+                plan,
+                deps_dirs,
+            };
+            run_compose(
+                wasms,
+                outputs,
+                package,
+                rules,
+                splits_dir,
+                ComposeFlags {
+                    skip_type_check,
+                    provenance,
+                    strict_wiring,
+                },
+            )
+        }
 
-        Command::Compose {
-            wasms,
+        Command::Unsplice {
+            comp_wasm,
+            all,
+            middleware,
+            builtin,
             output,
             emit_wac,
+            annotate_wac,
+            emit_manifest,
             plan,
+            deps_dirs,
+            splits_dir,
             package,
-        } => run_compose(wasms, output, emit_wac, plan, package),
+        } => {
+            // The arg group guarantees exactly one of these is set.
+            let remove = match builtin {
+                Some(name) => MiddlewareSelector::Builtin(name),
+                None if all => MiddlewareSelector::All,
+                None => MiddlewareSelector::Named(middleware),
+            };
+            let outputs = Outputs {
+                output,
+                emit_wac,
+                annotate_wac: annotate_wac.then(|| comp_wasm.display().to_string()),
+                emit_manifest,
+                plan,
+                deps_dirs,
+            };
+            run_unsplice(comp_wasm, remove, outputs, splits_dir, package)
+        }
+
+        Command::Apply {
+            manifest,
+            output,
+            deps_dirs,
+        } => run_apply(manifest, output, deps_dirs),
+
+        Command::Inspect { comp_wasm, json } => run_inspect(comp_wasm, json),
     }
 }
 
-#[allow(clippy::too_many_arguments)]
 fn run_splice(
     splice_cfg_file: PathBuf,
     comp_wasm: PathBuf,
-    output: Option<PathBuf>,
-    emit_wac: Option<PathBuf>,
-    plan: bool,
+    outputs: Outputs,
     splits_dir: Option<PathBuf>,
     package: String,
-    skip_type_check: bool,
+    flags: SpliceFlags,
 ) -> Result<()> {
-    let rules_yaml = fs::read_to_string(&splice_cfg_file)
-        .with_context(|| format!("Failed to read: {}", splice_cfg_file.display()))?;
+    let rules = read_rules(&splice_cfg_file)?;
 
     // Pick where splits live. Tempdir guard is held until after
     // to_wasm() runs (or leaked on failure / --plan).
-    let needs_persist = plan || emit_wac.is_some() || splits_dir.is_some();
+    let needs_persist = outputs.needs_persist() || splits_dir.is_some();
     let splits = SplitsLocation::resolve(splits_dir, needs_persist)?;
 
-    let bundle = splice(SpliceRequest {
-        composition_wasm: comp_wasm,
-        rules_yaml,
-        package_name: package,
-        splits_dir: splits.path().to_path_buf(),
-        skip_type_check,
-    })?;
+    let mut request = SpliceRequest::new(comp_wasm, rules);
+    request.package_name = package;
+    request.splits_dir = splits
+        .dir()
+        .expect("splice always resolves a splits dir")
+        .to_path_buf();
+    request.skip_type_check = flags.skip_type_check;
+    request.provenance = flags.provenance;
+    request.reject_existing = flags.reject_existing;
+    request.strict_wiring = flags.strict_wiring;
+    let bundle = splice(request)?;
     print_diagnostics(&bundle.diagnostics);
 
-    finish(bundle, output, emit_wac, plan, splits)
+    finish(bundle, outputs, splits)
 }
 
 fn run_compose(
     wasms: Vec<String>,
-    output: Option<PathBuf>,
-    emit_wac: Option<PathBuf>,
-    plan: bool,
+    outputs: Outputs,
     package: String,
+    rules_file: Option<PathBuf>,
+    splits_dir: Option<PathBuf>,
+    flags: ComposeFlags,
 ) -> Result<()> {
     let components: Vec<ComponentInput> = wasms
         .iter()
@@ -217,30 +519,177 @@ fn run_compose(
         })
         .collect();
 
-    let bundle = compose(ComposeRequest {
-        components,
-        package_name: package,
-    })?;
+    // A plain compose has no splits dir to manage; with `--rules`,
+    // generated adapters and builtins need one, and so do the WIT
+    // packages `--strict-wiring` types imports by.
+    let rules = rules_file.as_deref().map(read_rules).transpose()?;
+    let splits = if rules.is_some() || flags.strict_wiring {
+        let needs_persist = outputs.needs_persist() || splits_dir.is_some();
+        SplitsLocation::resolve(splits_dir, needs_persist)?
+    } else {
+        SplitsLocation::None
+    };
+
+    let mut request = ComposeRequest::new(components);
+    request.package_name = package;
+    request.rules = rules.unwrap_or_default();
+    request.splits_dir = splits.dir().map(Path::to_path_buf);
+    request.skip_type_check = flags.skip_type_check;
+    request.provenance = flags.provenance;
+    request.strict_wiring = flags.strict_wiring;
+    let bundle = compose(request)?;
     print_diagnostics(&bundle.diagnostics);
 
-    // Compose has no splits dir to manage.
-    finish(bundle, output, emit_wac, plan, SplitsLocation::None)
+    finish(bundle, outputs, splits)
 }
 
-/// Tail-end of both subcommands: write the WAC if requested, then
-/// either print the `--plan` shell command or run in-process compose
-/// and write the composed `.wasm`.
-fn finish(
-    bundle: Bundle,
+fn run_unsplice(
+    comp_wasm: PathBuf,
+    remove: MiddlewareSelector,
+    outputs: Outputs,
+    splits_dir: Option<PathBuf>,
+    package: String,
+) -> Result<()> {
+    let needs_persist = outputs.needs_persist() || splits_dir.is_some();
+    let splits = SplitsLocation::resolve(splits_dir, needs_persist)?;
+
+    let mut request = UnspliceRequest::new(comp_wasm, remove);
+    request.package_name = package;
+    request.splits_dir = splits
+        .dir()
+        .expect("unsplice always resolves a splits dir")
+        .to_path_buf();
+    let bundle = unsplice(request)?;
+
+    finish(bundle, outputs, splits)
+}
+
+fn run_apply(manifest: PathBuf, output: Option<PathBuf>, deps_dirs: Vec<PathBuf>) -> Result<()> {
+    let bundle = Bundle::load(&manifest)?;
+    let composed = bundle.to_wasm_with(&SearchPaths::new(deps_dirs))?;
+    write_composed(output, &composed)
+}
+
+fn run_inspect(comp_wasm: PathBuf, json: bool) -> Result<()> {
+    let bytes =
+        fs::read(&comp_wasm).with_context(|| format!("Failed to read: {}", comp_wasm.display()))?;
+    let Some(provenance) = read_provenance(&bytes)? else {
+        anyhow::bail!(
+            "{} has no {PROVENANCE_SECTION} section (was it spliced with --provenance?)",
+            comp_wasm.display()
+        );
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&provenance)?);
+        return Ok(());
+    }
+
+    println!("spliced by splicer {}", provenance.splicer_version);
+    if let Some(hash) = &provenance.input_hash {
+        println!("input:     {hash}");
+    }
+    println!("rules:     {}", provenance.rules.len());
+    println!("middleware:");
+    for mw in &provenance.middleware {
+        let origin = match &mw.builtin {
+            Some(builtin) => format!(" (builtin {builtin})"),
+            None => String::new(),
+        };
+        println!("  {}{origin} on {}", mw.name.bold(), mw.interface);
+        println!(
+            "    {}",
+            mw.digest
+                .as_deref()
+                .unwrap_or("<referenced by name>")
+                .dimmed()
+        );
+    }
+    if !provenance.adapters.is_empty() {
+        println!("adapters:");
+        for adapter in &provenance.adapters {
+            println!(
+                "  {} on {} [{}]",
+                adapter.middleware_name.bold(),
+                adapter.target_interface,
+                adapter.tier1_interfaces.join(", ")
+            );
+            println!("    {}", adapter.digest.dimmed());
+        }
+    }
+    Ok(())
+}
+
+fn read_rules(path: &Path) -> Result<Vec<SpliceRule>> {
+    let rules_yaml =
+        fs::read_to_string(path).with_context(|| format!("Failed to read: {}", path.display()))?;
+    parse_yaml(&rules_yaml).context("Failed to parse splice rules YAML")
+}
+
+/// Where `splice` / `compose` / `unsplice` write their results.
+struct Outputs {
     output: Option<PathBuf>,
     emit_wac: Option<PathBuf>,
+    /// With `--annotate-wac`: the input to name in the emitted WAC's
+    /// header.
+    annotate_wac: Option<String>,
+    emit_manifest: Option<PathBuf>,
     plan: bool,
-    splits: SplitsLocation,
-) -> Result<()> {
+    /// Where composing looks for packages the bundle doesn't provide.
+    deps_dirs: Vec<PathBuf>,
+}
+
+impl Outputs {
+    /// Whether the generated files must outlive this run, because
+    /// something written to disk points at them.
+    fn needs_persist(&self) -> bool {
+        self.plan || self.emit_wac.is_some() || self.emit_manifest.is_some()
+    }
+}
+
+/// The on/off switches of `splice`.
+struct SpliceFlags {
+    skip_type_check: bool,
+    provenance: bool,
+    reject_existing: bool,
+    strict_wiring: bool,
+}
+
+/// The on/off switches of `compose`.
+struct ComposeFlags {
+    skip_type_check: bool,
+    provenance: bool,
+    strict_wiring: bool,
+}
+
+/// Tail-end of both subcommands: write the WAC and manifest if
+/// requested, then either print the `--plan` shell command or run
+/// in-process compose and write the composed `.wasm`.
+fn finish(bundle: Bundle, outputs: Outputs, splits: SplitsLocation) -> Result<()> {
+    let Outputs {
+        output,
+        emit_wac,
+        annotate_wac,
+        emit_manifest,
+        plan,
+        deps_dirs,
+    } = outputs;
+    let wac = match (annotate_wac, bundle.plan()) {
+        (Some(input), Some(plan)) => plan.to_annotated_wac(&input),
+        _ => bundle.wac().to_string(),
+    };
+
+    if let Some(ref manifest_path) = emit_manifest {
+        bundle.save(manifest_path)?;
+        eprintln!(
+            "{}",
+            format!("Bundle manifest saved to: {}", manifest_path.display()).dimmed()
+        );
+    }
+
     if plan {
         // --plan implies --emit-wac if the user didn't pass one.
         let wac_path = emit_wac.unwrap_or_else(|| PathBuf::from(DEFAULT_OUTPUT_WAC));
-        write_wac(&wac_path, &bundle.wac)?;
+        write_wac(&wac_path, &wac)?;
         let wac_path_str = path_str(&wac_path)?;
         // Plan mode keeps the splits dir on disk so the printed
         // command actually works.
@@ -255,32 +704,44 @@ fn finish(
 
     // Default mode: optionally persist the WAC, then compose to wasm.
     if let Some(ref wac_path) = emit_wac {
-        write_wac(wac_path, &bundle.wac)?;
+        write_wac(wac_path, &wac)?;
     }
 
-    let composed = match bundle.to_wasm() {
+    let composed = match bundle.to_wasm_with(&SearchPaths::new(deps_dirs)) {
         Ok(b) => b,
-        Err(e) => return Err(handle_compose_failure(e, &bundle, emit_wac, splits)),
+        Err(e) => {
+            return Err(handle_compose_failure(
+                e.into(),
+                &bundle,
+                &wac,
+                emit_wac,
+                splits,
+            ))
+        }
     };
+    write_composed(output, &composed)
+}
 
+fn write_composed(output: Option<PathBuf>, composed: &[u8]) -> Result<()> {
     let output_path = output.unwrap_or_else(|| PathBuf::from(DEFAULT_OUTPUT_WASM));
-    fs::write(&output_path, &composed)
-        .with_context(|| format!("Failed to write composed wasm: {}", output_path.display()))?;
-    Ok(())
+    fs::write(&output_path, composed)
+        .with_context(|| format!("Failed to write composed wasm: {}", output_path.display()))
 }
 
-/// On compose failure: persist the WAC (if not already), keep the
-/// splits dir on disk, and surface a single error containing both
-/// paths plus the standalone `wac compose` shell command for repro.
+/// On compose failure: persist `wac`, the WAC as rendered for output
+/// (if not already), keep the splits dir on disk, and surface a single
+/// error containing both paths plus the standalone `wac compose` shell
+/// command for repro.
 fn handle_compose_failure(
     err: anyhow::Error,
     bundle: &Bundle,
+    wac: &str,
     emit_wac: Option<PathBuf>,
     splits: SplitsLocation,
 ) -> anyhow::Error {
     let wac_path = match emit_wac {
         Some(p) => p,
-        None => match persist_wac_on_failure(&bundle.wac) {
+        None => match persist_wac_on_failure(wac) {
             Ok(p) => p,
             Err(write_err) => {
                 return err.context(format!(
@@ -351,8 +812,8 @@ fn path_str(p: &Path) -> Result<&str> {
 /// [`SplitsLocation::persist`] to keep the splits on disk past the
 /// process exit (used by `--plan` and on compose failure).
 enum SplitsLocation {
-    /// No splits dir for this run (e.g. the `compose` subcommand,
-    /// which composes from individual components and doesn't split).
+    /// No splits dir for this run (a plain `compose`, which composes
+    /// from individual components and has nothing to write).
     None,
     /// User-supplied or default-on-disk path. Always preserved.
     Persistent(PathBuf),
@@ -375,14 +836,11 @@ impl SplitsLocation {
         Ok(Self::Temp(dir))
     }
 
-    fn path(&self) -> &Path {
+    fn dir(&self) -> Option<&Path> {
         match self {
-            // The lib's `splice()` requires a splits_dir; for compose
-            // we never invoke split_out_composition, so this branch
-            // never returns to the lib. "" is a sentinel.
-            Self::None => Path::new(""),
-            Self::Persistent(p) => p.as_path(),
-            Self::Temp(d) => d.path(),
+            Self::None => None,
+            Self::Persistent(p) => Some(p.as_path()),
+            Self::Temp(d) => Some(d.path()),
         }
     }
 
@@ -421,7 +879,7 @@ fn print_diagnostics(diagnostics: &[ContractResult]) {
             ContractResult::Error(msg) => eprintln!(
                 "{}: type check skipped — {}",
                 "WARN".yellow().bold(),
-                msg.yellow()
+                msg.to_string().yellow()
             ),
         }
     }
@@ -0,0 +1,237 @@
+//! Wiring for the imports of injected middleware beyond the interface
+//! it wraps.
+//!
+//! A rule wires a middleware's target interface from the provider it
+//! sits in front of; anything else the middleware imports — a kv
+//! store, a config service — would fall through WAC's `...` to the
+//! host. [`wire_middleware_deps`] reads each middleware's package for
+//! those imports and wires each to a component listed under the
+//! injection's `deps:`, or else to the instance in the composition
+//! that provides it, so the middleware talks to the same store the
+//! application does.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::{bail, Context, Result};
+use wit_parser::WorldKey;
+
+use crate::artifacts::Artifacts;
+use crate::parse::config::{MiddlewareDep, SpliceRule};
+use crate::plan::{CompositionPlan, InstanceOrigin, PlanItem, PlannedArg, PlannedInstance};
+use crate::wac::INST_PREFIX;
+
+/// A middleware or dependency instance whose imports are still to wire.
+struct Pending<'a> {
+    /// Index of the instance in `plan.items`.
+    idx: usize,
+    /// Index of the rule that injected it (or its middleware).
+    rule: usize,
+    /// Its package, to read its imports from.
+    path: &'a str,
+    /// The `deps:` of its injection, consulted before the composition.
+    deps: &'a [MiddlewareDep],
+}
+
+/// Wire every import of the middleware (and replacement) instances in
+/// `plan` that their site left unwired: to the first of their
+/// injection's `deps` that exports it — instantiated once, its own
+/// imports wired the same way — or else to the one composition
+/// instance in `providers` (interface → vars of the instances
+/// exporting it). Imports neither provides are left to the host.
+/// Dependency packages are added to `used_middlewares`.
+///
+/// Middleware injected by name only, or whose package can't be read,
+/// is left as it is unless it lists `deps`. Fails when an import has
+/// several providers in the composition, or when wiring it would make
+/// a cycle.
+pub(crate) fn wire_middleware_deps(
+    plan: &mut CompositionPlan,
+    rules: &[SpliceRule],
+    providers: &BTreeMap<String, BTreeSet<String>>,
+    used_middlewares: &mut Vec<(String, String)>,
+    artifacts: &Artifacts,
+) -> Result<()> {
+    let mut pending: Vec<Pending> = Vec::new();
+    for (idx, item) in plan.items.iter().enumerate() {
+        let PlanItem::Instance(PlannedInstance {
+            origin: InstanceOrigin::Middleware { rule } | InstanceOrigin::Replacement { rule },
+            package,
+            ..
+        }) = item
+        else {
+            continue;
+        };
+        let Some(injection) = rules[*rule]
+            .inject()
+            .iter()
+            .find(|inj| *package == format!("{INST_PREFIX}:{}", inj.name))
+        else {
+            continue;
+        };
+        match &injection.path {
+            Some(path) => pending.push(Pending {
+                idx,
+                rule: *rule,
+                path,
+                deps: &injection.deps,
+            }),
+            None if !injection.deps.is_empty() => bail!(
+                "rule {}: `{}` lists `deps` but has no `path` to read its imports from",
+                rule + 1,
+                injection.name
+            ),
+            None => {}
+        }
+    }
+
+    // dependency name -> the interfaces it exports.
+    let mut dep_exports: HashMap<&str, BTreeSet<String>> = HashMap::new();
+    while let Some(Pending {
+        idx,
+        rule,
+        path,
+        deps,
+    }) = pending.pop()
+    {
+        let imports = match interfaces(artifacts, path, Side::Imports) {
+            Ok(imports) => imports,
+            // Nothing to wire from; composing will report the package.
+            Err(_) if deps.is_empty() => continue,
+            Err(e) => return Err(e),
+        };
+        let var = instance(plan, idx).var.clone();
+        for name in imports {
+            if instance(plan, idx).args.iter().any(|arg| arg.name == name) {
+                continue;
+            }
+
+            let mut from_dep = None;
+            for dep in deps {
+                if !dep_exports.contains_key(dep.name.as_str()) {
+                    let exports = interfaces(artifacts, &dep.path, Side::Exports)?;
+                    dep_exports.insert(&dep.name, exports);
+                }
+                if dep_exports[dep.name.as_str()].contains(&name) {
+                    from_dep = Some(dep);
+                    break;
+                }
+            }
+            let source = if let Some(dep) = from_dep {
+                match plan.items.iter().find_map(|item| match item {
+                    PlanItem::Instance(inst) if inst.var == dep.name => Some(inst.origin),
+                    _ => None,
+                }) {
+                    Some(InstanceOrigin::Dependency { .. }) => {}
+                    Some(origin) => bail!(
+                        "rule {}: dependency `{}` has the same name as {origin}",
+                        rule + 1,
+                        dep.name
+                    ),
+                    None => {
+                        pending.push(Pending {
+                            idx: plan.items.len(),
+                            rule,
+                            path: &dep.path,
+                            deps,
+                        });
+                        plan.items.push(PlanItem::Instance(PlannedInstance {
+                            var: dep.name.clone(),
+                            package: format!("{INST_PREFIX}:{}", dep.name),
+                            args: vec![],
+                            origin: InstanceOrigin::Dependency { rule },
+                            note: format!(
+                                "rule {}: dependency `{}` of `{var}`, from {}",
+                                rule + 1,
+                                dep.name,
+                                dep.path
+                            ),
+                        }));
+                        used_middlewares.push((dep.name.clone(), dep.path.clone()));
+                    }
+                }
+                dep.name.clone()
+            } else {
+                match providers.get(&name) {
+                    None => continue,
+                    Some(vars) if vars.len() > 1 => bail!(
+                        "rule {}: `{var}` imports `{name}`, which the composition provides \
+                         from more than one instance ({}); list the provider to use under the \
+                         injection's `deps`",
+                        rule + 1,
+                        vars.iter().cloned().collect::<Vec<_>>().join(", ")
+                    ),
+                    Some(vars) => vars.first().expect("providers are non-empty").clone(),
+                }
+            };
+
+            if depends_on(plan, &source, &var) {
+                bail!(
+                    "rule {}: `{var}` imports `{name}` from `{source}`, which itself depends \
+                     on `{var}`; wiring it would make a cycle",
+                    rule + 1
+                );
+            }
+            let PlanItem::Instance(inst) = &mut plan.items[idx] else {
+                unreachable!("only instances are pending");
+            };
+            inst.args.push(PlannedArg { name, source });
+        }
+    }
+    Ok(())
+}
+
+/// Which of a component's interfaces [`interfaces`] lists.
+enum Side {
+    Imports,
+    Exports,
+}
+
+/// The names of the interfaces the package at `path` imports or exports.
+fn interfaces(artifacts: &Artifacts, path: &str, side: Side) -> Result<BTreeSet<String>> {
+    let bytes = artifacts.read(path)?;
+    let wit = artifacts
+        .parsed()
+        .wit(&bytes)
+        .with_context(|| format!("Failed to read the interfaces of {path}"))?;
+    let (resolve, world) = &*wit;
+    let world = &resolve.worlds[*world];
+    let items = match side {
+        Side::Imports => &world.imports,
+        Side::Exports => &world.exports,
+    };
+    Ok(items
+        .keys()
+        .filter(|key| matches!(key, WorldKey::Interface(_)))
+        .map(|key| resolve.name_world_key(key))
+        .collect())
+}
+
+/// The instance at `idx` in `plan.items`.
+fn instance(plan: &CompositionPlan, idx: usize) -> &PlannedInstance {
+    match &plan.items[idx] {
+        PlanItem::Instance(inst) => inst,
+        _ => unreachable!("only instances are pending"),
+    }
+}
+
+/// Whether the instance `var` is, or transitively imports from, `target`.
+fn depends_on(plan: &CompositionPlan, var: &str, target: &str) -> bool {
+    let mut seen = BTreeSet::new();
+    let mut stack = vec![var.to_string()];
+    while let Some(var) = stack.pop() {
+        if var == target {
+            return true;
+        }
+        if !seen.insert(var.clone()) {
+            continue;
+        }
+        if let Some(PlanItem::Instance(inst)) = plan
+            .items
+            .iter()
+            .find(|item| matches!(item, PlanItem::Instance(inst) if inst.var == var))
+        {
+            stack.extend(inst.args.iter().map(|arg| arg.source.clone()));
+        }
+    }
+    false
+}
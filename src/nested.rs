@@ -0,0 +1,120 @@
+//! Splicing into compositions nested inside the one being spliced.
+//!
+//! A composed component can embed a composition of its own — a
+//! vendor's prebuilt subsystem, say. cviz lists that composition's
+//! instances alongside the outer ones, but they aren't the outer
+//! composition's to wire: [`scope_to_root`] drops them, so the nested
+//! composition is instantiated whole, from its own split.
+//!
+//! A rule reaches inside it by qualifying its instance selectors with
+//! the path of instances leading there: `outer/inner/srv-b` is the
+//! `srv-b` instance of the composition instantiated as `inner` inside
+//! the one instantiated as `outer`. [`partition_rules`] takes those
+//! rules off the outer composition's list, one segment shorter, and
+//! the splice applies them to the nested composition on its own —
+//! recursively, for deeper paths — writing the result over its split.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use cviz::model::CompositionGraph;
+
+use crate::parse::config::SpliceRule;
+use crate::split::Nesting;
+use crate::wac::node_split_num;
+
+/// Separates the segments of a path-qualified instance selector.
+pub(crate) const PATH_SEP: char = '/';
+
+/// Drop the nodes of `graph` defined inside a nested composition,
+/// leaving the instances of the composition itself.
+pub(crate) fn scope_to_root(graph: &mut CompositionGraph, nesting: &Nesting) {
+    let inner: Vec<u32> = graph
+        .nodes
+        .iter()
+        .filter(|(_, node)| nesting.inside_composition(node.component_num as usize + 1))
+        .map(|(id, _)| *id)
+        .collect();
+    for id in inner {
+        graph.nodes.remove(&id);
+    }
+}
+
+/// Take the rules whose selectors lead into a nested composition off
+/// `rules`, grouped by the instance they lead into, with that first
+/// path segment stripped. Assumes the rules passed
+/// [`crate::lowlevel::validate_rules`], so each rule's selectors share
+/// one path. Returns the rules left for this composition and the
+/// grouped ones.
+pub(crate) fn partition_rules(
+    rules: Vec<SpliceRule>,
+) -> (Vec<SpliceRule>, BTreeMap<String, Vec<SpliceRule>>) {
+    let mut own = Vec::new();
+    let mut nested: BTreeMap<String, Vec<SpliceRule>> = BTreeMap::new();
+    for mut rule in rules {
+        let head = rule
+            .selectors()
+            .first()
+            .and_then(|selector| selector.split_once(PATH_SEP))
+            .map(|(head, _)| head.to_string());
+        let Some(head) = head else {
+            own.push(rule);
+            continue;
+        };
+        for selector in rule.selectors_mut() {
+            *selector = selector[head.len() + 1..].to_string();
+        }
+        nested.entry(head).or_default().push(rule);
+    }
+    (own, nested)
+}
+
+/// The split of the nested composition instantiated as `name` in
+/// `graph`, to apply the rules leading into it to. `None`, with a
+/// warning, when no instance is named `name`.
+///
+/// Fails when the instance isn't a nested composition, or when
+/// another instance shares its component — rewriting the split would
+/// change both.
+pub(crate) fn nested_split(
+    graph: &CompositionGraph,
+    nesting: &Nesting,
+    name: &str,
+) -> Result<Option<usize>> {
+    let Some(id) = graph
+        .nodes
+        .iter()
+        .find(|(_, node)| node.display_label() == name)
+        .map(|(id, _)| *id)
+    else {
+        eprintln!(
+            "{}: no instance named '{name}' to splice into; rules under '{name}{PATH_SEP}' \
+             were not applied.",
+            "WARN".yellow().bold()
+        );
+        return Ok(None);
+    };
+    let split = node_split_num(id, graph);
+    if !nesting.compositions.contains(&split) {
+        bail!("`{name}` isn't a nested composition, so there's nothing inside it to select");
+    }
+    if let Some((_, other)) = graph
+        .nodes
+        .iter()
+        .find(|(other, _)| **other != id && node_split_num(**other, graph) == split)
+    {
+        bail!(
+            "`{name}` shares its composition with `{}`; splicing into one instance of a \
+             shared nested composition isn't supported",
+            other.display_label()
+        );
+    }
+    Ok(Some(split))
+}
+
+/// The directory the splits of the nested composition instantiated as
+/// `name` go in.
+pub(crate) fn nested_splits_dir(splits_dir: &str, name: &str) -> String {
+    format!("{splits_dir}/{name}")
+}
@@ -0,0 +1,340 @@
+//! Where [`crate::compose_wac_with`] finds the packages a WAC document
+//! references.
+//!
+//! A [`PackageSource`] maps a WAC package key (`ns:name`, optionally
+//! versioned) to the package's bytes, or says it doesn't have it so
+//! the next source can be asked. splicer ships sources for the shapes
+//! packages usually come in:
+//!
+//! - a `BTreeMap<String, Vec<u8>>` of bytes already in memory,
+//! - a `BTreeMap<String, PathBuf>` of explicit paths, like
+//!   [`crate::Bundle::wac_deps`],
+//! - [`SearchPaths`], directories laid out the way `wac compose`
+//!   searches them (`<dir>/<ns>/<name>.wasm`),
+//! - [`OciLayout`], a local cache of packages pulled from an OCI
+//!   registry, stored as OCI image layouts.
+//!
+//! Slices, arrays and `Vec`s of sources try each one in order, so callers can
+//! fall back from, say, a bundle's own packages to a shared cache.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// The key package `name` at `version` is listed under in a map of
+/// packages: `ns:name`, or `ns:name@version`.
+pub(crate) fn package_key(name: &str, version: Option<&Version>) -> String {
+    match version {
+        Some(version) => format!("{name}@{version}"),
+        None => name.to_string(),
+    }
+}
+
+/// Resolves WAC package keys to package bytes. See the
+/// [module docs](self) for the sources splicer provides.
+pub trait PackageSource {
+    /// The bytes of package `name` (e.g. `"my:srv-a"`) at `version`,
+    /// or `Ok(None)` when this source doesn't provide it. `version` is
+    /// `None` for unversioned references (`new my:srv-a { ... }`).
+    ///
+    /// Errors are reserved for packages the source does claim but
+    /// can't load (an unreadable file, a corrupt blob); they abort
+    /// resolution instead of falling through to the next source.
+    fn package(&self, name: &str, version: Option<&Version>) -> anyhow::Result<Option<Vec<u8>>>;
+}
+
+impl<S: PackageSource + ?Sized> PackageSource for &S {
+    fn package(&self, name: &str, version: Option<&Version>) -> anyhow::Result<Option<Vec<u8>>> {
+        (**self).package(name, version)
+    }
+}
+
+impl<S: PackageSource + ?Sized> PackageSource for Box<S> {
+    fn package(&self, name: &str, version: Option<&Version>) -> anyhow::Result<Option<Vec<u8>>> {
+        (**self).package(name, version)
+    }
+}
+
+/// Each source in order; the first one that has the package wins.
+impl<S: PackageSource> PackageSource for [S] {
+    fn package(&self, name: &str, version: Option<&Version>) -> anyhow::Result<Option<Vec<u8>>> {
+        for source in self {
+            if let Some(bytes) = source.package(name, version)? {
+                return Ok(Some(bytes));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<S: PackageSource, const N: usize> PackageSource for [S; N] {
+    fn package(&self, name: &str, version: Option<&Version>) -> anyhow::Result<Option<Vec<u8>>> {
+        self.as_slice().package(name, version)
+    }
+}
+
+impl<S: PackageSource> PackageSource for Vec<S> {
+    fn package(&self, name: &str, version: Option<&Version>) -> anyhow::Result<Option<Vec<u8>>> {
+        self.as_slice().package(name, version)
+    }
+}
+
+/// Package bytes keyed by package name — `ns:name`, or
+/// `ns:name@version` for a versioned reference — as taken by
+/// [`crate::compose_wac_bytes`].
+impl PackageSource for BTreeMap<String, Vec<u8>> {
+    fn package(&self, name: &str, version: Option<&Version>) -> anyhow::Result<Option<Vec<u8>>> {
+        Ok(self.get(&package_key(name, version)).cloned())
+    }
+}
+
+/// Package paths keyed by package name, as in
+/// [`crate::Bundle::wac_deps`]. A listed path that can't be read is an
+/// error.
+impl PackageSource for BTreeMap<String, PathBuf> {
+    fn package(&self, name: &str, version: Option<&Version>) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(path) = self.get(&package_key(name, version)) else {
+            return Ok(None);
+        };
+        let bytes = std::fs::read(path).with_context(|| {
+            format!(
+                "local path `{}` for package `{name}` can't be read",
+                path.display()
+            )
+        })?;
+        Ok(Some(bytes))
+    }
+}
+
+/// Directories searched the way `wac compose --deps-dir` searches
+/// them: package `ns:name` is `<dir>/ns/name.wasm`, and version `v` of
+/// it is `<dir>/ns/name/v.wasm`. The first directory holding the file
+/// wins.
+#[derive(Clone, Debug, Default)]
+pub struct SearchPaths {
+    dirs: Vec<PathBuf>,
+}
+
+impl SearchPaths {
+    /// Search `dirs`, in order.
+    pub fn new(dirs: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        Self {
+            dirs: dirs.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl PackageSource for SearchPaths {
+    fn package(&self, name: &str, version: Option<&Version>) -> anyhow::Result<Option<Vec<u8>>> {
+        for dir in &self.dirs {
+            let mut path = package_dir(dir, name);
+            if let Some(version) = version {
+                path.push(version.to_string());
+            }
+            path.as_mut_os_string().push(".wasm");
+            if path.is_file() {
+                let bytes = std::fs::read(&path)
+                    .with_context(|| format!("failed to read '{}'", path.display()))?;
+                return Ok(Some(bytes));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A local cache of registry packages, one
+/// [OCI image layout](https://github.com/opencontainers/image-spec/blob/main/image-layout.md)
+/// per package at `<root>/<ns>/<name>` (the layout `oras copy
+/// --to-oci-layout` writes). Version `v` of a package is the image
+/// tagged `v`; unversioned references use the `latest` tag. The wasm
+/// layer's digest is checked before its bytes are returned.
+#[derive(Clone, Debug)]
+pub struct OciLayout {
+    root: PathBuf,
+}
+
+/// Media type of the layer holding a component in a wasm OCI artifact.
+const WASM_LAYER_MEDIA_TYPE: &str = "application/wasm";
+
+/// Index/manifest annotation naming the tag an image is stored under.
+const REF_NAME_ANNOTATION: &str = "org.opencontainers.image.ref.name";
+
+impl OciLayout {
+    /// The cache rooted at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl PackageSource for OciLayout {
+    fn package(&self, name: &str, version: Option<&Version>) -> anyhow::Result<Option<Vec<u8>>> {
+        let layout = package_dir(&self.root, name);
+        let index_path = layout.join("index.json");
+        if !index_path.is_file() {
+            return Ok(None);
+        }
+        let index: OciIndex = read_json(&index_path)?;
+        let tag = version.map_or_else(|| "latest".to_string(), Version::to_string);
+        let Some(manifest) = index
+            .manifests
+            .iter()
+            .find(|desc| desc.annotations.get(REF_NAME_ANNOTATION) == Some(&tag))
+        else {
+            return Ok(None);
+        };
+
+        let manifest: OciManifest = serde_json::from_slice(&read_blob(&layout, manifest)?)
+            .with_context(|| format!("invalid image manifest for package `{name}` ({tag})"))?;
+        let Some(layer) = manifest
+            .layers
+            .iter()
+            .find(|layer| layer.media_type == WASM_LAYER_MEDIA_TYPE)
+        else {
+            bail!("image for package `{name}` ({tag}) has no `{WASM_LAYER_MEDIA_TYPE}` layer");
+        };
+        read_blob(&layout, layer).map(Some)
+    }
+}
+
+#[derive(Deserialize)]
+struct OciIndex {
+    manifests: Vec<OciDescriptor>,
+}
+
+#[derive(Deserialize)]
+struct OciManifest {
+    layers: Vec<OciDescriptor>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OciDescriptor {
+    #[serde(default)]
+    media_type: String,
+    digest: String,
+    #[serde(default)]
+    annotations: BTreeMap<String, String>,
+}
+
+/// `<dir>/<ns>/<name>` for package `ns:name`.
+fn package_dir(dir: &Path, name: &str) -> PathBuf {
+    let mut path = dir.to_path_buf();
+    path.extend(name.split(':'));
+    path
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> anyhow::Result<T> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("failed to read '{}'", path.display()))?;
+    serde_json::from_slice(&bytes).with_context(|| format!("invalid JSON in '{}'", path.display()))
+}
+
+/// The blob `desc` points at in `layout`, checked against its digest.
+fn read_blob(layout: &Path, desc: &OciDescriptor) -> anyhow::Result<Vec<u8>> {
+    let Some(hex) = desc.digest.strip_prefix("sha256:") else {
+        bail!("unsupported digest algorithm in '{}'", desc.digest);
+    };
+    let path = layout.join("blobs").join("sha256").join(hex);
+    let bytes =
+        std::fs::read(&path).with_context(|| format!("failed to read '{}'", path.display()))?;
+    if format!("{:x}", Sha256::digest(&bytes)) != hex {
+        bail!("blob '{}' doesn't match its digest", path.display());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        Version::parse(s).unwrap()
+    }
+
+    #[test]
+    fn search_paths_follow_wac_layout() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(second.path().join("my/srv/")).unwrap();
+        std::fs::write(second.path().join("my/srv.wasm"), b"plain").unwrap();
+        std::fs::write(second.path().join("my/srv/1.2.0.wasm"), b"versioned").unwrap();
+
+        let paths = SearchPaths::new([first.path(), second.path()]);
+        assert_eq!(paths.package("my:srv", None).unwrap().unwrap(), b"plain");
+        assert_eq!(
+            paths.package("my:srv", Some(&v("1.2.0"))).unwrap().unwrap(),
+            b"versioned"
+        );
+        assert!(paths.package("my:other", None).unwrap().is_none());
+    }
+
+    #[test]
+    fn sources_fall_through_in_order() {
+        let memory = BTreeMap::from([("my:a".to_string(), b"a".to_vec())]);
+        let empty = BTreeMap::<String, Vec<u8>>::new();
+        let sources: Vec<&dyn PackageSource> = vec![&empty, &memory];
+        assert_eq!(sources.package("my:a", None).unwrap().unwrap(), b"a");
+        assert!(sources
+            .package("my:a", Some(&v("1.0.0")))
+            .unwrap()
+            .is_none());
+    }
+
+    /// Write a one-layer wasm image for `name` tagged `tag`.
+    fn write_image(root: &Path, name: &str, tag: &str, wasm: &[u8]) {
+        let layout = package_dir(root, name);
+        let blobs = layout.join("blobs/sha256");
+        std::fs::create_dir_all(&blobs).unwrap();
+        let put = |bytes: &[u8]| {
+            let hex = format!("{:x}", Sha256::digest(bytes));
+            std::fs::write(blobs.join(&hex), bytes).unwrap();
+            format!("sha256:{hex}")
+        };
+        let layer = put(wasm);
+        let manifest = put(serde_json::json!({
+            "schemaVersion": 2,
+            "layers": [{ "mediaType": WASM_LAYER_MEDIA_TYPE, "digest": layer }],
+        })
+        .to_string()
+        .as_bytes());
+        let index = serde_json::json!({
+            "schemaVersion": 2,
+            "manifests": [{
+                "digest": manifest,
+                "annotations": { REF_NAME_ANNOTATION: tag },
+            }],
+        });
+        std::fs::write(layout.join("index.json"), index.to_string()).unwrap();
+    }
+
+    #[test]
+    fn oci_layout_resolves_tags() {
+        let root = tempfile::tempdir().unwrap();
+        write_image(root.path(), "my:mw", "0.3.0", b"\0asm-mw");
+        let cache = OciLayout::new(root.path());
+
+        assert_eq!(
+            cache.package("my:mw", Some(&v("0.3.0"))).unwrap().unwrap(),
+            b"\0asm-mw"
+        );
+        assert!(cache.package("my:mw", None).unwrap().is_none());
+        assert!(cache.package("my:other", None).unwrap().is_none());
+    }
+
+    #[test]
+    fn oci_layout_rejects_tampered_blobs() {
+        let root = tempfile::tempdir().unwrap();
+        write_image(root.path(), "my:mw", "latest", b"\0asm-mw");
+        let hex = format!("{:x}", Sha256::digest(b"\0asm-mw"));
+        std::fs::write(root.path().join("my/mw/blobs/sha256").join(hex), b"evil").unwrap();
+
+        let err = OciLayout::new(root.path())
+            .package("my:mw", None)
+            .unwrap_err();
+        assert!(err.to_string().contains("digest"), "{err}");
+    }
+}
@@ -0,0 +1,445 @@
+//! Fluent builders for [`SpliceRule`]s, for callers that compute their
+//! rules in code rather than loading a YAML config.
+//!
+//! ```
+//! use splicer::types::{Injection, Rule};
+//!
+//! let rule = Rule::before("wasi:http/handler@0.3.0")
+//!     .provider("auth")
+//!     .inject(Injection::from_builtin("otel-bare-spans"))
+//!     .build()?;
+//! # Ok::<(), splicer::Error>(())
+//! ```
+//!
+//! [`BeforeRule::build`] / [`BetweenRule::build`] /
+//! [`ReplaceRule::build`] / [`ExportRule::build`] run the same checks
+//! a YAML rule goes through (see [`validate_rules`]). Cross-rule checks
+//! — globally unique injection names — run again when the full rule
+//! list reaches [`crate::splice`].
+
+use super::config::{validate_rules, Injection, SpliceRule};
+use crate::error::{Error, Result};
+
+/// Entry point for building a [`SpliceRule`] in code. Pick the
+/// matching strategy with [`Rule::before`], [`Rule::between`] or
+/// [`Rule::replace`] — or reshape the composition's exports with
+/// [`Rule::hide_export`], [`Rule::rename_export`] or
+/// [`Rule::allow_exports`] — chain the optional settings, then call
+/// `build()`.
+pub struct Rule;
+
+impl Rule {
+    /// Inject middleware before every provider of `interface`. Narrow
+    /// the match to one provider with [`BeforeRule::provider`].
+    pub fn before(interface: impl Into<String>) -> BeforeRule {
+        BeforeRule {
+            interface: interface.into(),
+            provider_name: None,
+            provider_instance: None,
+            consumer_name: None,
+            provider_alias: None,
+            inject: Vec::new(),
+        }
+    }
+
+    /// Inject middleware on the `interface` edge where `outer` calls
+    /// into `inner`.
+    pub fn between(
+        interface: impl Into<String>,
+        inner: impl Into<String>,
+        outer: impl Into<String>,
+    ) -> BetweenRule {
+        BetweenRule {
+            interface: interface.into(),
+            inner_name: inner.into(),
+            inner_alias: None,
+            outer_name: outer.into(),
+            outer_alias: None,
+            inject: Vec::new(),
+        }
+    }
+
+    /// Replace every provider of `interface` with the component
+    /// `with`, which must export it. Narrow the match to one provider
+    /// with [`ReplaceRule::provider`].
+    pub fn replace(interface: impl Into<String>, with: Injection) -> ReplaceRule {
+        ReplaceRule {
+            interface: interface.into(),
+            provider_name: None,
+            provider_instance: None,
+            with,
+        }
+    }
+
+    /// Drop the composition's export of `interface`.
+    pub fn hide_export(interface: impl Into<String>) -> ExportRule {
+        ExportRule(SpliceRule::HideExport {
+            interface: interface.into(),
+        })
+    }
+
+    /// Export `interface` from the composition under the name `to`.
+    pub fn rename_export(interface: impl Into<String>, to: impl Into<String>) -> ExportRule {
+        ExportRule(SpliceRule::RenameExport {
+            interface: interface.into(),
+            to: to.into(),
+        })
+    }
+
+    /// Fail the splice if the composition exports anything besides
+    /// `interfaces`.
+    pub fn allow_exports<I, S>(interfaces: I) -> ExportRule
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        ExportRule(SpliceRule::AllowExports {
+            interfaces: interfaces.into_iter().map(Into::into).collect(),
+        })
+    }
+}
+
+/// Builder for [`SpliceRule::Before`]. Created by [`Rule::before`].
+#[derive(Clone, Debug)]
+#[must_use = "call `build()` to get a `SpliceRule`"]
+pub struct BeforeRule {
+    interface: String,
+    provider_name: Option<String>,
+    provider_instance: Option<u32>,
+    consumer_name: Option<String>,
+    provider_alias: Option<String>,
+    inject: Vec<Injection>,
+}
+
+impl BeforeRule {
+    /// Only match the provider instance with this name.
+    pub fn provider(mut self, name: impl Into<String>) -> Self {
+        self.provider_name = Some(name.into());
+        self
+    }
+
+    /// Only match the provider instance with this index — for telling
+    /// apart instances of the same component.
+    pub fn provider_instance(mut self, index: u32) -> Self {
+        self.provider_instance = Some(index);
+        self
+    }
+
+    /// Only match where the instance named `name` imports the
+    /// interface from the provider.
+    pub fn consumer(mut self, name: impl Into<String>) -> Self {
+        self.consumer_name = Some(name.into());
+        self
+    }
+
+    /// Alias the matched provider to this name in the generated WAC.
+    pub fn provider_alias(mut self, alias: impl Into<String>) -> Self {
+        self.provider_alias = Some(alias.into());
+        self
+    }
+
+    /// Append a middleware to the chain. Call repeatedly to inject
+    /// several; they're invoked in the order added.
+    pub fn inject(mut self, injection: Injection) -> Self {
+        self.inject.push(injection);
+        self
+    }
+
+    /// Validate and produce the [`SpliceRule`]. Fails with
+    /// [`Error::Config`].
+    pub fn build(self) -> Result<SpliceRule> {
+        let rule = SpliceRule::Before {
+            interface: self.interface,
+            provider_name: self.provider_name,
+            provider_instance: self.provider_instance,
+            consumer_name: self.consumer_name,
+            provider_alias: self.provider_alias,
+            inject: self.inject,
+        };
+        validate_rules(std::slice::from_ref(&rule)).map_err(Error::Config)?;
+        Ok(rule)
+    }
+}
+
+/// Builder for [`SpliceRule::Between`]. Created by [`Rule::between`].
+#[derive(Clone, Debug)]
+#[must_use = "call `build()` to get a `SpliceRule`"]
+pub struct BetweenRule {
+    interface: String,
+    inner_name: String,
+    inner_alias: Option<String>,
+    outer_name: String,
+    outer_alias: Option<String>,
+    inject: Vec<Injection>,
+}
+
+impl BetweenRule {
+    /// Alias the inner (provider-side) component in the generated WAC.
+    pub fn inner_alias(mut self, alias: impl Into<String>) -> Self {
+        self.inner_alias = Some(alias.into());
+        self
+    }
+
+    /// Alias the outer (consumer-side) component in the generated WAC.
+    pub fn outer_alias(mut self, alias: impl Into<String>) -> Self {
+        self.outer_alias = Some(alias.into());
+        self
+    }
+
+    /// Append a middleware to the chain. Call repeatedly to inject
+    /// several; they're invoked in the order added.
+    pub fn inject(mut self, injection: Injection) -> Self {
+        self.inject.push(injection);
+        self
+    }
+
+    /// Validate and produce the [`SpliceRule`]. Fails with
+    /// [`Error::Config`].
+    pub fn build(self) -> Result<SpliceRule> {
+        let rule = SpliceRule::Between {
+            interface: self.interface,
+            inner_name: self.inner_name,
+            inner_alias: self.inner_alias,
+            outer_name: self.outer_name,
+            outer_alias: self.outer_alias,
+            inject: self.inject,
+        };
+        validate_rules(std::slice::from_ref(&rule)).map_err(Error::Config)?;
+        Ok(rule)
+    }
+}
+
+/// Builder for [`SpliceRule::Replace`]. Created by [`Rule::replace`].
+#[derive(Clone, Debug)]
+#[must_use = "call `build()` to get a `SpliceRule`"]
+pub struct ReplaceRule {
+    interface: String,
+    provider_name: Option<String>,
+    provider_instance: Option<u32>,
+    with: Injection,
+}
+
+impl ReplaceRule {
+    /// Only replace the provider instance with this name.
+    pub fn provider(mut self, name: impl Into<String>) -> Self {
+        self.provider_name = Some(name.into());
+        self
+    }
+
+    /// Only replace the provider instance with this index.
+    pub fn provider_instance(mut self, index: u32) -> Self {
+        self.provider_instance = Some(index);
+        self
+    }
+
+    /// Validate and produce the [`SpliceRule`]. Fails with
+    /// [`Error::Config`].
+    pub fn build(self) -> Result<SpliceRule> {
+        let rule = SpliceRule::Replace {
+            interface: self.interface,
+            provider_name: self.provider_name,
+            provider_instance: self.provider_instance,
+            inject: vec![self.with],
+        };
+        validate_rules(std::slice::from_ref(&rule)).map_err(Error::Config)?;
+        Ok(rule)
+    }
+}
+
+/// Builder for the rules on the composition's exports. Created by
+/// [`Rule::hide_export`], [`Rule::rename_export`] and
+/// [`Rule::allow_exports`].
+#[derive(Clone, Debug)]
+#[must_use = "call `build()` to get a `SpliceRule`"]
+pub struct ExportRule(SpliceRule);
+
+impl ExportRule {
+    /// Validate and produce the [`SpliceRule`]. Fails with
+    /// [`Error::Config`].
+    pub fn build(self) -> Result<SpliceRule> {
+        validate_rules(std::slice::from_ref(&self.0)).map_err(Error::Config)?;
+        Ok(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::config::parse_yaml;
+
+    fn assert_build_err(result: Result<SpliceRule>, expected_fragment: &str) {
+        let err = result.unwrap_err().to_string();
+        assert!(
+            err.contains(expected_fragment),
+            "expected error containing {expected_fragment:?}, got: {err}"
+        );
+    }
+
+    /// A builder rule and the equivalent YAML normalize to the same
+    /// `SpliceRule`.
+    #[test]
+    fn before_matches_yaml() {
+        let built = Rule::before("wasi:http/handler@0.3.0")
+            .provider("auth")
+            .inject(Injection::from_builtin("hello-tier1"))
+            .inject(Injection::from_path("mw", "./mw.wasm"))
+            .build()
+            .unwrap();
+        let parsed = parse_yaml(
+            r#"
+version: 1
+rules:
+  - before:
+      interface: wasi:http/handler@0.3.0
+      provider:
+        name: auth
+    inject:
+      - builtin: hello-tier1
+      - name: mw
+        path: ./mw.wasm
+"#,
+        )
+        .unwrap();
+        assert_eq!(format!("{built:?}"), format!("{:?}", parsed[0]));
+    }
+
+    #[test]
+    fn before_scopes_to_instance_and_consumer() {
+        let built = Rule::before("my:app/config")
+            .provider_instance(3)
+            .consumer("tenant-b")
+            .inject(Injection::from_name("mw"))
+            .build()
+            .unwrap();
+        let parsed = parse_yaml(
+            r#"
+version: 1
+rules:
+  - before:
+      interface: my:app/config
+      provider:
+        instance: 3
+      consumer:
+        name: tenant-b
+    inject:
+      - name: mw
+"#,
+        )
+        .unwrap();
+        assert_eq!(built, parsed[0]);
+    }
+
+    #[test]
+    fn between_sets_aliases() {
+        let rule = Rule::between("wasi:http/handler", "srv-b", "srv")
+            .inner_alias("renamed-b")
+            .inject(Injection::from_name("mw"))
+            .build()
+            .unwrap();
+        let SpliceRule::Between {
+            inner_name,
+            inner_alias,
+            outer_name,
+            outer_alias,
+            inject,
+            ..
+        } = rule
+        else {
+            panic!("expected Between rule");
+        };
+        assert_eq!(inner_name, "srv-b");
+        assert_eq!(inner_alias.as_deref(), Some("renamed-b"));
+        assert_eq!(outer_name, "srv");
+        assert!(outer_alias.is_none());
+        assert_eq!(inject[0].name, "mw");
+    }
+
+    #[test]
+    fn replace_matches_yaml() {
+        let built = Rule::replace(
+            "my:db/query@0.1.0",
+            Injection::from_path("db-mock", "./db-mock.wasm"),
+        )
+        .provider("db-real")
+        .build()
+        .unwrap();
+        let parsed = parse_yaml(
+            r#"
+version: 1
+rules:
+  - replace:
+      interface: my:db/query@0.1.0
+      provider:
+        name: db-real
+    with:
+      name: db-mock
+      path: ./db-mock.wasm
+"#,
+        )
+        .unwrap();
+        assert_eq!(built, parsed[0]);
+    }
+
+    #[test]
+    fn export_rules_match_yaml() {
+        let built = vec![
+            Rule::hide_export("my:app/debug@0.1.0").build().unwrap(),
+            Rule::rename_export("my:app/api@0.1.0", "api")
+                .build()
+                .unwrap(),
+            Rule::allow_exports(["api"]).build().unwrap(),
+        ];
+        let parsed = parse_yaml(
+            r#"
+version: 1
+rules:
+  - hide_export:
+      interface: my:app/debug@0.1.0
+  - rename_export:
+      interface: my:app/api@0.1.0
+      to: api
+  - allow_exports:
+      interfaces: [api]
+"#,
+        )
+        .unwrap();
+        assert_eq!(built, parsed);
+    }
+
+    #[test]
+    fn build_rejects_empty_inject() {
+        assert_build_err(
+            Rule::before("wasi:http/handler").build(),
+            "'inject' list must contain at least one entry",
+        );
+    }
+
+    #[test]
+    fn build_rejects_empty_interface() {
+        assert_build_err(
+            Rule::before("").inject(Injection::from_name("mw")).build(),
+            "'interface' must not be empty",
+        );
+    }
+
+    #[test]
+    fn build_rejects_same_inner_outer() {
+        assert_build_err(
+            Rule::between("wasi:http/handler", "srv", "srv")
+                .inject(Injection::from_name("mw"))
+                .build(),
+            "'inner' and 'outer' must name different instances",
+        );
+    }
+
+    #[test]
+    fn build_rejects_duplicate_names_within_rule() {
+        assert_build_err(
+            Rule::before("wasi:http/handler")
+                .inject(Injection::from_name("mw"))
+                .inject(Injection::from_path("mw", "./mw.wasm"))
+                .build(),
+            "injection name 'mw' is used in rule 1 but was already declared in rule 1",
+        );
+    }
+}
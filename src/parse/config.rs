@@ -1,5 +1,6 @@
+use crate::nested::PATH_SEP;
 use anyhow::bail;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Parse a YAML splice configuration string into a list of validated
@@ -21,7 +22,14 @@ pub struct ConfigFile {
 pub struct YamlRule {
     before: Option<YamlStrategyBefore>,
     between: Option<YamlStrategyBetween>,
+    replace: Option<YamlStrategyReplace>,
+    hide_export: Option<YamlHideExport>,
+    rename_export: Option<YamlRenameExport>,
+    allow_exports: Option<YamlAllowExports>,
+    #[serde(default)]
     inject: Vec<YamlInjection>,
+    /// The replacement component of a `replace` rule.
+    with: Option<YamlInjection>,
 }
 
 /// Raw YAML shape of an `inject` entry. Either:
@@ -38,6 +46,9 @@ pub struct YamlInjection {
     pub name: Option<String>,
     pub path: Option<String>,
     pub builtin: Option<BuiltinSpec>,
+    pub instance: Option<InstanceMode>,
+    #[serde(default)]
+    pub deps: Vec<MiddlewareDep>,
 }
 
 /// `inject: [{ builtin: ... }]` payload. Two shapes — short scalar
@@ -77,6 +88,7 @@ impl BuiltinSpec {
 pub struct YamlStrategyBefore {
     interface: String,
     provider: Option<YamlProviderOpt>,
+    consumer: Option<YamlConsumer>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -86,6 +98,28 @@ pub struct YamlStrategyBetween {
     interface: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct YamlStrategyReplace {
+    interface: String,
+    provider: Option<YamlReplacedProvider>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct YamlHideExport {
+    interface: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct YamlRenameExport {
+    interface: String,
+    to: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct YamlAllowExports {
+    interfaces: Vec<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct YamlProviderReq {
     // The name of the instance to match on in the component
@@ -100,10 +134,27 @@ pub struct YamlProviderReq {
 pub struct YamlProviderOpt {
     // The name of the instance to match on in the component
     name: Option<String>,
+    // The index of the instance to match on, for telling apart
+    // instances of the same component (`node N` in `--annotate-wac`)
+    instance: Option<u32>,
     // Alias the matched provider to this name in the generated wac
     alias: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct YamlReplacedProvider {
+    // The name of the instance to replace
+    name: Option<String>,
+    // The index of the instance to replace (`node N` in `--annotate-wac`)
+    instance: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct YamlConsumer {
+    // The name of the instance importing the interface from the provider
+    name: String,
+}
+
 /// Extra information stored on an [`Injection`] when it has been resolved as a
 /// tier-1 adapter by `add_to_inject_plan`.  Not present in the YAML config.
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -114,25 +165,63 @@ pub struct AdapterInjectionInfo {
     pub tier1_interfaces: Vec<String>,
 }
 
+/// How many instances of a middleware serve the sites it's injected
+/// at (`instance:` on an `inject` entry).
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[serde(rename_all = "kebab-case")]
+pub enum InstanceMode {
+    /// One instance for every site, so they share its state (e.g. a
+    /// rate limiter). Each site's interface is wired into it, so a
+    /// non-tier-1 middleware can only be shared across sites that
+    /// wrap different interfaces, or the same provider.
+    Shared,
+    /// A fresh instance per site (e.g. a per-endpoint cache).
+    PerSite,
+}
+
+/// A component an injected middleware's imports are wired to (`deps:`
+/// on an `inject` entry), such as a kv store the composition doesn't
+/// have. Instantiated once, however many middlewares list it.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MiddlewareDep {
+    /// The component's name (used as the WAC variable).
+    pub name: String,
+    /// Path to the component's `.wasm` file on disk.
+    pub path: String,
+}
+
 /// A middleware to inject at a splice point. Constructed from the YAML
 /// config `inject` list or programmatically via [`Injection::from_path`]
 /// / [`Injection::from_name`].
-#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Injection {
     /// The middleware's logical name (used as the WAC variable).
     pub name: String,
     /// Path to the middleware `.wasm` file on disk. `None` when the
     /// middleware is referenced by name only (contract checks will
     /// produce a warning instead of a definitive result).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
     /// Name of a splicer-shipped builtin middleware (see
     /// [`crate::builtins`]). Set by the YAML parser when an inject
     /// entry uses `builtin: <name>`. The splice pipeline materializes
-    /// the embedded bytes to disk and populates [`Injection::path`]
+    /// the embedded bytes into the splits dir (or the in-memory
+    /// package map for [`crate::splice_bytes`]) and populates [`Injection::path`]
     /// before contract validation runs, so downstream stages don't
     /// need to know about builtins.
-    #[serde(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub builtin: Option<String>,
+    /// Whether the sites this middleware is injected at share one
+    /// instance. `None` keeps the default: tier-1 middleware is
+    /// shared (each site gets its own adapter), anything else is
+    /// per-site.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<InstanceMode>,
+    /// Components to wire the middleware's imports to, ahead of the
+    /// composition's own instances. Imports neither provides are left
+    /// to the host.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deps: Vec<MiddlewareDep>,
     /// Populated at runtime by `add_to_inject_plan` when this injection
     /// is resolved as a tier-1 adapter. Not part of the YAML config and
     /// not user-settable — use the `generated_adapters` field on
@@ -150,6 +239,8 @@ impl Injection {
             name: name.into(),
             path: Some(path.into()),
             builtin: None,
+            instance: None,
+            deps: Vec::new(),
             adapter_info: None,
         }
     }
@@ -162,6 +253,8 @@ impl Injection {
             name: name.into(),
             path: None,
             builtin: None,
+            instance: None,
+            deps: Vec::new(),
             adapter_info: None,
         }
     }
@@ -175,13 +268,43 @@ impl Injection {
             name: name.clone(),
             path: None,
             builtin: Some(name),
+            instance: None,
+            deps: Vec::new(),
             adapter_info: None,
         }
     }
+
+    /// Choose whether the sites this middleware is injected at share
+    /// one instance.
+    pub fn with_instance(mut self, mode: InstanceMode) -> Self {
+        self.instance = Some(mode);
+        self
+    }
+
+    /// Wire the middleware's imports of whatever the component at
+    /// `path` exports to an instance of it named `name`.
+    pub fn with_dep(mut self, name: impl Into<String>, path: impl Into<String>) -> Self {
+        self.deps.push(MiddlewareDep {
+            name: name.into(),
+            path: path.into(),
+        });
+        self
+    }
+
+    /// The instance mode in effect, once the injection has been
+    /// resolved (or not) as a tier-1 adapter.
+    pub(crate) fn instance_mode(&self) -> InstanceMode {
+        self.instance.unwrap_or(if self.adapter_info.is_some() {
+            InstanceMode::Shared
+        } else {
+            InstanceMode::PerSite
+        })
+    }
 }
 
 /// A validated splice rule, normalized from the YAML config.
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SpliceRule {
     /// Inject middleware before a provider on an interface edge.
     Before {
@@ -189,6 +312,15 @@ pub enum SpliceRule {
         interface: String,
         /// Optional provider name to scope the match.
         provider_name: Option<String>,
+        /// Optional provider instance index to scope the match: the
+        /// composition's `N`th instance, as numbered in the
+        /// `node N` notes of [`crate::lowlevel::CompositionPlan::to_annotated_wac`].
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        provider_instance: Option<u32>,
+        /// Optional name of the instance importing the interface from
+        /// the provider, to scope the match to that edge.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        consumer_name: Option<String>,
         /// Optional alias for the matched provider in the generated WAC.
         provider_alias: Option<String>,
         /// Middleware to inject (in order).
@@ -209,41 +341,155 @@ pub enum SpliceRule {
         /// Middleware to inject (in order).
         inject: Vec<Injection>,
     },
+    /// Swap the provider of an interface for a different component
+    /// exporting it, rewiring the provider's consumers to it.
+    Replace {
+        /// The interface whose provider is replaced.
+        interface: String,
+        /// Optional provider name to scope the match.
+        provider_name: Option<String>,
+        /// Optional provider instance index to scope the match, as for
+        /// [`SpliceRule::Before`].
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        provider_instance: Option<u32>,
+        /// The replacement: always exactly one component, kept as a
+        /// list so it's handled like any other rule's injections.
+        inject: Vec<Injection>,
+    },
+    /// Drop an export from the composition, and any component only it
+    /// kept in use.
+    HideExport {
+        /// The export to drop.
+        interface: String,
+    },
+    /// Export an interface from the composition under another name.
+    RenameExport {
+        /// The export to rename.
+        interface: String,
+        /// Its new name.
+        to: String,
+    },
+    /// Fail unless every export of the composition, once the other
+    /// rules are applied, is one of `interfaces`.
+    AllowExports {
+        /// The exports allowed to remain.
+        interfaces: Vec<String>,
+    },
 }
 
 impl SpliceRule {
-    /// The injection list for this rule. Both variants always carry
-    /// one — only the matching strategy around it differs.
+    /// The interface this rule matches on. `None` for
+    /// [`SpliceRule::AllowExports`], which covers every export.
+    pub fn interface(&self) -> Option<&str> {
+        match self {
+            SpliceRule::Before { interface, .. }
+            | SpliceRule::Between { interface, .. }
+            | SpliceRule::Replace { interface, .. }
+            | SpliceRule::HideExport { interface }
+            | SpliceRule::RenameExport { interface, .. } => Some(interface),
+            SpliceRule::AllowExports { .. } => None,
+        }
+    }
+
+    /// The injection list for this rule. Only the matching strategy
+    /// around it differs between the injecting rules (a replacement is
+    /// a one-entry list); rules on the composition's exports inject
+    /// nothing.
     pub fn inject(&self) -> &[Injection] {
         match self {
-            SpliceRule::Before { inject, .. } | SpliceRule::Between { inject, .. } => inject,
+            SpliceRule::Before { inject, .. }
+            | SpliceRule::Between { inject, .. }
+            | SpliceRule::Replace { inject, .. } => inject,
+            SpliceRule::HideExport { .. }
+            | SpliceRule::RenameExport { .. }
+            | SpliceRule::AllowExports { .. } => &[],
         }
     }
 
     /// Mutable view of the injection list, for callers that need to
     /// rewrite entries in place (e.g. resolving builtins to disk
-    /// paths).
-    pub fn inject_mut(&mut self) -> &mut Vec<Injection> {
+    /// paths). `None` for rules on the composition's exports.
+    pub fn inject_mut(&mut self) -> Option<&mut Vec<Injection>> {
         match self {
-            SpliceRule::Before { inject, .. } | SpliceRule::Between { inject, .. } => inject,
+            SpliceRule::Before { inject, .. }
+            | SpliceRule::Between { inject, .. }
+            | SpliceRule::Replace { inject, .. } => Some(inject),
+            SpliceRule::HideExport { .. }
+            | SpliceRule::RenameExport { .. }
+            | SpliceRule::AllowExports { .. } => None,
         }
     }
+
+    /// The instance names this rule selects by. Each may be qualified
+    /// with the path of the nested composition it's in
+    /// (`outer/inner/srv-b`).
+    pub(crate) fn selectors(&self) -> Vec<&str> {
+        let names = match self {
+            SpliceRule::Before {
+                provider_name,
+                consumer_name,
+                ..
+            } => vec![provider_name.as_ref(), consumer_name.as_ref()],
+            SpliceRule::Between {
+                inner_name,
+                outer_name,
+                ..
+            } => vec![Some(inner_name), Some(outer_name)],
+            SpliceRule::Replace { provider_name, .. } => vec![provider_name.as_ref()],
+            _ => vec![],
+        };
+        names.into_iter().flatten().map(String::as_str).collect()
+    }
+
+    /// Mutable view of [`SpliceRule::selectors`].
+    pub(crate) fn selectors_mut(&mut self) -> Vec<&mut String> {
+        let names = match self {
+            SpliceRule::Before {
+                provider_name,
+                consumer_name,
+                ..
+            } => vec![provider_name.as_mut(), consumer_name.as_mut()],
+            SpliceRule::Between {
+                inner_name,
+                outer_name,
+                ..
+            } => vec![Some(inner_name), Some(outer_name)],
+            SpliceRule::Replace { provider_name, .. } => vec![provider_name.as_mut()],
+            _ => vec![],
+        };
+        names.into_iter().flatten().collect()
+    }
+
+    /// Whether this rule reshapes the composition's exports rather
+    /// than injecting anything.
+    pub fn on_exports(&self) -> bool {
+        matches!(
+            self,
+            SpliceRule::HideExport { .. }
+                | SpliceRule::RenameExport { .. }
+                | SpliceRule::AllowExports { .. }
+        )
+    }
 }
 
+/// The strategies a rule picks one of, as listed in errors.
+const STRATEGIES: &str =
+    "'before', 'between', 'replace', 'hide_export', 'rename_export' or 'allow_exports'";
+
 impl ConfigFile {
     /// Validate the parsed configuration, returning a descriptive error for any problem.
     ///
     /// Checks (in order):
     /// 1. Supported version number.
-    /// 2. Each rule specifies exactly one strategy (`before` XOR `between`).
-    /// 3. Each rule's `inject` list is non-empty.
-    /// 4. Each injection name is non-empty.
-    /// 5. Each injection `path`, when present, is non-empty.
-    /// 6. Interface names are non-empty.
-    /// 7. `before` provider `name`, when present, is non-empty.
-    /// 8. `between` `inner` and `outer` must name different instances.
-    /// 9. Injection names are globally unique across all rules (required because
-    ///    each name becomes a WAC instance identifier and `--dep` argument key).
+    /// 2. Each rule specifies exactly one strategy (`before`, `between`,
+    ///    `replace`, `hide_export`, `rename_export` or `allow_exports`),
+    ///    with `inject` for the first two, `with` for `replace`, and
+    ///    neither for the rest.
+    /// 3. Each injection uses exactly one form (`name` XOR `builtin`).
+    ///
+    /// The remaining checks run on the normalized rules via
+    /// [`validate_rules`], so YAML configs and rules built with
+    /// [`crate::types::Rule`] are held to the same standard.
     pub fn validate(&self) -> anyhow::Result<()> {
         if self.version != 1 {
             bail!(
@@ -252,202 +498,393 @@ impl ConfigFile {
             );
         }
 
-        // name → first rule index (1-based) for duplicate detection
-        let mut seen_names: HashMap<&str, usize> = HashMap::new();
-
         for (i, rule) in self.rules.iter().enumerate() {
             let rule_num = i + 1;
 
-            // Strategy must be exactly one of before/between.
-            match (&rule.before, &rule.between) {
-                (Some(_), Some(_)) => {
-                    bail!("rule {rule_num}: a rule may specify 'before' or 'between', not both")
-                }
-                (None, None) => {
-                    bail!("rule {rule_num}: a rule must specify either 'before' or 'between'")
-                }
-                _ => {}
+            // Strategy must be exactly one of before/between/replace or
+            // the export rules.
+            let strategies = [
+                rule.before.is_some(),
+                rule.between.is_some(),
+                rule.replace.is_some(),
+                rule.hide_export.is_some(),
+                rule.rename_export.is_some(),
+                rule.allow_exports.is_some(),
+            ];
+            match strategies.iter().filter(|set| **set).count() {
+                0 => bail!("rule {rule_num}: a rule must specify one of {}", STRATEGIES),
+                1 => {}
+                _ => bail!(
+                    "rule {rule_num}: a rule may specify only one of {}",
+                    STRATEGIES
+                ),
             }
-
-            // Interface name must be non-empty.
-            let interface = if let Some(b) = &rule.before {
-                &b.interface
-            } else if let Some(bw) = &rule.between {
-                &bw.interface
-            } else {
-                unreachable!()
-            };
-            if interface.is_empty() {
-                bail!("rule {rule_num}: 'interface' must not be empty");
+            let on_exports = rule.hide_export.is_some()
+                || rule.rename_export.is_some()
+                || rule.allow_exports.is_some();
+            if on_exports && (!rule.inject.is_empty() || rule.with.is_some()) {
+                bail!(
+                    "rule {rule_num}: rules on the composition's exports take no 'inject' or \
+                     'with'"
+                );
             }
-
-            // before-specific checks.
-            if let Some(before) = &rule.before {
-                if let Some(prov) = &before.provider {
-                    if prov.name.as_deref() == Some("") {
-                        bail!(
-                            "rule {rule_num}: provider 'name' must not be empty if specified \
-                             (omit the key to leave it unset)"
-                        );
-                    }
+            match (&rule.replace, &rule.with) {
+                (Some(_), None) => {
+                    bail!("rule {rule_num}: a 'replace' rule needs a 'with' component")
                 }
-            }
-
-            // between-specific checks.
-            if let Some(between) = &rule.between {
-                if between.inner.name == between.outer.name {
-                    bail!(
-                        "rule {rule_num} (between): 'inner' and 'outer' must name different \
-                         instances, but both are '{}'",
-                        between.inner.name
-                    );
+                (Some(_), Some(_)) if !rule.inject.is_empty() => bail!(
+                    "rule {rule_num}: a 'replace' rule takes its component under 'with', not \
+                     'inject'"
+                ),
+                (None, Some(_)) => {
+                    bail!("rule {rule_num}: 'with' only applies to 'replace' rules")
                 }
+                _ => {}
             }
 
-            // inject list must be non-empty.
-            if rule.inject.is_empty() {
-                bail!("rule {rule_num}: 'inject' list must contain at least one entry");
-            }
-
-            for (j, inj) in rule.inject.iter().enumerate() {
+            for (j, inj) in rule.inject.iter().chain(&rule.with).enumerate() {
                 let inj_num = j + 1;
 
                 // user form vs builtin form are mutually exclusive.
                 // Builtin form scopes its WAC-var override and (later)
-                // its config inside the `builtin:` map, so top-level
-                // `name`/`path` next to `builtin:` is a misconfig.
-                match (&inj.builtin, &inj.name, &inj.path) {
-                    (None, None, _) => {
+                // its config inside the `builtin:` map, so a top-level
+                // `name` next to `builtin:` is a misconfig. (`path`
+                // next to `builtin:` is caught by `validate_rules`.)
+                match (&inj.builtin, &inj.name) {
+                    (None, None) => {
                         bail!("rule {rule_num}, injection {inj_num}: missing 'name' or 'builtin'")
                     }
-                    (Some(_), Some(_), _) => bail!(
+                    (Some(_), Some(_)) => bail!(
                         "rule {rule_num}, injection {inj_num}: 'builtin' replaces top-level \
                          'name' — move the WAC-var override to 'builtin.alias'"
                     ),
-                    (Some(_), _, Some(_)) => bail!(
-                        "rule {rule_num}, injection {inj_num}: 'builtin' and 'path' are mutually \
-                         exclusive — drop one"
-                    ),
                     _ => {}
                 }
-                if inj.name.as_deref() == Some("") {
-                    bail!("rule {rule_num}, injection {inj_num}: injection name must not be empty");
+            }
+        }
+
+        validate_rules(
+            &self
+                .rules
+                .iter()
+                .map(YamlRule::to_splice_rule)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Convert validated YAML rules into normalized [`SpliceRule`]s.
+    ///
+    /// Assumes [`ConfigFile::validate`] has already been called.
+    pub fn into_splice_rules(self) -> Vec<SpliceRule> {
+        self.rules.iter().map(YamlRule::to_splice_rule).collect()
+    }
+}
+
+impl YamlRule {
+    /// Normalize this YAML rule into a [`SpliceRule`]. Assumes the
+    /// shape checks in [`ConfigFile::validate`] have passed.
+    fn to_splice_rule(&self) -> SpliceRule {
+        let inject = self.inject.iter().map(to_injection).collect();
+        if let Some(YamlStrategyBefore {
+            interface,
+            provider,
+            consumer,
+        }) = &self.before
+        {
+            SpliceRule::Before {
+                interface: interface.clone(),
+                provider_name: provider.as_ref().and_then(|p| p.name.clone()),
+                provider_instance: provider.as_ref().and_then(|p| p.instance),
+                consumer_name: consumer.as_ref().map(|c| c.name.clone()),
+                provider_alias: provider.as_ref().and_then(|p| p.alias.clone()),
+                inject,
+            }
+        } else if let Some(YamlStrategyBetween {
+            interface,
+            inner,
+            outer,
+        }) = &self.between
+        {
+            SpliceRule::Between {
+                interface: interface.clone(),
+                inner_name: inner.name.clone(),
+                inner_alias: inner.alias.clone(),
+                outer_name: outer.name.clone(),
+                outer_alias: outer.alias.clone(),
+                inject,
+            }
+        } else if let Some(YamlStrategyReplace {
+            interface,
+            provider,
+        }) = &self.replace
+        {
+            SpliceRule::Replace {
+                interface: interface.clone(),
+                provider_name: provider.as_ref().and_then(|p| p.name.clone()),
+                provider_instance: provider.as_ref().and_then(|p| p.instance),
+                inject: self.with.iter().map(to_injection).collect(),
+            }
+        } else if let Some(YamlHideExport { interface }) = &self.hide_export {
+            SpliceRule::HideExport {
+                interface: interface.clone(),
+            }
+        } else if let Some(YamlRenameExport { interface, to }) = &self.rename_export {
+            SpliceRule::RenameExport {
+                interface: interface.clone(),
+                to: to.clone(),
+            }
+        } else if let Some(YamlAllowExports { interfaces }) = &self.allow_exports {
+            SpliceRule::AllowExports {
+                interfaces: interfaces.clone(),
+            }
+        } else {
+            unreachable!("validate() guarantees exactly one strategy per rule")
+        }
+    }
+}
+
+/// Validate a list of normalized [`SpliceRule`]s, returning a
+/// descriptive error for the first problem found. Rules are numbered
+/// from 1 in error messages, matching their position in the list.
+///
+/// Checks (in order, per rule):
+/// 1. Interface names, and `rename_export` and `allow_exports`
+///    export names, are non-empty.
+/// 2. `before` provider and consumer names, and `replace` provider
+///    names, when present, are non-empty.
+/// 3. `between` `inner` and `outer` must name different instances, and
+///    `rename_export` must change the name.
+/// 4. Each injecting rule's `inject` list is non-empty (a single entry
+///    for `replace`).
+/// 5. Each injection name (or builtin name / alias) is non-empty.
+/// 6. Each injection `path`, when present, is non-empty.
+/// 7. Builtin injections don't also carry a `path`.
+/// 8. Each injection's `deps` entries have a name and a path, and every
+///    listing of a dependency name gives the same path.
+/// 9. Injection names are globally unique across all rules (required because
+///    each name becomes a WAC instance identifier and `--dep` argument key),
+///    except that other rules may reuse a `shared` injection: same name,
+///    same package, and `instance: shared` on every use.
+/// 10. No dependency shares a name with an injection.
+/// 11. Instance names qualified with a nested composition's path
+///     (`outer/srv-b`) have no empty segment, and all of a rule's
+///     instance names are in the same composition.
+pub fn validate_rules(rules: &[SpliceRule]) -> anyhow::Result<()> {
+    // name → (first rule index (1-based), its injection) for duplicate detection
+    let mut seen_names: HashMap<&str, (usize, &Injection)> = HashMap::new();
+    // dependency name → its path; every listing must agree.
+    let mut seen_deps: HashMap<&str, &str> = HashMap::new();
+
+    for (i, rule) in rules.iter().enumerate() {
+        let rule_num = i + 1;
+
+        if rule.interface() == Some("") {
+            bail!("rule {rule_num}: 'interface' must not be empty");
+        }
+
+        match rule {
+            SpliceRule::Before {
+                provider_name,
+                consumer_name,
+                ..
+            } => {
+                if provider_name.as_deref() == Some("") {
+                    bail!(
+                        "rule {rule_num}: provider 'name' must not be empty if specified \
+                         (omit the key to leave it unset)"
+                    );
+                }
+                if consumer_name.as_deref() == Some("") {
+                    bail!(
+                        "rule {rule_num}: consumer 'name' must not be empty if specified \
+                         (omit the key to leave it unset)"
+                    );
+                }
+            }
+            SpliceRule::Between {
+                inner_name,
+                outer_name,
+                ..
+            } => {
+                if inner_name == outer_name {
+                    bail!(
+                        "rule {rule_num} (between): 'inner' and 'outer' must name different \
+                         instances, but both are '{inner_name}'"
+                    );
                 }
-                if inj.path.as_deref() == Some("") {
+            }
+            SpliceRule::Replace {
+                provider_name,
+                inject,
+                ..
+            } => {
+                if provider_name.as_deref() == Some("") {
                     bail!(
-                        "rule {rule_num}, injection {inj_num}: 'path' must not be empty if \
-                         specified (omit the key to leave it unset)"
+                        "rule {rule_num}: provider 'name' must not be empty if specified \
+                         (omit the key to leave it unset)"
                     );
                 }
-                if let Some(spec) = &inj.builtin {
-                    if spec.builtin_name().is_empty() {
+                if inject.len() > 1 {
+                    bail!(
+                        "rule {rule_num} (replace): a provider is replaced by exactly one \
+                         component, but {} are given",
+                        inject.len()
+                    );
+                }
+            }
+            SpliceRule::HideExport { .. } => {}
+            SpliceRule::RenameExport { interface, to } => {
+                if to.is_empty() {
+                    bail!("rule {rule_num}: 'to' must not be empty");
+                }
+                if to == interface {
+                    bail!("rule {rule_num} (rename_export): '{interface}' is renamed to itself");
+                }
+            }
+            SpliceRule::AllowExports { interfaces } => {
+                if interfaces.iter().any(String::is_empty) {
+                    bail!("rule {rule_num}: 'interfaces' entries must not be empty");
+                }
+            }
+        }
+
+        let mut scopes = rule.selectors().into_iter().map(|selector| {
+            selector
+                .rsplit_once(PATH_SEP)
+                .map_or("", |(scope, _)| scope)
+        });
+        if let Some(selector) = rule
+            .selectors()
+            .into_iter()
+            .find(|sel| sel.contains(PATH_SEP) && sel.split(PATH_SEP).any(str::is_empty))
+        {
+            bail!("rule {rule_num}: '{selector}' has an empty path segment");
+        }
+        if let Some(scope) = scopes.next() {
+            if scopes.any(|other| other != scope) {
+                bail!(
+                    "rule {rule_num}: the instances a rule names must all be in the same \
+                     composition, but {} aren't",
+                    rule.selectors()
+                        .iter()
+                        .map(|sel| format!("'{sel}'"))
+                        .collect::<Vec<_>>()
+                        .join(" and ")
+                );
+            }
+        }
+
+        if rule.on_exports() {
+            continue;
+        }
+        if rule.inject().is_empty() {
+            bail!("rule {rule_num}: 'inject' list must contain at least one entry");
+        }
+
+        for (j, inj) in rule.inject().iter().enumerate() {
+            let inj_num = j + 1;
+
+            match inj.builtin.as_deref() {
+                Some("") => {
+                    bail!("rule {rule_num}, injection {inj_num}: builtin 'name' must not be empty")
+                }
+                // A builtin's WAC var is its alias (falling back to the
+                // builtin name), so an empty name here means an empty alias.
+                Some(_) if inj.name.is_empty() => bail!(
+                    "rule {rule_num}, injection {inj_num}: builtin 'alias' must not be \
+                     empty if specified (omit the key to leave it unset)"
+                ),
+                Some(_) if inj.path.is_some() => bail!(
+                    "rule {rule_num}, injection {inj_num}: 'builtin' and 'path' are mutually \
+                     exclusive — drop one"
+                ),
+                Some(_) => {}
+                None => {
+                    if inj.name.is_empty() {
                         bail!(
-                            "rule {rule_num}, injection {inj_num}: builtin 'name' must not be \
+                            "rule {rule_num}, injection {inj_num}: injection name must not be \
                              empty"
                         );
                     }
-                    if spec.alias() == Some("") {
+                    if inj.path.as_deref() == Some("") {
                         bail!(
-                            "rule {rule_num}, injection {inj_num}: builtin 'alias' must not be \
-                             empty if specified (omit the key to leave it unset)"
+                            "rule {rule_num}, injection {inj_num}: 'path' must not be empty if \
+                             specified (omit the key to leave it unset)"
                         );
                     }
                 }
+            }
 
-                // Effective WAC-var name for uniqueness: builtin form
-                // uses `alias` falling back to the builtin's name; user
-                // form uses the top-level `name`.
-                let effective_name = if let Some(spec) = &inj.builtin {
-                    spec.alias().unwrap_or_else(|| spec.builtin_name())
-                } else {
-                    inj.name.as_deref().expect("validated above")
-                };
-
-                // Global uniqueness: injection names are used as WAC identifiers.
-                if let Some(first_rule) = seen_names.get(effective_name) {
+            for dep in &inj.deps {
+                if dep.name.is_empty() || dep.path.is_empty() {
                     bail!(
-                        "injection name '{effective_name}' is used in rule {rule_num} but was \
-                         already declared in rule {first_rule}; each injection must have a \
-                         globally unique name"
+                        "rule {rule_num}, injection {inj_num}: every 'deps' entry needs a \
+                         'name' and a 'path'"
                     );
                 }
-                seen_names.insert(effective_name, rule_num);
+                match seen_deps.get(dep.name.as_str()) {
+                    Some(path) if *path != dep.path => bail!(
+                        "dependency '{}' is listed with path '{}' in rule {rule_num}, but \
+                         with '{path}' elsewhere; each dependency name is one component",
+                        dep.name,
+                        dep.path
+                    ),
+                    _ => {
+                        seen_deps.insert(&dep.name, &dep.path);
+                    }
+                }
             }
-        }
 
-        Ok(())
+            // Global uniqueness: injection names are used as WAC identifiers.
+            if let Some((first_rule, first)) = seen_names.get(inj.name.as_str()) {
+                let shared = |inj: &Injection| inj.instance == Some(InstanceMode::Shared);
+                let same_package = (&first.path, &first.builtin) == (&inj.path, &inj.builtin);
+                if *first_rule == rule_num || !shared(first) || !shared(inj) || !same_package {
+                    bail!(
+                        "injection name '{}' is used in rule {rule_num} but was already \
+                         declared in rule {first_rule}; each injection must have a globally \
+                         unique name, unless every use sets 'instance: shared' and the same \
+                         package",
+                        inj.name
+                    );
+                }
+                continue;
+            }
+            seen_names.insert(&inj.name, (rule_num, inj));
+        }
     }
-
-    /// Convert validated YAML rules into normalized [`SpliceRule`]s.
-    ///
-    /// Assumes [`ConfigFile::validate`] has already been called.
-    pub fn into_splice_rules(self) -> Vec<SpliceRule> {
-        self.rules
-            .into_iter()
-            .map(
-                |YamlRule {
-                     before,
-                     between,
-                     inject,
-                 }| {
-                    let inject = inject.into_iter().map(into_injection).collect();
-                    if let Some(YamlStrategyBefore {
-                        interface,
-                        provider,
-                    }) = before
-                    {
-                        SpliceRule::Before {
-                            interface,
-                            provider_name: provider.as_ref().and_then(|p| p.name.clone()),
-                            provider_alias: provider.and_then(|p| p.alias),
-                            inject,
-                        }
-                    } else if let Some(YamlStrategyBetween {
-                        interface,
-                        inner,
-                        outer,
-                    }) = between
-                    {
-                        SpliceRule::Between {
-                            interface,
-                            inner_name: inner.name,
-                            inner_alias: inner.alias,
-                            outer_name: outer.name,
-                            outer_alias: outer.alias,
-                            inject,
-                        }
-                    } else {
-                        unreachable!("validate() guarantees exactly one strategy per rule")
-                    }
-                },
-            )
-            .collect()
+    if let Some((name, (rule_num, _))) = seen_names
+        .iter()
+        .find(|(name, _)| seen_deps.contains_key(*name))
+    {
+        bail!(
+            "'{name}' names both an injection (in rule {rule_num}) and a dependency; \
+             dependencies are WAC identifiers too, so pick another name"
+        );
     }
+
+    Ok(())
 }
 
-/// Map a validated [`YamlInjection`] to the canonical [`Injection`].
+/// Map a shape-checked [`YamlInjection`] to the canonical [`Injection`].
 /// `validate()` has already enforced that exactly one form (user vs
-/// builtin) is set with non-empty names. The builtin form's `alias`
-/// (if any) becomes the WAC variable name; otherwise the builtin's
-/// own name is reused.
-fn into_injection(yaml: YamlInjection) -> Injection {
-    let YamlInjection {
-        name,
-        path,
-        builtin,
-    } = yaml;
-    let (wac_name, builtin_name) = match builtin {
+/// builtin) is set. The builtin form's `alias` (if any) becomes the
+/// WAC variable name; otherwise the builtin's own name is reused.
+fn to_injection(yaml: &YamlInjection) -> Injection {
+    let (wac_name, builtin_name) = match &yaml.builtin {
         Some(spec) => {
             let bname = spec.builtin_name().to_string();
             let alias = spec.alias().map(str::to_string);
             (alias.unwrap_or_else(|| bname.clone()), Some(bname))
         }
-        None => (name.expect("validated"), None),
+        None => (yaml.name.clone().expect("validated"), None),
     };
     Injection {
         name: wac_name,
-        path,
+        path: yaml.path.clone(),
         builtin: builtin_name,
+        instance: yaml.instance,
+        deps: yaml.deps.clone(),
         adapter_info: None,
     }
 }
@@ -475,6 +912,7 @@ rules:
             provider_name,
             provider_alias,
             inject,
+            ..
         } = &rules[0]
         else {
             panic!("expected Before rule");
@@ -512,6 +950,35 @@ rules:
         assert!(provider_alias.is_none());
     }
 
+    #[test]
+    fn parse_before_rule_instance_and_consumer() {
+        let yaml = r#"
+version: 1
+rules:
+  - before:
+      interface: my:app/config
+      provider:
+        instance: 3
+      consumer:
+        name: tenant-b
+    inject:
+      - name: middleware-a
+"#;
+        let rules = parse_yaml(yaml).unwrap();
+        let SpliceRule::Before {
+            provider_name,
+            provider_instance,
+            consumer_name,
+            ..
+        } = &rules[0]
+        else {
+            panic!("expected Before rule");
+        };
+        assert!(provider_name.is_none());
+        assert_eq!(*provider_instance, Some(3));
+        assert_eq!(consumer_name.as_deref(), Some("tenant-b"));
+    }
+
     #[test]
     fn parse_between_rule() {
         let yaml = r#"
@@ -645,7 +1112,7 @@ rules:
     inject:
       - name: mw
 "#,
-            "'before' or 'between', not both",
+            "only one of 'before', 'between', 'replace', 'hide_export'",
         );
     }
 
@@ -658,7 +1125,46 @@ rules:
   - inject:
       - name: mw
 "#,
-            "either 'before' or 'between'",
+            "one of 'before', 'between', 'replace', 'hide_export'",
+        );
+    }
+
+    #[test]
+    fn validate_replace_takes_with_not_inject() {
+        assert_err(
+            r#"
+version: 1
+rules:
+  - replace:
+      interface: my:db/query@0.1.0
+"#,
+            "a 'replace' rule needs a 'with' component",
+        );
+        assert_err(
+            r#"
+version: 1
+rules:
+  - replace:
+      interface: my:db/query@0.1.0
+    with:
+      name: db-mock
+    inject:
+      - name: mw
+"#,
+            "takes its component under 'with', not 'inject'",
+        );
+        assert_err(
+            r#"
+version: 1
+rules:
+  - before:
+      interface: my:db/query@0.1.0
+    with:
+      name: db-mock
+    inject:
+      - name: mw
+"#,
+            "'with' only applies to 'replace' rules",
         );
     }
 
@@ -739,6 +1245,23 @@ rules:
         );
     }
 
+    #[test]
+    fn validate_empty_before_consumer_name() {
+        assert_err(
+            r#"
+version: 1
+rules:
+  - before:
+      interface: my:app/config
+      consumer:
+        name: ""
+    inject:
+      - name: mw
+"#,
+            "consumer 'name' must not be empty",
+        );
+    }
+
     #[test]
     fn validate_between_same_inner_outer() {
         assert_err(
@@ -758,6 +1281,38 @@ rules:
         );
     }
 
+    #[test]
+    fn validate_nested_selectors_share_a_composition() {
+        assert_err(
+            r#"
+version: 1
+rules:
+  - between:
+      interface: wasi:http/handler
+      inner:
+        name: outer/srv
+      outer:
+        name: gateway
+    inject:
+      - name: mw
+"#,
+            "must all be in the same composition, but 'outer/srv' and 'gateway' aren't",
+        );
+        assert_err(
+            r#"
+version: 1
+rules:
+  - before:
+      interface: wasi:http/handler
+      provider:
+        name: outer//srv
+    inject:
+      - name: mw
+"#,
+            "'outer//srv' has an empty path segment",
+        );
+    }
+
     #[test]
     fn validate_duplicate_injection_name_across_rules() {
         assert_err(
@@ -777,6 +1332,130 @@ rules:
         );
     }
 
+    #[test]
+    fn validate_shared_injection_reused_across_rules() {
+        let rules = parse_yaml(
+            r#"
+version: 1
+rules:
+  - before:
+      interface: wasi:http/handler
+    inject:
+      - name: limiter
+        path: ./limiter.wasm
+        instance: shared
+  - before:
+      interface: wasi:logging/log
+    inject:
+      - name: limiter
+        path: ./limiter.wasm
+        instance: shared
+"#,
+        )
+        .unwrap();
+        assert_eq!(rules[1].inject()[0].instance, Some(InstanceMode::Shared));
+
+        assert_err(
+            r#"
+version: 1
+rules:
+  - before:
+      interface: wasi:http/handler
+    inject:
+      - name: limiter
+        path: ./limiter.wasm
+        instance: shared
+  - before:
+      interface: wasi:logging/log
+    inject:
+      - name: limiter
+        path: ./limiter.wasm
+        instance: per-site
+"#,
+            "unless every use sets 'instance: shared' and the same package",
+        );
+    }
+
+    #[test]
+    fn parse_injection_deps() {
+        let rules = parse_yaml(
+            r#"
+version: 1
+rules:
+  - before:
+      interface: wasi:http/handler
+    inject:
+      - name: cache
+        path: ./cache.wasm
+        deps:
+          - name: kv
+            path: ./kv.wasm
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            rules[0].inject()[0].deps,
+            [MiddlewareDep {
+                name: "kv".into(),
+                path: "./kv.wasm".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn validate_conflicting_deps() {
+        assert_err(
+            r#"
+version: 1
+rules:
+  - before:
+      interface: wasi:http/handler
+    inject:
+      - name: cache
+        deps:
+          - name: kv
+            path: ""
+"#,
+            "every 'deps' entry needs a 'name' and a 'path'",
+        );
+        assert_err(
+            r#"
+version: 1
+rules:
+  - before:
+      interface: wasi:http/handler
+    inject:
+      - name: cache
+        deps:
+          - name: kv
+            path: ./kv.wasm
+  - before:
+      interface: wasi:logging/log
+    inject:
+      - name: audit
+        deps:
+          - name: kv
+            path: ./other-kv.wasm
+"#,
+            "dependency 'kv' is listed with path './other-kv.wasm' in rule 2",
+        );
+        assert_err(
+            r#"
+version: 1
+rules:
+  - before:
+      interface: wasi:http/handler
+    inject:
+      - name: cache
+        deps:
+          - name: audit
+            path: ./audit.wasm
+      - name: audit
+"#,
+            "'audit' names both an injection (in rule 1) and a dependency",
+        );
+    }
+
     #[test]
     fn validate_duplicate_injection_name_within_rule() {
         assert_err(
@@ -0,0 +1,196 @@
+//! Memoized parses of component bytes, shared by every pipeline stage.
+//!
+//! A splice run looks at the same components from several angles: the
+//! contract check parses each middleware's exports and imports (once
+//! per rule that injects it), every tier-1 adapter decodes its consumer
+//! split's WIT (several adapters often share a split), and WAC
+//! generation decodes each adapter again for its resource imports.
+//! [`ParseCache`] keys each parsed form by the sha256 of the bytes it
+//! came from, so each distinct component is parsed at most once per
+//! form no matter which stage — or how many — asks for it.
+//!
+//! Only successful parses are kept; a failure is recomputed (and
+//! reported) by whichever caller asks next. The cache is shared by
+//! clones of the [`crate::artifacts::Artifacts`] store that owns it and
+//! is safe to use from the concurrent adapter builds.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, bail, Result};
+use cviz::model::CompositionGraph;
+use cviz::parse::component::{parse_component, parse_component_imports};
+use sha2::{Digest, Sha256};
+use wit_parser::{Resolve, WorldId};
+
+use crate::adapter::decode_component_wit;
+
+/// `(interface_name, fingerprint)` for each instance import of a
+/// component, as returned by [`parse_component_imports`].
+pub(crate) type ComponentImports = Vec<(String, Option<String>)>;
+
+/// Content-hash-keyed memo of the parsed forms of components.
+#[derive(Default)]
+pub(crate) struct ParseCache {
+    graphs: Memo<CompositionGraph>,
+    imports: Memo<ComponentImports>,
+    wit: Memo<(Resolve, WorldId)>,
+}
+
+impl ParseCache {
+    /// The cviz composition graph of `bytes`.
+    pub(crate) fn graph(&self, bytes: &[u8]) -> Result<Arc<CompositionGraph>> {
+        self.graphs.get_or_try(bytes, parse_graph)
+    }
+
+    /// The cviz composition graph of `bytes`, taken out of the cache
+    /// so the caller can rewrite it. Reuses the memoized parse when
+    /// nothing else still holds it; the next [`ParseCache::graph`] of
+    /// the same bytes parses afresh.
+    pub(crate) fn take_graph(&self, bytes: &[u8]) -> Result<CompositionGraph> {
+        match self.graphs.take(bytes).map(Arc::try_unwrap) {
+            Some(Ok(graph)) => Ok(graph),
+            // Never parsed, or still shared: parse a private copy.
+            _ => self.graphs.count(|| parse_graph(bytes)),
+        }
+    }
+
+    /// The instance imports of the component `bytes`.
+    pub(crate) fn imports(&self, bytes: &[u8]) -> Result<Arc<ComponentImports>> {
+        self.imports.get_or_try(bytes, parse_component_imports)
+    }
+
+    /// The WIT of the component `bytes`: its decoded [`Resolve`] and
+    /// the world it targets.
+    pub(crate) fn wit(&self, bytes: &[u8]) -> Result<Arc<(Resolve, WorldId)>> {
+        self.wit.get_or_try(bytes, decode_component_wit)
+    }
+
+    /// How many composition graphs were actually parsed.
+    #[cfg(test)]
+    pub(crate) fn graph_parses(&self) -> usize {
+        self.graphs
+            .parses
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+/// Parse `bytes` into its cviz composition graph.
+fn parse_graph(bytes: &[u8]) -> Result<CompositionGraph> {
+    // cviz panics (rather than erroring) on bytes it can't decode, and
+    // the default hook prints that panic to stderr. Reject non-wasm
+    // input up front so the common mistake fails quietly; the unwind
+    // guard only catches binaries that are malformed past the header.
+    if !bytes.starts_with(WASM_MAGIC) {
+        bail!("input is not a valid wasm binary");
+    }
+    std::panic::catch_unwind(|| parse_component(bytes))
+        .map_err(|_| anyhow!("input is not a valid wasm binary"))?
+}
+
+/// The `\0asm` magic every wasm binary starts with.
+const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// One parsed form, keyed by content hash.
+struct Memo<T> {
+    entries: Mutex<HashMap<[u8; 32], Arc<T>>>,
+    /// How many times this form was actually parsed.
+    #[cfg(test)]
+    parses: std::sync::atomic::AtomicUsize,
+}
+
+impl<T> Default for Memo<T> {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::default(),
+            #[cfg(test)]
+            parses: Default::default(),
+        }
+    }
+}
+
+impl<T> Memo<T> {
+    /// The memoized `parse(bytes)`, computing it on a miss. The lock
+    /// isn't held while parsing, so concurrent misses on the same bytes
+    /// may both parse; the first result stored wins.
+    fn get_or_try(&self, bytes: &[u8], parse: impl FnOnce(&[u8]) -> Result<T>) -> Result<Arc<T>> {
+        let key: [u8; 32] = Sha256::digest(bytes).into();
+        if let Some(parsed) = self.lock().get(&key) {
+            return Ok(Arc::clone(parsed));
+        }
+        let parsed = Arc::new(self.count(|| parse(bytes))?);
+        Ok(Arc::clone(self.lock().entry(key).or_insert(parsed)))
+    }
+
+    /// Remove and return the entry for `bytes`, if there is one.
+    fn take(&self, bytes: &[u8]) -> Option<Arc<T>> {
+        let key: [u8; 32] = Sha256::digest(bytes).into();
+        self.lock().remove(&key)
+    }
+
+    /// Run `parse`, counting it in test builds.
+    fn count(&self, parse: impl FnOnce() -> Result<T>) -> Result<T> {
+        #[cfg(test)]
+        self.parses
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        parse()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<[u8; 32], Arc<T>>> {
+        // A panicking parse never holds the lock, so poisoning can't
+        // leave a half-written entry behind.
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component() -> Vec<u8> {
+        wat::parse_str(
+            r#"(component
+                (import "my:pkg/api" (instance (export "get" (func (result u32)))))
+            )"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn same_bytes_parse_once() {
+        let cache = ParseCache::default();
+        let bytes = component();
+        let first = cache.imports(&bytes).unwrap();
+        // Equal content at a different address hits the same entry.
+        let second = cache.imports(&bytes.clone()).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(first[0].0, "my:pkg/api");
+
+        let wit = cache.wit(&bytes).unwrap();
+        assert!(Arc::ptr_eq(&wit, &cache.wit(&bytes).unwrap()));
+    }
+
+    #[test]
+    fn failures_are_not_cached() {
+        let cache = ParseCache::default();
+        assert!(cache.graph(b"not wasm").is_err());
+        assert!(cache.graphs.lock().is_empty());
+        assert!(cache.graph(&component()).is_ok());
+    }
+
+    #[test]
+    fn taking_a_graph_reuses_its_parse() {
+        let cache = ParseCache::default();
+        let bytes = component();
+        drop(cache.graph(&bytes).unwrap());
+        cache.take_graph(&bytes).unwrap();
+        assert_eq!(cache.graph_parses(), 1);
+        assert!(cache.graphs.lock().is_empty());
+
+        // Once taken, the next ask parses again.
+        let shared = cache.graph(&bytes).unwrap();
+        cache.take_graph(&bytes).unwrap();
+        assert_eq!(cache.graph_parses(), 3, "a shared graph can't be taken");
+        drop(shared);
+    }
+}
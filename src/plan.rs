@@ -0,0 +1,673 @@
+//! The typed composition plan WAC generation produces.
+//!
+//! [`crate::wac::generate_wac`] decides which packages to instantiate,
+//! how each instance's imports are wired, and what the composition
+//! exports. It records those decisions as a [`CompositionPlan`] rather
+//! than as WAC text. The plan renders to the WAC source splicer has
+//! always emitted ([`CompositionPlan::to_wac`]), and it also builds a
+//! [`wac_graph::CompositionGraph`] directly
+//! ([`CompositionPlan::build_graph`]) — no WAC text in between, so no
+//! identifier quoting to get wrong, and a wiring failure names the
+//! instance and the rule that produced it.
+//!
+//! A plan is normally *implicit*: each instantiation ends in WAC's
+//! `...`, so imports the generator didn't wire become imports of the
+//! composition, merged by name. An [explicit](CompositionPlan::explicit)
+//! plan (see [`crate::SpliceRequest::strict_wiring`]) wires every
+//! import — to an instance or to a [`PlanItem::Import`] — so the
+//! composed component's dependencies are all spelled out.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use anyhow::{anyhow, bail, Context, Result};
+use semver::Version;
+use wac_graph::types::Package;
+use wac_graph::{CompositionGraph, NodeId, NodeKind, PackageId};
+
+use crate::packages::PackageSource;
+
+/// A planned composition: the instances to create and the exports to
+/// re-export, in emission order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CompositionPlan {
+    /// Package name of the composition (the WAC `package` directive).
+    pub package: String,
+    /// Imports, instantiations and exports, in order. An import or
+    /// instance is always planned before anything that wires from it.
+    pub items: Vec<PlanItem>,
+    /// Every import of every instance is listed in its
+    /// [`PlannedInstance::args`], so instances render without `...`.
+    pub explicit: bool,
+}
+
+/// One step of a [`CompositionPlan`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PlanItem {
+    /// Import an interface into the composition.
+    Import(PlannedImport),
+    /// Instantiate a package.
+    Instance(PlannedInstance),
+    /// Re-export an instance's export from the composition.
+    Export(PlannedExport),
+}
+
+/// An interface imported by the composition (a WAC
+/// `import <var>: <name>;`), typed by the interface's definition in a
+/// WIT package.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlannedImport {
+    /// Name the import is bound to in the WAC.
+    pub var: String,
+    /// Full interface name (e.g. `"wasi:http/handler@0.3.0"`) — the
+    /// import's name in the composed component.
+    pub name: String,
+    /// Key of the WIT package defining the interface (e.g.
+    /// `"wasi:http@0.3.0"`), as in [`crate::Bundle::wac_deps`].
+    pub package: String,
+}
+
+/// A package instantiation (a WAC `let <var> = new <package> { ... };`).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlannedInstance {
+    /// Name the instance is bound to — the WAC variable, and the
+    /// instance's name in the composed component.
+    pub var: String,
+    /// Fully-qualified package key (e.g. `"my:srv-a"`), as in
+    /// [`crate::Bundle::wac_deps`].
+    pub package: String,
+    /// Imports wired to an earlier instance's export, or to a
+    /// [`PlannedImport`]. Unless the plan is
+    /// [explicit](CompositionPlan::explicit), every other import
+    /// becomes an import of the composition (WAC's `...`).
+    pub args: Vec<PlannedArg>,
+    /// What in the input produced this instance.
+    pub origin: InstanceOrigin,
+    /// One-line account of where the instance came from — the rule
+    /// and injection, or the input node and its split — rendered as a
+    /// comment by [`CompositionPlan::to_annotated_wac`].
+    pub note: String,
+}
+
+/// An import of a [`PlannedInstance`] satisfied by an earlier
+/// instance's export of the same name, or by a [`PlannedImport`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlannedArg {
+    /// The interface imported — and exported by `source`.
+    pub name: String,
+    /// `var` of the instance or import providing it.
+    pub source: String,
+}
+
+/// A re-export of an instance's export (WAC `export <source>["<name>"];`,
+/// or `... as "<rename>";`).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlannedExport {
+    /// `var` of the instance whose export is re-exported.
+    pub source: String,
+    /// Export name on `source`, and on the composition unless renamed.
+    pub name: String,
+    /// Name the composition exports it under instead of `name`.
+    pub rename: Option<String>,
+}
+
+impl PlannedExport {
+    /// The export's name on the composition.
+    pub fn exported_name(&self) -> &str {
+        self.rename.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// What produced a [`PlannedInstance`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InstanceOrigin {
+    /// A component of the input composition, by its cviz node id.
+    Component { node: u32 },
+    /// Middleware injected by the rule at index `rule` of the splice
+    /// rules.
+    Middleware { rule: usize },
+    /// The tier-1 adapter generated for middleware injected by the rule
+    /// at index `rule`.
+    Adapter { rule: usize },
+    /// A component listed under `deps:` by middleware injected by the
+    /// rule at index `rule`.
+    Dependency { rule: usize },
+    /// The component the `replace` rule at index `rule` swapped in for
+    /// a provider.
+    Replacement { rule: usize },
+}
+
+impl fmt::Display for InstanceOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Rules are numbered from 1 in diagnostics, as in the generator's warnings.
+        match self {
+            InstanceOrigin::Component { node } => write!(f, "component node {node}"),
+            InstanceOrigin::Middleware { rule } => {
+                write!(f, "middleware injected by rule {}", rule + 1)
+            }
+            InstanceOrigin::Adapter { rule } => {
+                write!(f, "tier-1 adapter for rule {}", rule + 1)
+            }
+            InstanceOrigin::Dependency { rule } => {
+                write!(f, "dependency of middleware injected by rule {}", rule + 1)
+            }
+            InstanceOrigin::Replacement { rule } => {
+                write!(f, "replacement from rule {}", rule + 1)
+            }
+        }
+    }
+}
+
+impl CompositionPlan {
+    /// An empty plan for the composition package `package`.
+    pub fn new(package: impl Into<String>) -> Self {
+        Self {
+            package: package.into(),
+            items: Vec::new(),
+            explicit: false,
+        }
+    }
+
+    /// Render the plan as WAC source.
+    pub fn to_wac(&self) -> String {
+        self.render(false)
+    }
+
+    /// [`CompositionPlan::to_wac`] with comments for auditing: a header
+    /// naming the splicer version and `input` (what was spliced or
+    /// composed), and each instance's [`PlannedInstance::note`] above
+    /// its `let`. Composes exactly like the plain rendering.
+    pub fn to_annotated_wac(&self, input: &str) -> String {
+        format!(
+            "// Generated by splicer {}\n// Input: {input}\n\n{}",
+            env!("CARGO_PKG_VERSION"),
+            self.render(true)
+        )
+    }
+
+    /// Restore the invariant that every item is planned after what it
+    /// wires from, moving as few items as possible: a plan already in
+    /// order stays as it is. Needed once an instance gains args after
+    /// it was planned (a `shared` middleware's later sites).
+    pub(crate) fn order_by_wiring(&mut self) {
+        let defined: HashSet<String> = self
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                PlanItem::Import(import) => Some(import.var.clone()),
+                PlanItem::Instance(inst) => Some(inst.var.clone()),
+                PlanItem::Export(_) => None,
+            })
+            .collect();
+        let sources = |item: &PlanItem| -> Vec<String> {
+            match item {
+                PlanItem::Import(_) => vec![],
+                PlanItem::Instance(inst) => inst.args.iter().map(|a| a.source.clone()).collect(),
+                PlanItem::Export(export) => vec![export.source.clone()],
+            }
+        };
+        let mut pending = std::mem::take(&mut self.items);
+        let mut placed: HashSet<String> = HashSet::new();
+        while !pending.is_empty() {
+            // The first item whose sources are all placed; on a cycle,
+            // keep the rest as they are for `build_graph` to report.
+            let next = pending
+                .iter()
+                .position(|item| {
+                    sources(item)
+                        .iter()
+                        .all(|src| placed.contains(src) || !defined.contains(src))
+                })
+                .unwrap_or(0);
+            let item = pending.remove(next);
+            match &item {
+                PlanItem::Import(import) => placed.insert(import.var.clone()),
+                PlanItem::Instance(inst) => placed.insert(inst.var.clone()),
+                PlanItem::Export(_) => false,
+            };
+            self.items.push(item);
+        }
+    }
+
+    /// Whether the plan instantiates something as `var`.
+    pub(crate) fn has_instance(&self, var: &str) -> bool {
+        self.items
+            .iter()
+            .any(|item| matches!(item, PlanItem::Instance(inst) if inst.var == var))
+    }
+
+    /// Drop the component instances in `vars` that nothing wires from
+    /// or exports any more, then whichever of their own sources that
+    /// leaves unused in turn. Injected instances are kept.
+    pub(crate) fn prune_unused(&mut self, mut vars: Vec<String>) {
+        while let Some(var) = vars.pop() {
+            let used = self.items.iter().any(|item| match item {
+                PlanItem::Instance(inst) => inst.args.iter().any(|arg| arg.source == var),
+                PlanItem::Export(export) => export.source == var,
+                PlanItem::Import(_) => false,
+            });
+            if used {
+                continue;
+            }
+            let Some(pos) = self.items.iter().position(|item| {
+                matches!(
+                    item,
+                    PlanItem::Instance(PlannedInstance {
+                        var: v,
+                        origin: InstanceOrigin::Component { .. },
+                        ..
+                    }) if *v == var
+                )
+            }) else {
+                continue;
+            };
+            let PlanItem::Instance(removed) = self.items.remove(pos) else {
+                unreachable!("only instances are pruned");
+            };
+            vars.extend(removed.args.into_iter().map(|arg| arg.source));
+        }
+    }
+
+    fn render(&self, annotate: bool) -> String {
+        let imports: HashSet<&str> = self
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                PlanItem::Import(import) => Some(import.var.as_str()),
+                _ => None,
+            })
+            .collect();
+        let mut stmts = vec![format!("package {};", self.package)];
+        for item in &self.items {
+            let inst_wac = |inst: &PlannedInstance| inst.to_wac(self.explicit, &imports);
+            stmts.push(match item {
+                PlanItem::Import(import) => format!("import {}: {};", import.var, import.name),
+                PlanItem::Instance(inst) if annotate && !inst.note.is_empty() => {
+                    format!("// {}\n{}", inst.note, inst_wac(inst))
+                }
+                PlanItem::Instance(inst) => inst_wac(inst),
+                PlanItem::Export(export) => match &export.rename {
+                    Some(rename) => format!(
+                        "export {}[\"{}\"] as \"{rename}\";",
+                        export.source, export.name
+                    ),
+                    None => format!("export {}[\"{}\"];", export.source, export.name),
+                },
+            });
+        }
+        stmts.join("\n\n")
+    }
+
+    /// Build the composition graph the plan describes, loading each
+    /// package it instantiates from `source`. Equivalent to resolving
+    /// [`CompositionPlan::to_wac`] with `wac-parser`.
+    ///
+    /// Fails on a package `source` can't provide, and on wiring the
+    /// packages' types reject; either error names the instance and
+    /// what produced it.
+    pub fn build_graph(&self, source: &dyn PackageSource) -> Result<CompositionGraph> {
+        let mut graph = CompositionGraph::new();
+        let mut nodes: HashMap<&str, NodeId> = HashMap::new();
+        for item in &self.items {
+            match item {
+                PlanItem::Import(import) => {
+                    if nodes.contains_key(import.var.as_str()) {
+                        bail!("import `{}` is planned more than once", import.var);
+                    }
+                    let node = add_import(&mut graph, import, source)
+                        .with_context(|| format!("Failed to import `{}`", import.name))?;
+                    nodes.insert(&import.var, node);
+                }
+                PlanItem::Instance(inst) => {
+                    if nodes.contains_key(inst.var.as_str()) {
+                        bail!(
+                            "instance `{}` ({}) is planned more than once",
+                            inst.var,
+                            inst.origin
+                        );
+                    }
+                    let node =
+                        instantiate(&mut graph, inst, &nodes, source).with_context(|| {
+                            format!("Failed to instantiate `{}` ({})", inst.var, inst.origin)
+                        })?;
+                    nodes.insert(&inst.var, node);
+                }
+                PlanItem::Export(export) => {
+                    let &node = nodes.get(export.source.as_str()).ok_or_else(|| {
+                        anyhow!(
+                            "export `{}` is taken from `{}`, which isn't instantiated",
+                            export.name,
+                            export.source
+                        )
+                    })?;
+                    let alias = graph
+                        .alias_instance_export(node, &export.name)
+                        .with_context(|| format!("Failed to export `{}`", export.name))?;
+                    graph
+                        .export(alias, export.exported_name().to_string())
+                        .with_context(|| {
+                            format!("Failed to export `{}`", export.exported_name())
+                        })?;
+                }
+            }
+        }
+        Ok(graph)
+    }
+}
+
+impl PlannedInstance {
+    /// The instance's `let` statement, wiring args whose source is in
+    /// `imports` from the import itself. Middleware keeps the compact
+    /// form the generator has always emitted for it; an `explicit`
+    /// plan's instances list their args without `...`.
+    fn to_wac(&self, explicit: bool, imports: &HashSet<&str>) -> String {
+        let (var, pkg) = (&self.var, &self.package);
+        let args = self.args.iter().map(|arg| {
+            if imports.contains(arg.source.as_str()) {
+                format!("\"{}\": {},", arg.name, arg.source)
+            } else {
+                format!("\"{}\": {}[\"{}\"],", arg.name, arg.source, arg.name)
+            }
+        });
+        if explicit {
+            if self.args.is_empty() {
+                return format!("let {var} = new {pkg} {{}};");
+            }
+            let mut line = format!("let {var} = new {pkg} {{");
+            for arg in args {
+                line.push_str("\n    ");
+                line.push_str(&arg);
+            }
+            line.push_str("\n};");
+            return line;
+        }
+        match self.origin {
+            InstanceOrigin::Middleware { .. }
+            | InstanceOrigin::Dependency { .. }
+            | InstanceOrigin::Replacement { .. }
+                if self.args.is_empty() =>
+            {
+                format!("let {var} = new {pkg} {{ ... }};")
+            }
+            InstanceOrigin::Middleware { .. }
+            | InstanceOrigin::Dependency { .. }
+            | InstanceOrigin::Replacement { .. } => format!(
+                "let {var} = new {pkg} {{\n    {} ...\n}};",
+                args.collect::<Vec<_>>().join("\n    ")
+            ),
+            _ => {
+                let mut line = format!("let {var} = new {pkg} {{");
+                for arg in args {
+                    line.push_str("\n    ");
+                    line.push_str(&arg);
+                }
+                line.push_str("\n    ...\n};");
+                line
+            }
+        }
+    }
+}
+
+/// The package `key` (`ns:name`, optionally `@version`) in `graph`,
+/// loaded from `source` and registered on first use.
+fn load_package(
+    graph: &mut CompositionGraph,
+    key: &str,
+    source: &dyn PackageSource,
+) -> Result<PackageId> {
+    let (name, version) = match key.split_once('@') {
+        Some((name, version)) => (
+            name,
+            Some(
+                Version::parse(version)
+                    .with_context(|| format!("WAC package `{key}` has an invalid version"))?,
+            ),
+        ),
+        None => (key, None),
+    };
+    if let Some((id, _)) = graph.get_package_by_name(name, version.as_ref()) {
+        return Ok(id);
+    }
+    let bytes = source
+        .package(name, version.as_ref())
+        .with_context(|| format!("Failed to load WAC package `{key}`"))?
+        .ok_or_else(|| anyhow!("no package found for WAC package `{key}`"))?;
+    let package = Package::from_bytes(name, version.as_ref(), bytes, graph.types_mut())
+        .with_context(|| format!("Failed to parse WAC package `{key}`"))?;
+    Ok(graph.register_package(package)?)
+}
+
+/// Import `import`'s interface into `graph`, typed by its definition
+/// in the WIT package `import.package`.
+fn add_import(
+    graph: &mut CompositionGraph,
+    import: &PlannedImport,
+    source: &dyn PackageSource,
+) -> Result<NodeId> {
+    let package = load_package(graph, &import.package, source)?;
+    // `ns:pkg/iface@version` is defined as `iface` in its package.
+    let iface = import
+        .name
+        .split_once('/')
+        .map_or(import.name.as_str(), |(_, rest)| rest);
+    let iface = iface.split_once('@').map_or(iface, |(iface, _)| iface);
+    let kind = graph[package]
+        .definitions()
+        .get(iface)
+        .copied()
+        .ok_or_else(|| {
+            anyhow!(
+                "WIT package `{}` doesn't define interface `{iface}`",
+                import.package
+            )
+        })?;
+    let node = graph.import(import.name.clone(), kind.promote())?;
+    graph.set_node_name(node, import.var.clone());
+    Ok(node)
+}
+
+/// Instantiate `inst` in `graph`, registering its package on first use
+/// and wiring each arg from the already-planned `nodes` — an import is
+/// wired as is, an instance through an alias of its export.
+fn instantiate(
+    graph: &mut CompositionGraph,
+    inst: &PlannedInstance,
+    nodes: &HashMap<&str, NodeId>,
+    source: &dyn PackageSource,
+) -> Result<NodeId> {
+    let package = load_package(graph, &inst.package, source)?;
+    let node = graph.instantiate(package);
+    graph.set_node_name(node, inst.var.clone());
+    for arg in &inst.args {
+        let &provider = nodes.get(arg.source.as_str()).ok_or_else(|| {
+            anyhow!(
+                "`{}` is wired from `{}`, which isn't instantiated yet",
+                arg.name,
+                arg.source
+            )
+        })?;
+        let alias = match graph[provider].kind() {
+            NodeKind::Import(_) => provider,
+            _ => graph
+                .alias_instance_export(provider, &arg.name)
+                .with_context(|| format!("Failed to wire `{}` from `{}`", arg.name, arg.source))?,
+        };
+        graph
+            .set_instantiation_argument(node, &arg.name, alias)
+            .with_context(|| format!("Failed to wire `{}` from `{}`", arg.name, arg.source))?;
+    }
+    Ok(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use wac_graph::EncodeOptions;
+
+    const API: &str = "my:pkg/api";
+
+    /// A component exporting `my:pkg/api` with one `get` function.
+    fn provider() -> Vec<u8> {
+        wat::parse_str(
+            r#"(component
+                (core module $m (func (export "get") (result i32) i32.const 7))
+                (core instance $i (instantiate $m))
+                (func $get (result u32) (canon lift (core func $i "get")))
+                (instance $api (export "get" (func $get)))
+                (export "my:pkg/api" (instance $api))
+            )"#,
+        )
+        .unwrap()
+    }
+
+    /// A component importing `my:pkg/api` and re-exporting it.
+    fn consumer() -> Vec<u8> {
+        wat::parse_str(
+            r#"(component
+                (import "my:pkg/api" (instance $api (export "get" (func (result u32)))))
+                (export "my:pkg/api" (instance $api))
+            )"#,
+        )
+        .unwrap()
+    }
+
+    fn instance(var: &str, args: &[&str], origin: InstanceOrigin) -> PlanItem {
+        PlanItem::Instance(PlannedInstance {
+            var: var.into(),
+            package: format!("my:{var}"),
+            args: args
+                .iter()
+                .map(|source| PlannedArg {
+                    name: API.into(),
+                    source: (*source).into(),
+                })
+                .collect(),
+            origin,
+            note: format!("{var} for the tests"),
+        })
+    }
+
+    fn plan() -> CompositionPlan {
+        let mut plan = CompositionPlan::new("test:pkg");
+        plan.items = vec![
+            instance("srv", &[], InstanceOrigin::Component { node: 0 }),
+            instance("mw", &["srv"], InstanceOrigin::Middleware { rule: 0 }),
+            PlanItem::Export(PlannedExport {
+                source: "mw".into(),
+                name: API.into(),
+                rename: None,
+            }),
+        ];
+        plan
+    }
+
+    fn packages() -> BTreeMap<String, Vec<u8>> {
+        BTreeMap::from([
+            ("my:srv".to_string(), provider()),
+            ("my:mw".to_string(), consumer()),
+        ])
+    }
+
+    #[test]
+    fn renders_generator_wac() {
+        assert_eq!(
+            plan().to_wac(),
+            "package test:pkg;\n\n\
+             let srv = new my:srv {\n    ...\n};\n\n\
+             let mw = new my:mw {\n    \"my:pkg/api\": srv[\"my:pkg/api\"], ...\n};\n\n\
+             export mw[\"my:pkg/api\"];"
+        );
+    }
+
+    #[test]
+    fn annotations_are_comments() {
+        let wac = plan().to_annotated_wac("in.wasm");
+        assert!(wac.starts_with(&format!(
+            "// Generated by splicer {}\n// Input: in.wasm\n\npackage test:pkg;",
+            env!("CARGO_PKG_VERSION")
+        )));
+        assert!(wac.contains("// mw for the tests\nlet mw = new my:mw {"));
+        // Annotated WAC composes to the same bytes as the plain form.
+        assert_eq!(
+            crate::compose_wac_bytes(&wac, &packages()).unwrap(),
+            crate::compose_wac_bytes(&plan().to_wac(), &packages()).unwrap()
+        );
+    }
+
+    #[test]
+    fn graph_matches_parsed_wac() {
+        let direct = plan()
+            .build_graph(&packages())
+            .unwrap()
+            .encode(EncodeOptions::default())
+            .unwrap();
+        let parsed = crate::compose_wac_bytes(&plan().to_wac(), &packages()).unwrap();
+        // Same composition either way; only the producers section
+        // (which names the tool) may differ.
+        let exports = |bytes: &[u8]| {
+            crate::parse_cache::ParseCache::default()
+                .graph(bytes)
+                .unwrap()
+                .component_exports
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(exports(&direct), exports(&parsed));
+        assert_eq!(exports(&direct), vec![API.to_string()]);
+    }
+
+    #[test]
+    fn wiring_errors_name_the_rule() {
+        let mut plan = plan();
+        let PlanItem::Instance(mw) = &mut plan.items[1] else {
+            unreachable!()
+        };
+        // `srv` doesn't export this interface.
+        mw.args[0].name = "my:pkg/other".into();
+        let err = format!("{:#}", plan.build_graph(&packages()).unwrap_err());
+        assert!(
+            err.contains("`mw` (middleware injected by rule 1)"),
+            "{err}"
+        );
+        assert!(err.contains("my:pkg/other"), "{err}");
+    }
+
+    #[test]
+    fn order_by_wiring_moves_instances_after_their_sources() {
+        let mut plan = plan();
+        let in_order = plan.clone();
+        plan.order_by_wiring();
+        assert_eq!(plan, in_order);
+
+        plan.items.swap(0, 1);
+        plan.order_by_wiring();
+        assert_eq!(plan, in_order);
+    }
+
+    #[test]
+    fn duplicate_instances_are_rejected() {
+        let mut plan = plan();
+        plan.items.insert(
+            2,
+            instance("mw", &["srv"], InstanceOrigin::Middleware { rule: 1 }),
+        );
+        let err = format!("{:#}", plan.build_graph(&packages()).unwrap_err());
+        assert!(
+            err.contains("`mw` (middleware injected by rule 2) is planned more than once"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn missing_packages_are_reported() {
+        let mut packages = packages();
+        packages.remove("my:mw");
+        let err = format!("{:#}", plan().build_graph(&packages).unwrap_err());
+        assert!(
+            err.contains("no package found for WAC package `my:mw`"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! The `splicer-provenance` custom section.
+//!
+//! When asked to, splicer records what it did to a composition — its
+//! own version, a hash of the input, the rules it applied, and a
+//! digest of every middleware and adapter it injected — and embeds
+//! that record as a JSON custom section in the outer component of the
+//! composed output. [`read_provenance`] (and `splicer inspect`) decode
+//! it again, so auditors can tell what's inside a shipped artifact
+//! without the build that produced it.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use wasm_encoder::{ComponentSectionId, CustomSection, Encode};
+use wasmparser::{Parser, Payload};
+
+use crate::artifacts::{content_hash, Artifacts};
+use crate::error::{Error, Result};
+use crate::parse::config::SpliceRule;
+use crate::wac::GeneratedAdapter;
+
+/// Name of the custom section provenance is stored in.
+pub const PROVENANCE_SECTION: &str = "splicer-provenance";
+
+/// What splicer did to produce a component. Embedded by
+/// [`crate::Bundle::to_wasm`] when [`crate::Bundle::provenance`] is
+/// set; decoded by [`read_provenance`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Version of splicer that produced the component.
+    pub splicer_version: String,
+    /// Content hash (`sha256:<hex>`) of the composition that was
+    /// spliced. `None` when the composition was built from individual
+    /// components by [`crate::compose`].
+    pub input_hash: Option<String>,
+    /// The splice rules applied, as normalized from the config.
+    pub rules: Vec<SpliceRule>,
+    /// Every middleware injected, in rule order.
+    pub middleware: Vec<InjectedMiddleware>,
+    /// Every tier-1 adapter splicer generated.
+    pub adapters: Vec<AdapterProvenance>,
+}
+
+/// One middleware injection recorded in a [`Provenance`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InjectedMiddleware {
+    /// The injection's name (its WAC instance name).
+    pub name: String,
+    /// The interface it was injected on.
+    pub interface: String,
+    /// The builtin it was resolved from, if any.
+    pub builtin: Option<String>,
+    /// Content hash of the middleware's bytes. `None` for injections
+    /// that reference middleware by name only.
+    pub digest: Option<String>,
+}
+
+/// One generated tier-1 adapter recorded in a [`Provenance`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdapterProvenance {
+    /// Name of the middleware injection the adapter wraps.
+    pub middleware_name: String,
+    /// Target interface the adapter exports.
+    pub target_interface: String,
+    /// Tier-1 hook interfaces the wrapped middleware exports.
+    pub tier1_interfaces: Vec<String>,
+    /// Content hash of the adapter's bytes.
+    pub digest: String,
+}
+
+impl Provenance {
+    /// Record a splice run. `rules` are the rules as the caller gave
+    /// them; `materialized` are the same rules after builtins were
+    /// stamped with paths, whose bytes are hashed through `artifacts`.
+    pub(crate) fn record(
+        input: Option<&[u8]>,
+        rules: Vec<SpliceRule>,
+        materialized: &[SpliceRule],
+        adapters: &[GeneratedAdapter],
+        artifacts: &Artifacts,
+    ) -> anyhow::Result<Self> {
+        let mut middleware = Vec::new();
+        for rule in materialized {
+            for inj in rule.inject() {
+                let digest = match &inj.path {
+                    Some(path) => Some(content_hash(&artifacts.read(path)?)),
+                    None => None,
+                };
+                middleware.push(InjectedMiddleware {
+                    name: inj.name.clone(),
+                    interface: rule.interface().unwrap_or_default().to_string(),
+                    builtin: inj.builtin.clone(),
+                    digest,
+                });
+            }
+        }
+
+        let adapters = adapters
+            .iter()
+            .map(|adapter| {
+                Ok(AdapterProvenance {
+                    middleware_name: adapter.middleware_name.clone(),
+                    target_interface: adapter.target_interface.clone(),
+                    tier1_interfaces: adapter.tier1_interfaces.clone(),
+                    digest: content_hash(&artifacts.read(&adapter.adapter_path)?),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            splicer_version: env!("CARGO_PKG_VERSION").to_string(),
+            input_hash: input.map(content_hash),
+            rules,
+            middleware,
+            adapters,
+        })
+    }
+
+    /// Fold the record of an earlier splice into this one, so a
+    /// re-spliced composition still accounts for the middleware it
+    /// already carried. Earlier entries come first.
+    pub(crate) fn carry_over(mut self, prior: Provenance) -> Self {
+        self.rules.splice(0..0, prior.rules);
+        self.middleware.splice(0..0, prior.middleware);
+        self.adapters.splice(0..0, prior.adapters);
+        self
+    }
+
+    /// Append this record to `component` as a
+    /// [`PROVENANCE_SECTION`] custom section of the outer component.
+    pub(crate) fn embed(&self, mut component: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        let data = serde_json::to_vec(self).context("Failed to serialize provenance")?;
+        component.push(ComponentSectionId::CoreCustom.into());
+        CustomSection {
+            name: PROVENANCE_SECTION.into(),
+            data: data.into(),
+        }
+        .encode(&mut component);
+        Ok(component)
+    }
+}
+
+/// Decode the [`PROVENANCE_SECTION`] custom section from the outer
+/// component of `component`. Returns `Ok(None)` when splicer didn't
+/// record provenance. Sections inside nested components and modules
+/// are ignored; if the outer component carries several, the last one
+/// wins.
+///
+/// Fails with [`Error::GraphParse`] when `component` isn't a valid
+/// wasm binary, and [`Error::Provenance`] when the section can't be
+/// decoded.
+pub fn read_provenance(component: &[u8]) -> Result<Option<Provenance>> {
+    let mut depth = 0usize;
+    let mut found = None;
+    for payload in Parser::new(0).parse_all(component) {
+        let payload = payload
+            .context("Failed to parse wasm binary")
+            .map_err(Error::GraphParse)?;
+        match payload {
+            Payload::ModuleSection { .. } | Payload::ComponentSection { .. } => depth += 1,
+            Payload::End(_) => depth = depth.saturating_sub(1),
+            Payload::CustomSection(reader) if depth == 0 && reader.name() == PROVENANCE_SECTION => {
+                found = Some(reader.data());
+            }
+            _ => {}
+        }
+    }
+    found
+        .map(|data| {
+            serde_json::from_slice(data)
+                .context("Failed to decode provenance JSON")
+                .map_err(Error::Provenance)
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Provenance {
+        Provenance {
+            splicer_version: "0.0.0".into(),
+            input_hash: Some(content_hash(b"input")),
+            rules: Vec::new(),
+            middleware: vec![InjectedMiddleware {
+                name: "mw".into(),
+                interface: "my:pkg/api".into(),
+                builtin: None,
+                digest: Some(content_hash(b"mw")),
+            }],
+            adapters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn embed_roundtrips() {
+        let component = wat::parse_str("(component)").unwrap();
+        let embedded = sample().embed(component).unwrap();
+        wasmparser::Validator::new_with_features(wasmparser::WasmFeatures::all())
+            .validate_all(&embedded)
+            .expect("embedded component should validate");
+        assert_eq!(read_provenance(&embedded).unwrap(), Some(sample()));
+    }
+
+    /// A provenance section inside a nested component belongs to that
+    /// component, not the outer one.
+    #[test]
+    fn nested_sections_are_ignored() {
+        let inner = sample()
+            .embed(wat::parse_str("(component)").unwrap())
+            .unwrap();
+        let mut outer = wat::parse_str("(component)").unwrap();
+        outer.push(ComponentSectionId::Component.into());
+        (inner.len() as u32).encode(&mut outer);
+        outer.extend(&inner);
+        assert_eq!(read_provenance(&outer).unwrap(), None);
+    }
+}
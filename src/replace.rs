@@ -0,0 +1,192 @@
+//! The `replace` splice strategy: swap the provider of an interface for
+//! a different component exporting it.
+//!
+//! Unlike middleware, a replacement doesn't sit on an edge — it takes
+//! the provider's place on every one of them. So rather than going
+//! through the per-chain injection plan, [`apply_replacements`] works
+//! on the finished [`CompositionPlan`]: each consumer's import of the
+//! interface from the provider, and the composition's export of it, is
+//! rewired to the replacement. A provider nothing uses any more is
+//! dropped, along with any instance only it depended on.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use cviz::model::{CompositionGraph, ExportInfo};
+
+use crate::artifacts::Artifacts;
+use crate::contract::{validate_contract_in, ContractResult};
+use crate::parse::config::SpliceRule;
+use crate::plan::{CompositionPlan, InstanceOrigin, PlanItem, PlannedInstance};
+use crate::wac::{INST_PREFIX, PATH_PLACEHOLDER};
+
+/// Apply every `replace` rule in `rules` to `plan`, whose component
+/// instances are the nodes of `composition` bound as in
+/// `instance_vars`. Replacements are type-checked against the
+/// provider's side of the interface like middleware is; the results
+/// are returned. Their packages are added to `used_middlewares`, and
+/// nodes whose instance was dropped are removed from `used_comp_nodes`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn apply_replacements(
+    plan: &mut CompositionPlan,
+    rules: &[SpliceRule],
+    composition: &CompositionGraph,
+    instance_vars: &HashMap<u32, String>,
+    used_comp_nodes: &mut HashMap<u32, String>,
+    used_middlewares: &mut Vec<(String, String)>,
+    checked_middlewares: &mut HashMap<String, BTreeMap<String, ExportInfo>>,
+    artifacts: &Artifacts,
+) -> Result<Vec<ContractResult>> {
+    let mut diagnostics = Vec::new();
+    for (rule_idx, rule) in rules.iter().enumerate() {
+        let SpliceRule::Replace {
+            interface,
+            provider_name,
+            provider_instance,
+            inject,
+        } = rule
+        else {
+            continue;
+        };
+        let Some(replacement) = inject.first() else {
+            continue;
+        };
+
+        let providers = providers_of(composition, interface);
+        let matched: BTreeMap<u32, &Option<String>> = providers
+            .iter()
+            .filter(|(id, _)| {
+                provider_name
+                    .as_ref()
+                    .is_none_or(|name| composition.nodes[id].display_label() == name)
+                    && provider_instance.is_none_or(|instance| instance == **id)
+            })
+            .map(|(id, fingerprint)| (*id, fingerprint))
+            .collect();
+        if matched.is_empty() {
+            let names: BTreeSet<&str> = providers
+                .keys()
+                .map(|id| composition.nodes[id].display_label())
+                .collect();
+            eprintln!(
+                "{}: rule {} — no provider of '{}' matched; nothing was replaced.\n\
+                 \t  Providers of that interface: [{}]",
+                "WARN".yellow().bold(),
+                rule_idx + 1,
+                interface,
+                names.into_iter().collect::<Vec<_>>().join(", ")
+            );
+            continue;
+        }
+
+        let var = &replacement.name;
+        if plan.has_instance(var) {
+            bail!(
+                "rule {}: replacement `{var}` has the same name as another instance in the \
+                 composition",
+                rule_idx + 1
+            );
+        }
+        let mut replaced: BTreeSet<&str> = BTreeSet::new();
+        let mut checked: BTreeSet<&Option<String>> = BTreeSet::new();
+        for (id, fingerprint) in matched {
+            if checked.insert(fingerprint) {
+                diagnostics.extend(validate_contract_in(
+                    inject,
+                    interface,
+                    fingerprint,
+                    checked_middlewares,
+                    artifacts,
+                ));
+            }
+            if let Some(provider) = instance_vars.get(&id) {
+                replaced.insert(provider);
+            }
+        }
+        // Middleware that doesn't export the interface is only a
+        // warning (it may be tier-1); a replacement has to.
+        if checked_middlewares
+            .get(var)
+            .is_some_and(|exports| replacement.path.is_some() && !exports.contains_key(interface))
+        {
+            bail!(
+                "rule {}: replacement `{var}` doesn't export `{interface}`",
+                rule_idx + 1
+            );
+        }
+
+        let path = replacement
+            .path
+            .clone()
+            .unwrap_or(PATH_PLACEHOLDER.to_string());
+        plan.items.push(PlanItem::Instance(PlannedInstance {
+            var: var.clone(),
+            package: format!("{INST_PREFIX}:{var}"),
+            args: vec![],
+            origin: InstanceOrigin::Replacement { rule: rule_idx },
+            note: format!(
+                "rule {}: replace {} on `{interface}` with `{var}`, from {path}",
+                rule_idx + 1,
+                replaced
+                    .iter()
+                    .map(|provider| format!("`{provider}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }));
+        used_middlewares.push((var.clone(), path));
+
+        for item in plan.items.iter_mut() {
+            match item {
+                PlanItem::Instance(inst) => {
+                    for arg in inst.args.iter_mut() {
+                        if arg.name == *interface && replaced.contains(arg.source.as_str()) {
+                            arg.source = var.clone();
+                        }
+                    }
+                }
+                PlanItem::Export(export) => {
+                    if export.name == *interface && replaced.contains(export.source.as_str()) {
+                        export.source = var.clone();
+                    }
+                }
+                PlanItem::Import(_) => {}
+            }
+        }
+        let replaced: Vec<String> = replaced.into_iter().map(str::to_string).collect();
+        plan.prune_unused(replaced);
+    }
+
+    used_comp_nodes.retain(|node, _| {
+        instance_vars
+            .get(node)
+            .is_some_and(|var| plan.has_instance(var))
+    });
+    Ok(diagnostics)
+}
+
+/// The nodes of `composition` providing `interface` — to another node
+/// or as the composition's export — each with the interface's type
+/// fingerprint on that edge.
+fn providers_of(composition: &CompositionGraph, interface: &str) -> BTreeMap<u32, Option<String>> {
+    let mut providers = BTreeMap::new();
+    for node in composition.nodes.values() {
+        for conn in &node.imports {
+            if conn.is_host_import || conn.interface_name != interface {
+                continue;
+            }
+            if let Some(source) = conn.source_instance {
+                providers
+                    .entry(source)
+                    .or_insert_with(|| conn.fingerprint.clone());
+            }
+        }
+    }
+    if let Some(export) = composition.component_exports.get(interface) {
+        providers
+            .entry(export.source_instance)
+            .or_insert_with(|| export.fingerprint.clone());
+    }
+    providers
+}
@@ -1,9 +1,14 @@
+use crate::artifacts::Artifacts;
 use anyhow::Context;
-use std::collections::HashMap;
+use cviz::model::CompositionGraph;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
+use wasmparser::{ComponentExternalKind, ComponentInstance};
 use wirm::ir::component::visitor::{walk_structural, ComponentVisitor, VisitCtx};
-use wirm::{Component, Module};
+use wirm::Component;
 
 /// Default directory where split sub-components are written.
 pub const PATH_TO_SPLITS: &str = "./splits";
@@ -12,7 +17,8 @@ pub const PATH_TO_SPLITS: &str = "./splits";
 /// one `.wasm` file per nested component into the splits directory.
 /// Returns `(splits_dir, shim_map)` where `shim_map` records which
 /// splits are shim components that should be replaced by their outer
-/// component.
+/// component. Why each split was classified as it was is written to
+/// [`SPLIT_MANIFEST`] in the splits directory.
 pub fn split_out_composition(
     wasm_path: &PathBuf,
     splits_path: &Option<String>,
@@ -26,75 +32,420 @@ pub fn split_out_composition(
         .with_context(|| format!("Failed to create splits directory: {output}"))?;
     let buff = fs::read(wasm_path)
         .with_context(|| format!("Failed to read composition wasm: {}", wasm_path.display()))?;
-    let component = Component::parse(&buff, false, false).with_context(|| {
-        format!(
-            "Failed to parse composition wasm as a component: {}",
-            wasm_path.display()
-        )
-    })?;
 
-    let mut visitor = EmitVisitor::new(&output);
+    let nesting = split_into(&buff, &output, &mut Artifacts::on_disk())
+        .with_context(|| format!("Failed to split composition wasm: {}", wasm_path.display()))?;
+    Ok((output, nesting.shims))
+}
+
+/// How the components of a split composition nest, by split number.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Nesting {
+    /// Shim split -> the split of its outer component, which is
+    /// instantiated in its place (see [`split_out_composition`]).
+    pub shims: HashMap<usize, usize>,
+    /// Split -> the split of the component defining it. The root
+    /// (split 0) has no parent.
+    pub parents: HashMap<usize, usize>,
+    /// Splits that are compositions of their own: no core module, but
+    /// components nested inside them. Unlike shims, these are
+    /// instantiated as they are (see [`crate::nested`]).
+    pub compositions: BTreeSet<usize>,
+    /// Every split, in index order, with what it was taken for and
+    /// why. Written out as the [`SPLIT_MANIFEST`].
+    pub splits: Vec<SplitRecord>,
+}
+
+impl Nesting {
+    /// Whether `split` is defined inside one of the nested
+    /// [`Nesting::compositions`], at any depth.
+    pub fn inside_composition(&self, mut split: usize) -> bool {
+        while let Some(&parent) = self.parents.get(&split) {
+            if self.compositions.contains(&parent) {
+                return true;
+            }
+            split = parent;
+        }
+        false
+    }
+}
+
+/// Name of the manifest [`split_into`] writes next to the splits.
+pub const SPLIT_MANIFEST: &str = "splits.json";
+
+/// Contents of the [`SPLIT_MANIFEST`].
+#[derive(Serialize, Deserialize)]
+struct SplitManifest {
+    splits: Vec<SplitRecord>,
+}
+
+/// One split, as recorded in the [`SPLIT_MANIFEST`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct SplitRecord {
+    pub index: usize,
+    /// The split's file name, without `.wasm`: the label of the first
+    /// instance created from it, or `split<index>` if none is.
+    pub name: String,
+    /// The split of the component defining this one; `None` for the
+    /// root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<usize>,
+    #[serde(flatten)]
+    pub kind: SplitKind,
+    /// What in the component led to [`SplitRecord::kind`].
+    pub reason: String,
+    /// The names the component imports.
+    pub imports: Vec<String>,
+    /// The names the component exports.
+    pub exports: Vec<String>,
+    /// Hex sha256 of the split's bytes.
+    pub sha256: String,
+    /// An earlier split with the same bytes, whose file this one
+    /// shares instead of being written itself. The two are still
+    /// separate components, instantiated separately.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_as: Option<usize>,
+}
+
+/// What a split is taken for when the composition is regenerated.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum SplitKind {
+    /// The composition that was split.
+    Root,
+    /// A component instantiated from its own split.
+    Component,
+    /// A wrapper that only passes on what it imports. It can't be
+    /// instantiated on its own, so `outer`, which instantiates it, is
+    /// used in its place.
+    Shim { outer: usize },
+    /// A composition nested in its parent (see [`Nesting::compositions`]).
+    Composition,
+}
+
+/// Where each split of a splits directory was written, as read from
+/// its [`SPLIT_MANIFEST`]: splits are named after their instances, and
+/// byte-identical splits share one file.
+#[derive(Clone, Debug, Default)]
+pub struct SplitPaths {
+    dir: String,
+    /// Split index -> name, without `.wasm`, of the file holding it.
+    names: HashMap<usize, String>,
+    /// See [`SplitRecord::same_as`].
+    same_as: HashMap<usize, usize>,
+}
+
+impl SplitPaths {
+    /// The split paths of the splits directory `dir` on disk, e.g. as
+    /// written by [`split_out_composition`]. A directory without a
+    /// [`SPLIT_MANIFEST`] is taken to hold its splits at
+    /// `split<index>.wasm`.
+    pub fn read(dir: &str) -> anyhow::Result<Self> {
+        Self::load(dir, &Artifacts::on_disk())
+    }
+
+    /// [`SplitPaths::read`], through `artifacts`.
+    pub(crate) fn load(dir: &str, artifacts: &Artifacts) -> anyhow::Result<Self> {
+        let path = format!("{dir}/{SPLIT_MANIFEST}");
+        let Ok(json) = artifacts.read(&path) else {
+            return Ok(Self::new(dir, &[]));
+        };
+        let manifest: SplitManifest =
+            serde_json::from_slice(&json).with_context(|| format!("Failed to parse '{path}'"))?;
+        Ok(Self::new(dir, &manifest.splits))
+    }
+
+    /// The split paths of the splits directory `dir` holding the splits
+    /// `records` lists. A split that is the [`SplitRecord::same_as`]
+    /// another is found in that one's file.
+    pub(crate) fn new(dir: &str, records: &[SplitRecord]) -> Self {
+        let own: HashMap<usize, &str> = records
+            .iter()
+            .map(|record| (record.index, record.name.as_str()))
+            .collect();
+        Self {
+            dir: dir.to_string(),
+            names: records
+                .iter()
+                .map(|record| {
+                    let stored = record.same_as.unwrap_or(record.index);
+                    (record.index, own.get(&stored).unwrap_or(&"").to_string())
+                })
+                .collect(),
+            same_as: records
+                .iter()
+                .filter_map(|record| Some((record.index, record.same_as?)))
+                .collect(),
+        }
+    }
+
+    /// The splits directory.
+    pub fn dir(&self) -> &str {
+        &self.dir
+    }
+
+    /// The earlier split whose file split `index` shares, if any.
+    pub fn same_as(&self, index: usize) -> Option<usize> {
+        self.same_as.get(&index).copied()
+    }
+
+    /// The path of the file holding split `index`.
+    pub fn path(&self, index: usize) -> String {
+        match self.names.get(&index) {
+            Some(name) => format!("{}/{name}.wasm", self.dir),
+            None => indexed_split_path(&self.dir, index),
+        }
+    }
+}
+
+/// Split the composed component `bytes` into its sub-components,
+/// writing each one through `artifacts` into `splits_path`, named
+/// after the instance created from it, along with the
+/// [`SPLIT_MANIFEST`] recording where each went. Returns how they
+/// nest.
+pub(crate) fn split_into(
+    bytes: &[u8],
+    splits_path: &str,
+    artifacts: &mut Artifacts,
+) -> anyhow::Result<Nesting> {
+    let component = Component::parse(bytes, false, false)
+        .context("Failed to parse composition wasm as a component")?;
+
+    // The visitor only encodes; the splits are written in one batch
+    // afterwards so the writes can run concurrently.
+    let mut visitor = EmitVisitor::default();
     walk_structural(&component, &mut visitor);
 
-    if let Some(e) = &visitor.err {
-        return Err(anyhow::anyhow!("{}", e));
+    if let Some(e) = visitor.err.take() {
+        return Err(e);
+    }
+    let mut nesting = visitor.nesting;
+    nesting.splits.sort_by_key(|record| record.index);
+    // Naming is cosmetic; a composition cviz can't read still splits.
+    let graph = artifacts.parsed().graph(bytes).ok();
+    let names = split_names(split_labels(graph.as_deref()), nesting.splits.len());
+    for (record, name) in nesting.splits.iter_mut().zip(names) {
+        record.name = name;
+        record.sha256 = visitor.hashes[record.index].clone();
+        record.same_as = visitor.same_as.get(&record.index).copied();
+    }
+
+    for record in &nesting.splits {
+        if let Some(stored) = record.same_as {
+            tracing::debug!(
+                "split{} has the same bytes as split{stored}; it's stored once, as {}.wasm",
+                record.index,
+                nesting.splits[stored].name
+            );
+        }
+    }
+
+    let mut files: Vec<(String, Vec<u8>)> = visitor
+        .splits
+        .into_iter()
+        .map(|(index, bytes)| {
+            (
+                format!("{splits_path}/{}.wasm", nesting.splits[index].name),
+                bytes,
+            )
+        })
+        .collect();
+    let manifest = serde_json::to_vec_pretty(&SplitManifest {
+        splits: nesting.splits.clone(),
+    })
+    .context("Failed to serialize the split manifest")?;
+    files.push((format!("{splits_path}/{SPLIT_MANIFEST}"), manifest));
+    artifacts.write_all(files)?;
+
+    Ok(nesting)
+}
+
+/// Replace split `index` of the splits directory `dir` with `bytes`,
+/// through `artifacts`, and record it in the directory's
+/// [`SPLIT_MANIFEST`]. A split sharing its file with others gets one of
+/// its own; the others keep the bytes they had.
+pub(crate) fn rewrite_split(
+    dir: &str,
+    index: usize,
+    bytes: Vec<u8>,
+    artifacts: &mut Artifacts,
+) -> anyhow::Result<()> {
+    let manifest_path = format!("{dir}/{SPLIT_MANIFEST}");
+    let mut manifest: SplitManifest = serde_json::from_slice(&artifacts.read(&manifest_path)?)
+        .with_context(|| format!("Failed to parse '{manifest_path}'"))?;
+    let splits = &mut manifest.splits;
+    if splits.get(index).is_none_or(|record| record.index != index) {
+        anyhow::bail!("'{manifest_path}' has no split{index}");
     }
 
-    Ok((output, visitor.shim_comps))
+    let stored = splits[index].same_as.unwrap_or(index);
+    let sharing: Vec<usize> = splits
+        .iter()
+        .filter(|record| record.index != index && record.same_as.unwrap_or(record.index) == stored)
+        .map(|record| record.index)
+        .collect();
+    if let Some(&heir) = sharing.first().filter(|_| stored == index) {
+        // The file was this split's; hand the old bytes to the first
+        // split still sharing them.
+        let old = artifacts
+            .read(&SplitPaths::new(dir, splits).path(index))?
+            .into_owned();
+        for &other in &sharing {
+            splits[other].same_as = (other != heir).then_some(heir);
+        }
+        artifacts.write(&format!("{dir}/{}.wasm", splits[heir].name), old)?;
+    }
+
+    let record = &mut splits[index];
+    record.same_as = None;
+    record.sha256 = format!("{:x}", Sha256::digest(&bytes));
+    artifacts.write(&format!("{dir}/{}.wasm", record.name), bytes)?;
+
+    let json =
+        serde_json::to_vec_pretty(&manifest).context("Failed to serialize the split manifest")?;
+    artifacts.write(&manifest_path, json)
+}
+
+/// The label of the first instance (by node id) of the composition
+/// `graph` was parsed from created from each split, by split index.
+fn split_labels(graph: Option<&CompositionGraph>) -> BTreeMap<usize, String> {
+    let mut labels: BTreeMap<usize, String> = BTreeMap::new();
+    if let Some(graph) = graph {
+        let mut nodes: Vec<_> = graph.nodes.iter().collect();
+        nodes.sort_by_key(|(id, _)| **id);
+        for (_, node) in nodes {
+            let label = sanitize_name(node.display_label());
+            if !label.is_empty() {
+                labels
+                    .entry(node.component_num as usize + 1)
+                    .or_insert(label);
+            }
+        }
+    }
+    labels
+}
+
+/// The file name, without `.wasm`, of each of `count` splits, given
+/// their [`split_labels`]. A split is named after its label; a label
+/// another split already took gets `-<index>` appended, and then
+/// `-2`, `-3`, ... until the name is free. Splits without a label —
+/// the root, shims' outer components — keep [`indexed_split_path`]'s
+/// `split<index>`.
+fn split_names(labels: BTreeMap<usize, String>, count: usize) -> Vec<String> {
+    let mut names: Vec<String> = (0..count).map(|index| format!("split{index}")).collect();
+    let mut taken: HashSet<String> = (0..count)
+        .filter(|index| !labels.contains_key(index))
+        .map(|index| names[index].clone())
+        .collect();
+    for (index, label) in labels {
+        if index >= count {
+            continue;
+        }
+        let mut name = label.clone();
+        if taken.contains(&name) {
+            name = format!("{label}-{index}");
+        }
+        let mut n = 2;
+        while taken.contains(&name) {
+            name = format!("{label}-{index}-{n}");
+            n += 1;
+        }
+        taken.insert(name.clone());
+        names[index] = name;
+    }
+    names
+}
+
+/// `label` with anything that doesn't belong in a file name replaced.
+fn sanitize_name(label: &str) -> String {
+    label
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
 }
 
+#[derive(Default)]
 struct EmitVisitor {
-    output_path: String,
+    /// `(index, bytes)` of every split encoded so far, but for those
+    /// with the same bytes as an earlier one.
+    splits: Vec<(usize, Vec<u8>)>,
+    /// Hex sha256 of every split, by index.
+    hashes: Vec<String>,
+    /// Split -> the earlier split with the same bytes.
+    same_as: HashMap<usize, usize>,
     curr_comp_num: usize,
     comp_num_stack: Vec<usize>,
+    nesting: Nesting,
 
-    // Used to find shims
-    has_core_module: Vec<bool>,
-    shim_comps: HashMap<usize, usize>, // shim_comp_num -> outer_comp_num
-
-    err: Option<wirm::error::Error>,
+    err: Option<anyhow::Error>,
 }
 impl EmitVisitor {
-    fn new(output_path: &str) -> Self {
-        Self {
-            output_path: output_path.to_string(),
-            curr_comp_num: 0,
-            comp_num_stack: vec![],
-            has_core_module: vec![],
-            shim_comps: HashMap::new(),
-            err: None,
-        }
-    }
     fn handle_enter_component(&mut self, comp: &Component) {
         // we reserve 0 for the outermost component!
         // (if it's the outermost, the id is None)
+        if let Some(&outer_comp_num) = self.comp_num_stack.last() {
+            self.nesting
+                .parents
+                .insert(self.curr_comp_num, outer_comp_num);
+        }
         self.comp_num_stack.push(self.curr_comp_num);
 
-        if let Err(e) = comp.emit_wasm(&gen_split_path(&self.output_path, self.curr_comp_num)) {
-            self.err = Some(e);
+        if let Err(e) = self.emit_split(comp) {
+            self.err.get_or_insert(e);
         }
         self.curr_comp_num += 1;
-        self.has_core_module.push(false);
-    }
-    fn handle_exit_component(&mut self, _: &Component) {
-        self.apply_shim_identification_heuristic();
-    }
-
-    fn apply_shim_identification_heuristic(&mut self) {
-        let has_core_module = self.has_core_module.pop().unwrap();
-        if let Some(my_comp_num) = self.comp_num_stack.pop() {
-            if !has_core_module {
-                // protect against doing this for the outermost component
-                if let Some(outer_comp_num) = self.comp_num_stack.last() {
-                    // I'm making an assumption here, if the component I'm exiting does
-                    // not have an inner core module, it's likely a shim!
-                    // The outer component likely contains this inner shim with some necessary non-wac
-                    // stitching around it! So, I would need to make sure I instantiate the OUTER component
-                    // rather than the inner shim.
-                    self.shim_comps.insert(my_comp_num, *outer_comp_num);
-                }
+    }
+    fn emit_split(&mut self, comp: &Component) -> anyhow::Result<()> {
+        let index = self.curr_comp_num;
+        let bytes = comp
+            .encode()
+            .map_err(|e| anyhow::anyhow!("{e}"))
+            .with_context(|| format!("Failed to encode split{index}"))?;
+        let hash = format!("{:x}", Sha256::digest(&bytes));
+        match self.hashes.iter().position(|other| *other == hash) {
+            Some(stored) => {
+                self.same_as.insert(index, stored);
+            }
+            None => self.splits.push((index, bytes)),
+        }
+        self.hashes.push(hash);
+        Ok(())
+    }
+    fn handle_exit_component(&mut self, comp: &Component) {
+        let index = self
+            .comp_num_stack
+            .pop()
+            .expect("exiting a component that was entered");
+        let parent = self.comp_num_stack.last().copied();
+        let (kind, reason) = classify(comp, parent);
+        match kind {
+            SplitKind::Shim { outer } => {
+                self.nesting.shims.insert(index, outer);
             }
+            SplitKind::Composition => {
+                self.nesting.compositions.insert(index);
+            }
+            SplitKind::Root | SplitKind::Component => {}
         }
+        // The name, hash and dedup are filled in once every split is
+        // encoded.
+        self.nesting.splits.push(SplitRecord {
+            index,
+            name: String::new(),
+            parent,
+            kind,
+            reason,
+            imports: comp.imports.iter().map(|i| i.name.0.to_string()).collect(),
+            exports: comp.exports.iter().map(|e| e.name.0.to_string()).collect(),
+            sha256: String::new(),
+            same_as: None,
+        });
     }
 }
 impl ComponentVisitor<'_> for EmitVisitor {
@@ -110,12 +461,325 @@ impl ComponentVisitor<'_> for EmitVisitor {
     fn exit_component(&mut self, _cx: &VisitCtx, _id: u32, component: &Component) {
         self.handle_exit_component(component);
     }
-    fn visit_module(&mut self, _cx: &VisitCtx<'_>, _id: u32, _module: &Module<'_>) {
-        *self.has_core_module.last_mut().unwrap() = true;
+}
+
+/// Decide what the component `comp`, defined inside the split `parent`
+/// (`None` for the root), is taken for, from what its body does.
+///
+/// A component whose body only imports, defines types, aliases the
+/// exports of its imports and bundles those into instances has no
+/// behaviour of its own — it re-exports what it imports, or only
+/// types. That is the wrapper `wit-component` and `wac` emit around a
+/// component's exports, and it can only be instantiated where its
+/// parent wires it up: a shim. Anything that defines or instantiates
+/// code, lifts or lowers functions, or has a start function is a
+/// component in its own right, even without a core module of its own.
+fn classify(comp: &Component, parent: Option<usize>) -> (SplitKind, String) {
+    let Some(outer) = parent else {
+        return (
+            SplitKind::Root,
+            "the composition that was split".to_string(),
+        );
+    };
+    if !comp.modules.is_empty() {
+        return (
+            SplitKind::Component,
+            format!("defines {} core module(s)", comp.modules.len()),
+        );
+    }
+    if !comp.components.is_empty() {
+        return (
+            SplitKind::Composition,
+            format!(
+                "defines no core module, but instantiates {} nested component(s)",
+                comp.components.len()
+            ),
+        );
     }
+
+    let mut behaviour = Vec::new();
+    if !comp.instances.is_empty() {
+        behaviour.push("creates core instances");
+    }
+    if !comp.canons.items.is_empty() {
+        behaviour.push("lifts or lowers functions");
+    }
+    if comp
+        .component_instance
+        .iter()
+        .any(|inst| matches!(inst, ComponentInstance::Instantiate { .. }))
+    {
+        behaviour.push("instantiates imported components");
+    }
+    if !comp.start_section.is_empty() {
+        behaviour.push("has a start function");
+    }
+    if !behaviour.is_empty() {
+        return (
+            SplitKind::Component,
+            format!("defines no core module, but {}", behaviour.join(" and ")),
+        );
+    }
+
+    let reason = if comp
+        .exports
+        .iter()
+        .all(|export| export.kind == ComponentExternalKind::Type)
+    {
+        "only defines types"
+    } else {
+        "only re-exports what it imports"
+    };
+    (SplitKind::Shim { outer }, reason.to_string())
 }
 
-/// Build the filesystem path for a split sub-component by index.
-pub fn gen_split_path(splits_path: &str, comp_id: usize) -> String {
+/// Build the filesystem path for a split sub-component by index: where
+/// a split no instance is created from is written, and where every
+/// split is looked for in a splits directory without a
+/// [`SPLIT_MANIFEST`]. See [`SplitRecord::name`].
+pub(crate) fn indexed_split_path(splits_path: &str, comp_id: usize) -> String {
     format!("{splits_path}/split{comp_id}.wasm")
 }
+
+/// The path of split `comp_id` in the splits directory `splits_path`
+/// on disk. Splits are named after their instances now, so this reads
+/// the directory's [`SPLIT_MANIFEST`] on every call; read it once
+/// with [`SplitPaths::read`] instead.
+#[deprecated(note = "use SplitPaths")]
+pub fn gen_split_path(splits_path: &str, comp_id: usize) -> String {
+    match SplitPaths::read(splits_path) {
+        Ok(paths) => paths.path(comp_id),
+        Err(_) => indexed_split_path(splits_path, comp_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifacts::IN_MEMORY_ROOT;
+
+    fn split_kinds(wat: &str) -> Vec<(SplitKind, String)> {
+        let bytes = wat::parse_str(wat).unwrap();
+        let mut artifacts = Artifacts::in_memory();
+        let nesting = split_into(&bytes, IN_MEMORY_ROOT, &mut artifacts).unwrap();
+
+        let manifest = artifacts
+            .read(&format!("{IN_MEMORY_ROOT}/{SPLIT_MANIFEST}"))
+            .unwrap();
+        let manifest: serde_json::Value = serde_json::from_slice(&manifest).unwrap();
+        assert_eq!(
+            manifest["splits"].as_array().unwrap().len(),
+            nesting.splits.len()
+        );
+
+        nesting
+            .splits
+            .into_iter()
+            .map(|record| (record.kind, record.reason))
+            .collect()
+    }
+
+    /// Wrappers that only pass their imports on are shims; a component
+    /// with code, if not a module of its own, isn't.
+    #[test]
+    fn shims_are_told_apart_by_what_they_do() {
+        let kinds = split_kinds(
+            r#"(component
+                (component $reexport
+                    (import "a" (instance $a (export "get" (func (result u32)))))
+                    (export "b" (instance $a))
+                )
+                (component $types
+                    (type $t u32)
+                    (export "t" (type $t))
+                )
+                (component $imported-code
+                    (import "m" (core module $m (export "get" (func (result i32)))))
+                    (core instance $i (instantiate $m))
+                    (alias core export $i "get" (core func $get))
+                    (func $f (result u32) (canon lift (core func $get)))
+                    (export "get" (func $f))
+                )
+                (core module $m (func (export "get") (result i32) i32.const 1))
+                (import "a" (instance $a (export "get" (func (result u32)))))
+                (instance (instantiate $reexport (with "a" (instance $a))))
+                (instance (instantiate $types))
+                (instance (instantiate $imported-code (with "m" (core module $m))))
+            )"#,
+        );
+
+        assert_eq!(kinds[0].0, SplitKind::Root);
+        assert_eq!(
+            kinds[1],
+            (
+                SplitKind::Shim { outer: 0 },
+                "only re-exports what it imports".into()
+            )
+        );
+        assert_eq!(
+            kinds[2],
+            (SplitKind::Shim { outer: 0 }, "only defines types".into())
+        );
+        assert_eq!(kinds[3].0, SplitKind::Component);
+        assert!(
+            kinds[3].1.contains("creates core instances"),
+            "{}",
+            kinds[3].1
+        );
+    }
+
+    /// Splits are written under their instance's label, suffixed when
+    /// the name is taken, and the manifest says where each went.
+    #[test]
+    fn splits_are_named_after_their_instances() {
+        let bytes = wat::parse_str(
+            r#"(component
+                (component $a
+                    (core module $m)
+                    (export "a" (core module $m))
+                )
+                (component $b
+                    (core module $m)
+                    (export "b" (core module $m))
+                )
+                (instance $srv-b (instantiate $a))
+                (instance $split0 (instantiate $b))
+            )"#,
+        )
+        .unwrap();
+        let mut artifacts = Artifacts::in_memory();
+        let nesting = split_into(&bytes, IN_MEMORY_ROOT, &mut artifacts).unwrap();
+
+        let names: Vec<&str> = nesting.splits.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["split0", "srv-b", "split0-2"]);
+        assert_eq!(nesting.splits[1].parent, Some(0));
+        assert_eq!(nesting.splits[1].exports, ["a"]);
+
+        let paths = SplitPaths::load(IN_MEMORY_ROOT, &artifacts).unwrap();
+        for record in &nesting.splits {
+            let split = artifacts.read(&paths.path(record.index)).unwrap();
+            assert_eq!(record.sha256, format!("{:x}", Sha256::digest(&split)));
+        }
+    }
+
+    /// The splits `split_out_composition` writes are found on disk
+    /// through their manifest, not at `split<index>.wasm`.
+    #[test]
+    fn split_paths_read_the_manifest_on_disk() {
+        let tmp = tempfile::tempdir().unwrap();
+        let wasm = tmp.path().join("composed.wasm");
+        fs::write(
+            &wasm,
+            wat::parse_str(
+                r#"(component
+                    (component $a
+                        (core module $m)
+                        (export "a" (core module $m))
+                    )
+                    (instance $srv (instantiate $a))
+                )"#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let dir = tmp.path().join("splits").to_str().unwrap().to_string();
+        split_out_composition(&wasm, &Some(dir.clone())).unwrap();
+
+        let paths = SplitPaths::read(&dir).unwrap();
+        assert_eq!(paths.path(1), format!("{dir}/srv.wasm"));
+        assert!(std::path::Path::new(&paths.path(1)).exists());
+        assert!(!std::path::Path::new(&indexed_split_path(&dir, 1)).exists());
+        #[allow(deprecated)]
+        let legacy = gen_split_path(&dir, 1);
+        assert_eq!(legacy, paths.path(1));
+    }
+
+    /// A suffixed name another split already has as its label is
+    /// suffixed again, so no two splits share a file.
+    #[test]
+    fn collision_suffixes_skip_taken_names() {
+        let labels = BTreeMap::from([
+            (1, "db".to_string()),
+            (2, "db-3".to_string()),
+            (3, "db".to_string()),
+        ]);
+        assert_eq!(split_names(labels, 4), ["split0", "db", "db-3", "db-3-2"]);
+    }
+
+    /// Two copies of one component, each exporting its own interface.
+    /// Left unnamed, so the copies' bytes match.
+    const WAT_REPLICAS: &str = r#"(component
+        (component
+            (core module $m (func (export "run") (result i32) i32.const 42))
+            (core instance $mi (instantiate $m))
+            (func $f (result u32) (canon lift (core func $mi "run")))
+            (instance $out (export "run" (func $f)))
+            (export "my:svc/api@0.1.0" (instance $out))
+        )
+        (component
+            (core module $m (func (export "run") (result i32) i32.const 42))
+            (core instance $mi (instantiate $m))
+            (func $f (result u32) (canon lift (core func $mi "run")))
+            (instance $out (export "run" (func $f)))
+            (export "my:svc/api@0.1.0" (instance $out))
+        )
+        (instance $one (instantiate 0))
+        (instance $two (instantiate 1))
+        (export "my:svc/api@0.1.0" (instance $one "my:svc/api@0.1.0"))
+        (export "my:svc/backup@0.1.0" (instance $two "my:svc/api@0.1.0"))
+    )"#;
+
+    /// A split with the same bytes as an earlier one isn't written; it
+    /// and its instance's package point at the earlier one's file.
+    #[test]
+    fn identical_splits_are_stored_once() {
+        let bytes = wat::parse_str(WAT_REPLICAS).unwrap();
+        let mut artifacts = Artifacts::in_memory();
+        let nesting = split_into(&bytes, IN_MEMORY_ROOT, &mut artifacts).unwrap();
+
+        assert_eq!(nesting.splits[1].same_as, None);
+        assert_eq!(nesting.splits[2].same_as, Some(1));
+        assert_eq!(nesting.splits[1].sha256, nesting.splits[2].sha256);
+        assert!(artifacts
+            .read(&format!("{IN_MEMORY_ROOT}/two.wasm"))
+            .is_err());
+
+        let graph = artifacts.parsed().graph(&bytes).unwrap();
+        let out = crate::wac::generate_wac_in(
+            nesting.shims,
+            IN_MEMORY_ROOT,
+            &graph,
+            &[],
+            None,
+            "test:pkg",
+            &mut artifacts,
+        )
+        .unwrap();
+        let one_path = PathBuf::from(format!("{IN_MEMORY_ROOT}/one.wasm"));
+        assert_eq!(out.wac_deps.get("my:one"), Some(&one_path));
+        assert_eq!(out.wac_deps.get("my:two"), Some(&one_path));
+        assert!(out.wac.contains("let two = new my:two"), "{}", out.wac);
+    }
+
+    /// Rewriting a split whose file others share hands them the old
+    /// bytes under a file of their own.
+    #[test]
+    fn rewriting_a_shared_split_keeps_the_others() {
+        let bytes = wat::parse_str(WAT_REPLICAS).unwrap();
+        let mut artifacts = Artifacts::in_memory();
+        let nesting = split_into(&bytes, IN_MEMORY_ROOT, &mut artifacts).unwrap();
+        let old = artifacts
+            .read(&format!("{IN_MEMORY_ROOT}/one.wasm"))
+            .unwrap()
+            .into_owned();
+
+        rewrite_split(IN_MEMORY_ROOT, 1, b"new".to_vec(), &mut artifacts).unwrap();
+
+        let paths = SplitPaths::load(IN_MEMORY_ROOT, &artifacts).unwrap();
+        assert_eq!(paths.same_as(2), None);
+        assert_eq!(&*artifacts.read(&paths.path(1)).unwrap(), b"new");
+        assert_eq!(&*artifacts.read(&paths.path(2)).unwrap(), &old[..]);
+        assert_ne!(paths.path(1), paths.path(2));
+        assert_eq!(nesting.splits[2].name, "two");
+    }
+}
@@ -0,0 +1,350 @@
+//! Strict wiring: a [`CompositionPlan`] whose every import is explicit.
+//!
+//! The generator only wires the imports the input composition wired
+//! between its components; everything else falls through WAC's `...`
+//! and becomes an import of the composition implicitly, merged by
+//! name with whatever else imports the same thing. [`make_explicit`]
+//! reads each instance's package for the imports it actually has,
+//! wires the unsatisfied ones to top-level [`PlanItem::Import`]s, and
+//! marks the plan [explicit](CompositionPlan::explicit).
+//!
+//! A WAC `import` takes its type from a package, and splits don't
+//! carry one for the interfaces they import. So the WIT of every
+//! instance importing from a package is merged — each component only
+//! sees the parts of an interface it uses — and encoded as a WIT
+//! package next to the splits, which the imports are typed by.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use wit_parser::{InterfaceId, PackageId, PackageName, Resolve, WorldId, WorldItem, WorldKey};
+
+use crate::artifacts::Artifacts;
+use crate::plan::{CompositionPlan, PlanItem, PlannedArg, PlannedImport};
+use crate::wac::PATH_PLACEHOLDER;
+
+/// Wire every import of every instance in `plan`: imports no instance
+/// provides become [`PlannedImport`]s, typed by WIT packages written
+/// to `splits_path` through `artifacts` and added to `deps`.
+///
+/// Fails when an instance's package can't be read (middleware injected
+/// by name only has none yet), when an instance imports something
+/// other than a named interface (a bare function, say), and when two
+/// instances' views of an interface can't be merged.
+pub(crate) fn make_explicit(
+    plan: &mut CompositionPlan,
+    deps: &mut BTreeMap<String, PathBuf>,
+    splits_path: &str,
+    artifacts: &mut Artifacts,
+) -> Result<()> {
+    // Every consumer's WIT, merged, and the interface in it each
+    // unsatisfied import names.
+    let mut merged = Resolve::default();
+    let mut interfaces: BTreeMap<String, InterfaceId> = BTreeMap::new();
+    // (index into `plan.items`, import name) to wire to a top-level import.
+    let mut unwired: Vec<(usize, String)> = Vec::new();
+    for (idx, item) in plan.items.iter().enumerate() {
+        let PlanItem::Instance(inst) = item else {
+            continue;
+        };
+        let path = deps
+            .get(&inst.package)
+            .filter(|path| path.as_os_str() != PATH_PLACEHOLDER)
+            .with_context(|| {
+                format!(
+                    "`{}` ({}) has no package to read its imports from; strict wiring \
+                     needs a `path:` for every injected middleware",
+                    inst.var, inst.origin
+                )
+            })?;
+        let bytes = artifacts.read(&path.to_string_lossy())?;
+        let wit = artifacts
+            .parsed()
+            .wit(&bytes)
+            .with_context(|| format!("Failed to read the imports of `{}`", inst.var))?;
+        let (resolve, world) = &*wit;
+
+        let wired: HashSet<&str> = inst.args.iter().map(|arg| arg.name.as_str()).collect();
+        let mut missing = Vec::new();
+        for (key, item) in &resolve.worlds[*world].imports {
+            let name = resolve.name_world_key(key);
+            if wired.contains(name.as_str()) {
+                continue;
+            }
+            match (key, item) {
+                (WorldKey::Interface(_), WorldItem::Interface { id, .. }) => {
+                    missing.push((name, *id))
+                }
+                _ => bail!(
+                    "`{}` ({}) imports `{name}`, which isn't a named interface; strict \
+                     wiring can only import interfaces",
+                    inst.var,
+                    inst.origin
+                ),
+            }
+        }
+        if missing.is_empty() {
+            continue;
+        }
+        let remap = merged
+            .merge(own_root(resolve, *world, idx))
+            .with_context(|| {
+                format!(
+                    "The imports of `{}` ({}) disagree with another instance's",
+                    inst.var, inst.origin
+                )
+            })?;
+        for (name, id) in missing {
+            let id = remap.interfaces[id.index()].expect("merged interfaces are remapped");
+            interfaces.entry(name.clone()).or_insert(id);
+            unwired.push((idx, name));
+        }
+    }
+    if unwired.is_empty() {
+        plan.explicit = true;
+        return Ok(());
+    }
+
+    // One WIT package per package imported from.
+    let mut packages: BTreeMap<PackageId, String> = BTreeMap::new();
+    for &id in interfaces.values() {
+        let pkg = merged.interfaces[id]
+            .package
+            .expect("named interfaces belong to a package");
+        if packages.contains_key(&pkg) {
+            continue;
+        }
+        let name = &merged.packages[pkg].name;
+        let key = name.to_string();
+        if deps.contains_key(&key) {
+            bail!("WIT package `{key}` has the same name as an instantiated package");
+        }
+        let bytes = wit_component::encode(&merged, pkg)
+            .with_context(|| format!("Failed to encode WIT package `{key}`"))?;
+        let path = wit_package_path(splits_path, name);
+        artifacts.write(&path, bytes)?;
+        deps.insert(key.clone(), PathBuf::from(path));
+        packages.insert(pkg, key);
+    }
+
+    // Bind each import to a var no instance uses.
+    let mut taken: HashSet<String> = plan
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            PlanItem::Instance(inst) => Some(inst.var.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut imports: BTreeMap<String, PlannedImport> = BTreeMap::new();
+    for (name, &id) in &interfaces {
+        let iface = &merged.interfaces[id];
+        let pkg = iface.package.expect("named interfaces belong to a package");
+        let pkg_name = &merged.packages[pkg].name;
+        let base = format!(
+            "{}-{}-{}",
+            pkg_name.namespace,
+            pkg_name.name,
+            iface.name.as_deref().expect("named interfaces have a name")
+        );
+        let versioned = pkg_name.version.as_ref().map(|version| {
+            let version: String = version
+                .to_string()
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .collect();
+            format!("{base}-v{version}")
+        });
+        let Some(var) = std::iter::once(base)
+            .chain(versioned)
+            .find(|var| !taken.contains(var))
+        else {
+            bail!("no free WAC name to import `{name}` under");
+        };
+        taken.insert(var.clone());
+        imports.insert(
+            name.clone(),
+            PlannedImport {
+                var,
+                name: name.clone(),
+                package: packages[&pkg].clone(),
+            },
+        );
+    }
+
+    for (idx, name) in unwired {
+        let PlanItem::Instance(inst) = &mut plan.items[idx] else {
+            unreachable!("only instances are wired");
+        };
+        inst.args.push(PlannedArg {
+            source: imports[&name].var.clone(),
+            name,
+        });
+    }
+    // Imports come first, so everything wired from them follows.
+    plan.items
+        .splice(0..0, imports.into_values().map(PlanItem::Import));
+    plan.explicit = true;
+    Ok(())
+}
+
+/// A copy of `resolve` with the package holding `world` — the
+/// component's own, `root:component` for every decoded component —
+/// renamed after `idx`, so merging consumers' WIT doesn't try to merge
+/// their worlds too.
+fn own_root(resolve: &Resolve, world: WorldId, idx: usize) -> Resolve {
+    let mut resolve = resolve.clone();
+    if let Some(pkg) = resolve.worlds[world].package {
+        let old = resolve.packages[pkg].name.clone();
+        let mut name = old.clone();
+        name.name = format!("{}-{idx}", old.name);
+        resolve.package_names.shift_remove(&old);
+        resolve.package_names.insert(name.clone(), pkg);
+        resolve.packages[pkg].name = name;
+    }
+    resolve
+}
+
+/// Where the WIT package `name` strict wiring types imports by is
+/// written: under `<splits_path>/wit`, laid out as `wac compose
+/// --deps-dir` expects (`<ns>/<name>[/<version>].wasm`), since `wac`
+/// only looks versioned packages up there.
+fn wit_package_path(splits_path: &str, name: &PackageName) -> String {
+    let mut path = format!("{splits_path}/wit/{}/{}", name.namespace, name.name);
+    if let Some(version) = &name.version {
+        path.push_str(&format!("/{version}"));
+    }
+    path.push_str(".wasm");
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::{InstanceOrigin, PlannedInstance};
+    use wac_graph::EncodeOptions;
+
+    /// Exports `my:pkg/api`, importing `get` from the host's
+    /// `host:env/dep`.
+    const WAT_PROVIDER: &str = r#"(component
+        (import "host:env/dep@0.1.0" (instance $dep
+            (export "get" (func (result u32)))
+        ))
+        (alias export $dep "get" (func $f))
+        (instance $out (export "get" (func $f)))
+        (export "my:pkg/api" (instance $out))
+    )"#;
+
+    /// Imports `my:pkg/api`, and `put` from the host's `host:env/dep`.
+    const WAT_CONSUMER: &str = r#"(component
+        (import "my:pkg/api" (instance $api
+            (export "get" (func (result u32)))
+        ))
+        (import "host:env/dep@0.1.0" (instance $dep
+            (export "put" (func (param "x" u32)))
+        ))
+        (alias export $api "get" (func $f))
+        (instance $out (export "get" (func $f)))
+        (export "my:pkg/app" (instance $out))
+    )"#;
+
+    fn instance(var: &str, args: Vec<PlannedArg>, node: u32) -> PlanItem {
+        PlanItem::Instance(PlannedInstance {
+            var: var.into(),
+            package: format!("my:{var}"),
+            args,
+            origin: InstanceOrigin::Component { node },
+            note: String::new(),
+        })
+    }
+
+    /// The provider and a consumer wired to it, as the generator plans
+    /// them, with their packages stored in `artifacts`.
+    fn plan(artifacts: &mut Artifacts) -> (CompositionPlan, BTreeMap<String, PathBuf>) {
+        let mut deps = BTreeMap::new();
+        for (var, wat) in [("srv", WAT_PROVIDER), ("app", WAT_CONSUMER)] {
+            let path = format!("mem/{var}.wasm");
+            artifacts
+                .write(&path, wat::parse_str(wat).unwrap())
+                .unwrap();
+            deps.insert(format!("my:{var}"), PathBuf::from(path));
+        }
+        let mut plan = CompositionPlan::new("test:pkg");
+        plan.items = vec![
+            instance("srv", Vec::new(), 0),
+            instance(
+                "app",
+                vec![PlannedArg {
+                    name: "my:pkg/api".into(),
+                    source: "srv".into(),
+                }],
+                1,
+            ),
+        ];
+        (plan, deps)
+    }
+
+    #[test]
+    fn unsatisfied_imports_become_typed_imports() {
+        let mut artifacts = Artifacts::in_memory();
+        let (mut plan, mut deps) = plan(&mut artifacts);
+        make_explicit(&mut plan, &mut deps, "mem", &mut artifacts).unwrap();
+
+        assert!(plan.explicit);
+        assert_eq!(
+            plan.items[0],
+            PlanItem::Import(PlannedImport {
+                var: "host-env-dep".into(),
+                name: "host:env/dep@0.1.0".into(),
+                package: "host:env@0.1.0".into(),
+            })
+        );
+        let wac = plan.to_wac();
+        assert!(!wac.contains("..."), "{wac}");
+        assert!(
+            wac.contains("let app = new my:app {\n    \"my:pkg/api\": srv[\"my:pkg/api\"],\n    \"host:env/dep@0.1.0\": host-env-dep,\n};"),
+            "{wac}"
+        );
+
+        let packages: BTreeMap<String, Vec<u8>> = deps
+            .iter()
+            .map(|(key, path)| {
+                let bytes = artifacts.read(&path.to_string_lossy()).unwrap();
+                (key.clone(), bytes.into_owned())
+            })
+            .collect();
+        let direct = plan
+            .build_graph(&packages)
+            .unwrap()
+            .encode(EncodeOptions::default())
+            .unwrap();
+        let parsed = crate::compose_wac_bytes(&wac, &packages).unwrap();
+        for composed in [direct, parsed] {
+            // One import, the union of what the two instances use of it.
+            let (resolve, world) = crate::adapter::decode_component_wit(&composed).unwrap();
+            let imports = &resolve.worlds[world].imports;
+            assert_eq!(imports.len(), 1);
+            let (key, WorldItem::Interface { id, .. }) = imports.first().unwrap() else {
+                panic!("expected an interface import");
+            };
+            assert_eq!(resolve.name_world_key(key), "host:env/dep@0.1.0");
+            let mut funcs: Vec<_> = resolve.interfaces[*id].functions.keys().collect();
+            funcs.sort();
+            assert_eq!(funcs, ["get", "put"]);
+        }
+    }
+
+    #[test]
+    fn pathless_middleware_is_rejected() {
+        let mut artifacts = Artifacts::in_memory();
+        let (mut plan, mut deps) = plan(&mut artifacts);
+        deps.insert("my:app".into(), PathBuf::from(PATH_PLACEHOLDER));
+        let err = make_explicit(&mut plan, &mut deps, "mem", &mut artifacts).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("`app` (component node 1) has no package"),
+            "{err:#}"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::artifacts::Artifacts;
 use crate::{parse, wac};
 use cviz::model::CompositionGraph;
 use cviz::parse::json;
@@ -241,6 +242,58 @@ export app["wasi:logging/log@0.1.0"];
     Ok(())
 }
 
+/// Every planned instance records what produced it: the input
+/// component's node, or the rule that injected the middleware.
+#[test]
+fn plan_attributes_instances_to_rules() -> anyhow::Result<()> {
+    use crate::lowlevel::{InstanceOrigin, PlanItem};
+    let yaml = r#"
+version: 1
+rules:
+  - before:
+      interface: wasi:logging/log@0.1.0
+      provider:
+        name: log-provider
+    inject:
+      - name: log-middleware
+  - before:
+      interface: wasi:logging/log@0.1.0
+    inject:
+      - name: log-audit
+"#;
+    let cfg = parse::config::parse_yaml(yaml)?;
+    let graph = json::parse_json_str(testcases::json_log_short_chain())?;
+    let out = wac::generate_wac(
+        HashMap::new(),
+        "placeholder",
+        &graph,
+        &cfg,
+        None,
+        "example:composition",
+    )?;
+    assert_eq!(out.plan.to_wac(), out.wac);
+
+    let origins: HashMap<&str, InstanceOrigin> = out
+        .plan
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            PlanItem::Instance(inst) => Some((inst.var.as_str(), inst.origin)),
+            PlanItem::Import(_) | PlanItem::Export(_) => None,
+        })
+        .collect();
+    assert_eq!(
+        origins["log-middleware"],
+        InstanceOrigin::Middleware { rule: 0 }
+    );
+    assert_eq!(origins["log-audit"], InstanceOrigin::Middleware { rule: 1 });
+    assert!(matches!(
+        origins["log-provider"],
+        InstanceOrigin::Component { .. }
+    ));
+    Ok(())
+}
+
 #[test]
 fn between_on_log_chain() -> anyhow::Result<()> {
     let yaml = r#"
@@ -339,6 +392,516 @@ rules:
 // path (path: None), so `validate_contract` emits a Warn and proceeds;
 // the generated WAC must be identical to the untyped result.
 
+// --- Repeated-instance tests ---
+// One component instantiated twice (tenant-a / tenant-b), each wired to
+// its own config provider.
+
+fn generate_tenants(yaml: &str) -> anyhow::Result<wac::WacOutput> {
+    let cfg = parse::config::parse_yaml(yaml)?;
+    let graph = json::parse_json_str(testcases::json_two_tenants())?;
+    wac::generate_wac(
+        HashMap::new(),
+        "placeholder",
+        &graph,
+        &cfg,
+        None,
+        "example:composition",
+    )
+}
+
+#[test]
+fn repeated_component_keeps_an_instance_per_wiring() -> anyhow::Result<()> {
+    let out = generate_tenants("version: 1\nrules: []\n")?;
+    let wac = out.wac;
+    assert!(
+        wac.contains(
+            r#"let tenant-a = new my:tenant-a {
+    "my:app/config@0.1.0": config-a["my:app/config@0.1.0"],"#
+        ),
+        "tenant-a should be wired from config-a:\n{wac}"
+    );
+    assert!(
+        wac.contains(
+            r#"let tenant-b = new my:tenant-b {
+    "my:app/config@0.1.0": config-b["my:app/config@0.1.0"],"#
+        ),
+        "tenant-b should keep its own instance, wired from config-b:\n{wac}"
+    );
+    assert!(wac.contains(r#"export tenant-b["acme:tenants/b@0.1.0"];"#));
+    // Both instances are created from the one split.
+    assert_eq!(out.wac_deps["my:tenant-a"], out.wac_deps["my:tenant-b"]);
+    Ok(())
+}
+
+#[test]
+fn before_rule_targets_one_instance_by_consumer() -> anyhow::Result<()> {
+    let out = generate_tenants(
+        r#"
+version: 1
+rules:
+  - before:
+      interface: my:app/config@0.1.0
+      consumer:
+        name: tenant-b
+    inject:
+      - name: config-cache
+"#,
+    )?;
+    let wac = out.wac;
+    assert!(wac.contains(
+        r#"let config-cache = new my:config-cache {
+    "my:app/config@0.1.0": config-b["my:app/config@0.1.0"],"#
+    ));
+    assert!(wac.contains(r#""my:app/config@0.1.0": config-cache["my:app/config@0.1.0"]"#));
+    assert!(
+        wac.contains(r#""my:app/config@0.1.0": config-a["my:app/config@0.1.0"]"#),
+        "tenant-a must still be wired straight to config-a:\n{wac}"
+    );
+    Ok(())
+}
+
+#[test]
+fn before_rule_targets_one_instance_by_index() -> anyhow::Result<()> {
+    let out = generate_tenants(
+        r#"
+version: 1
+rules:
+  - before:
+      interface: my:app/config@0.1.0
+      provider:
+        instance: 301
+    inject:
+      - name: config-cache
+"#,
+    )?;
+    let wac = out.wac;
+    assert!(wac.contains(
+        r#"let config-cache = new my:config-cache {
+    "my:app/config@0.1.0": config-a["my:app/config@0.1.0"],"#
+    ));
+    assert!(
+        wac.contains(r#""my:app/config@0.1.0": config-b["my:app/config@0.1.0"]"#),
+        "tenant-b must still be wired straight to config-b:\n{wac}"
+    );
+    Ok(())
+}
+
+#[test]
+fn repeated_fan_in_consumers_get_their_own_vars() -> anyhow::Result<()> {
+    let cfg = parse::config::parse_yaml(
+        r#"
+version: 1
+rules:
+  - before:
+      interface: my:app/config@0.1.0
+      provider:
+        instance: 311
+    inject:
+      - name: config-cache
+  - before:
+      interface: my:app/log@0.1.0
+      provider:
+        instance: 314
+    inject:
+      - name: log-filter
+"#,
+    )?;
+    let graph = json::parse_json_str(testcases::json_two_fan_in_tenants())?;
+    let out = wac::generate_wac(
+        HashMap::new(),
+        "placeholder",
+        &graph,
+        &cfg,
+        None,
+        "example:composition",
+    )?;
+    let wac = out.wac;
+    assert!(
+        wac.contains(
+            r#"let app = new my:app {
+    "my:app/config@0.1.0": config-cache["my:app/config@0.1.0"],
+    "my:app/log@0.1.0": log-a["my:app/log@0.1.0"],"#
+        ),
+        "{wac}"
+    );
+    assert!(
+        wac.contains(
+            r#"let app-v2 = new my:app {
+    "my:app/config@0.1.0": config-b["my:app/config@0.1.0"],
+    "my:app/log@0.1.0": log-filter["my:app/log@0.1.0"],"#
+        ),
+        "{wac}"
+    );
+    assert_eq!(wac.matches("let app = ").count(), 1, "{wac}");
+    assert!(
+        wac.contains(r#"export app["acme:tenants/a@0.1.0"];"#),
+        "{wac}"
+    );
+    assert!(
+        wac.contains(r#"export app-v2["acme:tenants/b@0.1.0"];"#),
+        "{wac}"
+    );
+    Ok(())
+}
+
+#[test]
+fn consumer_rule_on_a_shared_provider_edge_is_rejected() {
+    let rule = |consumer: &str| {
+        parse::config::parse_yaml(&format!(
+            r#"
+version: 1
+rules:
+  - before:
+      interface: my:app/config@0.1.0
+      consumer:
+        name: {consumer}
+    inject:
+      - name: config-cache
+"#
+        ))
+        .unwrap()
+    };
+    let graph = json::parse_json_str(testcases::json_shared_config()).unwrap();
+    let generate = |consumer| {
+        wac::generate_wac(
+            HashMap::new(),
+            "placeholder",
+            &graph,
+            &rule(consumer),
+            None,
+            "example:composition",
+        )
+    };
+
+    // tenant-b's edge heads the one chain on config; tenant-a's has none.
+    generate("tenant-b").expect("the chained consumer can be targeted");
+    let err = generate("tenant-a")
+        .err()
+        .expect("an unchained consumer edge can't be targeted")
+        .to_string();
+    assert!(
+        err.contains("`tenant-a` imports `my:app/config@0.1.0` from `config`"),
+        "{err}"
+    );
+}
+
+#[test]
+fn unscoped_rule_on_repeated_instances_injects_per_site() -> anyhow::Result<()> {
+    let out = generate_tenants(
+        r#"
+version: 1
+rules:
+  - before:
+      interface: my:app/config@0.1.0
+    inject:
+      - name: config-cache
+"#,
+    )?;
+    let wac = out.wac;
+    for (var, provider) in [
+        ("config-cache", "config-b"),
+        ("config-cache-site2", "config-a"),
+    ] {
+        assert!(
+            wac.contains(&format!(
+                "let {var} = new my:config-cache {{\n    \"my:app/config@0.1.0\": {provider}[\"my:app/config@0.1.0\"],"
+            )),
+            "expected `{var}` wrapping {provider}:\n{wac}"
+        );
+    }
+    assert_eq!(
+        out.wac_deps.keys().filter(|k| k.contains("cache")).count(),
+        1
+    );
+    Ok(())
+}
+
+#[test]
+fn shared_middleware_cannot_wrap_two_providers_of_one_interface() {
+    let err = generate_tenants(
+        r#"
+version: 1
+rules:
+  - before:
+      interface: my:app/config@0.1.0
+    inject:
+      - name: config-cache
+        instance: shared
+"#,
+    )
+    .err()
+    .expect("one instance can't import the interface from both providers")
+    .to_string();
+    assert!(err.contains("`config-cache` is shared"), "{err}");
+}
+
+#[test]
+fn shared_middleware_wraps_every_interface_in_one_instance() -> anyhow::Result<()> {
+    let yaml = r#"
+version: 1
+rules:
+  - before:
+      interface: wasi:http/handler@0.3.0-rc-2026-01-06
+      provider:
+        name: http-provider
+    inject:
+      - name: audit
+        instance: shared
+  - before:
+      interface: wasi:logging/log@0.1.0
+      provider:
+        name: log-provider
+    inject:
+      - name: audit
+        instance: shared
+"#;
+    let cfg = parse::config::parse_yaml(yaml)?;
+    let graph = json::parse_json_str(testcases::json_multi_interface_node())?;
+    let out = wac::generate_wac(
+        HashMap::new(),
+        "placeholder",
+        &graph,
+        &cfg,
+        None,
+        "example:composition",
+    )?;
+    let wac = out.wac;
+    assert_eq!(wac.matches("new my:audit").count(), 1, "{wac}");
+    assert!(wac.contains(r#""wasi:logging/log@0.1.0": log-provider["wasi:logging/log@0.1.0"]"#));
+    assert!(wac.contains(r#""wasi:logging/log@0.1.0": audit["wasi:logging/log@0.1.0"]"#));
+    assert!(wac.contains(
+        r#""wasi:http/handler@0.3.0-rc-2026-01-06": audit["wasi:http/handler@0.3.0-rc-2026-01-06"]"#
+    ));
+    Ok(())
+}
+
+/// Generate WAC for `yaml` over [`testcases::json_kv_app`], with the
+/// cache middleware and the redis kv store in memory under `mem/`.
+fn generate_kv_app(yaml: &str) -> anyhow::Result<wac::WacOutput> {
+    let cfg = parse::config::parse_yaml(yaml)?;
+    let graph = json::parse_json_str(testcases::json_kv_app())?;
+    let mut artifacts = Artifacts::in_memory();
+    for (path, wat) in [
+        ("mem/cache.wasm", testcases::WAT_CACHE),
+        ("mem/redis.wasm", testcases::WAT_REDIS),
+    ] {
+        artifacts.write(path, wat::parse_str(wat)?)?;
+    }
+    wac::generate_wac_in(
+        HashMap::new(),
+        "placeholder",
+        &graph,
+        &cfg,
+        None,
+        "example:composition",
+        &mut artifacts,
+    )
+}
+
+#[test]
+fn middleware_imports_are_wired_from_the_composition() -> anyhow::Result<()> {
+    let wac = generate_kv_app(
+        r#"
+version: 1
+rules:
+  - before:
+      interface: my:app/api@0.1.0
+    inject:
+      - name: cache
+        path: mem/cache.wasm
+"#,
+    )?
+    .wac;
+    let cache = wac.find("let cache = ").expect("cache is planned");
+    assert!(wac.find("let kv = ").is_some_and(|kv| kv < cache), "{wac}");
+    assert!(
+        wac[cache..].contains("\"wasi:keyvalue/store@0.2.0\": kv[\"wasi:keyvalue/store@0.2.0\"]"),
+        "expected the cache to share the app's kv store:\n{wac}"
+    );
+    Ok(())
+}
+
+#[test]
+fn middleware_deps_come_before_the_composition() -> anyhow::Result<()> {
+    let out = generate_kv_app(
+        r#"
+version: 1
+rules:
+  - before:
+      interface: my:app/api@0.1.0
+    inject:
+      - name: cache
+        path: mem/cache.wasm
+        deps:
+          - name: redis
+            path: mem/redis.wasm
+"#,
+    )?;
+    let wac = &out.wac;
+    let redis = wac
+        .find("let redis = new my:redis { ... };")
+        .expect("redis is planned");
+    let cache = wac.find("let cache = ").expect("cache is planned");
+    assert!(redis < cache, "{wac}");
+    assert!(
+        wac[cache..]
+            .contains("\"wasi:keyvalue/store@0.2.0\": redis[\"wasi:keyvalue/store@0.2.0\"]"),
+        "expected the cache to use redis:\n{wac}"
+    );
+    assert_eq!(
+        out.wac_deps
+            .get("my:redis")
+            .map(|path| path.to_string_lossy()),
+        Some("mem/redis.wasm".into())
+    );
+    Ok(())
+}
+
+#[test]
+fn replace_rewires_consumers_and_drops_the_provider() -> anyhow::Result<()> {
+    let out = generate_kv_app(
+        r#"
+version: 1
+rules:
+  - replace:
+      interface: wasi:keyvalue/store@0.2.0
+      provider:
+        name: kv
+    with:
+      name: kv-mock
+      path: mem/redis.wasm
+"#,
+    )?;
+    let wac = &out.wac;
+    assert!(!wac.contains("let kv = "), "{wac}");
+    let mock = wac
+        .find("let kv-mock = new my:kv-mock { ... };")
+        .expect("kv-mock is planned");
+    let srv = wac.find("let srv = ").expect("srv is planned");
+    assert!(mock < srv, "{wac}");
+    assert!(
+        wac[srv..]
+            .contains("\"wasi:keyvalue/store@0.2.0\": kv-mock[\"wasi:keyvalue/store@0.2.0\"]"),
+        "expected srv to use kv-mock:\n{wac}"
+    );
+    assert!(!out.wac_deps.contains_key("my:kv"));
+    assert!(out.wac_deps.contains_key("my:kv-mock"));
+    Ok(())
+}
+
+#[test]
+fn replacement_must_export_the_interface() {
+    let err = generate_kv_app(
+        r#"
+version: 1
+rules:
+  - replace:
+      interface: wasi:keyvalue/store@0.2.0
+    with:
+      name: kv-mock
+      path: mem/cache.wasm
+"#,
+    )
+    .err()
+    .expect("the cache doesn't export the kv store");
+    assert!(
+        err.to_string()
+            .contains("replacement `kv-mock` doesn't export `wasi:keyvalue/store@0.2.0`"),
+        "{err:#}"
+    );
+}
+
+/// Generate WAC for `testcases::json_admin_app` under the rules in `yaml`.
+fn generate_admin_app(yaml: &str) -> anyhow::Result<wac::WacOutput> {
+    let cfg = parse::config::parse_yaml(yaml)?;
+    let graph = json::parse_json_str(testcases::json_admin_app())?;
+    wac::generate_wac(
+        HashMap::new(),
+        "placeholder",
+        &graph,
+        &cfg,
+        None,
+        "example:composition",
+    )
+}
+
+#[test]
+fn hide_export_drops_the_export_and_its_provider() -> anyhow::Result<()> {
+    let wac = generate_admin_app(
+        r#"
+version: 1
+rules:
+  - hide_export:
+      interface: my:app/admin@0.1.0
+"#,
+    )?;
+    assert!(!wac.wac.contains("my:app/admin@0.1.0"), "{}", wac.wac);
+    assert!(!wac.wac.contains("let admin = "), "{}", wac.wac);
+    // `kv` still serves `srv`.
+    assert!(wac.wac.contains("let kv = "), "{}", wac.wac);
+    assert!(wac.wac.contains("export srv[\"my:app/api@0.1.0\"];"));
+    assert!(!wac.wac_deps.contains_key("my:admin"));
+    assert!(wac.wac_deps.contains_key("my:kv"));
+    Ok(())
+}
+
+#[test]
+fn rename_export_and_allowlist() -> anyhow::Result<()> {
+    let wac = generate_admin_app(
+        r#"
+version: 1
+rules:
+  - rename_export:
+      interface: my:app/api@0.1.0
+      to: my:app/public@0.1.0
+  - hide_export:
+      interface: my:app/admin@0.1.0
+  - allow_exports:
+      interfaces: [my:app/public@0.1.0]
+"#,
+    )?;
+    assert!(
+        wac.wac
+            .contains("export srv[\"my:app/api@0.1.0\"] as \"my:app/public@0.1.0\";"),
+        "{}",
+        wac.wac
+    );
+
+    let err = generate_admin_app(
+        r#"
+version: 1
+rules:
+  - allow_exports:
+      interfaces: [my:app/api@0.1.0]
+"#,
+    )
+    .err()
+    .expect("the admin export isn't allowed");
+    assert!(
+        err.to_string()
+            .contains("the composition still exports `my:app/admin@0.1.0`"),
+        "{err:#}"
+    );
+
+    let err = generate_admin_app(
+        r#"
+version: 1
+rules:
+  - rename_export:
+      interface: my:app/api@0.1.0
+      to: my:app/admin@0.1.0
+"#,
+    )
+    .err()
+    .expect("the new name is taken");
+    assert!(
+        err.to_string()
+            .contains("which the composition already exports"),
+        "{err:#}"
+    );
+    Ok(())
+}
+
 #[test]
 fn before_on_all_typed() -> anyhow::Result<()> {
     run_all_typed(testcases::yaml_before(), testcases::yaml_before_all_exp())
@@ -511,7 +1074,7 @@ fn run_all_typed(yaml: &str, exp: HashMap<String, String>) -> anyhow::Result<()>
 // Exercises the full splice pipeline on a real composed Wasm binary that
 // contains an internal shim sub-component.  Unlike the JSON-fixture tests
 // above, this one writes actual bytes to disk so split_out_composition can
-// identify shim nodes via its heuristic, then verifies that generate_wac
+// identify shim nodes from their structure, then verifies that generate_wac
 // omits those spurious shim-sourced graph-level exports.
 
 #[test]
@@ -525,7 +1088,7 @@ fn shim_exports_not_in_splice_roundtrip_wac() -> anyhow::Result<()> {
     //     what wac compose produces when an inner component's shim becomes
     //     visible as a peer-level node after flattening)
     //
-    // $shim has no core module → split.rs marks it as a shim.
+    // $shim only re-exports its import → split.rs marks it as a shim.
     // $service has a core module → split.rs treats it as a real component.
     let wat = r#"(component
         (component $shim
@@ -576,7 +1139,7 @@ fn shim_exports_not_in_splice_roundtrip_wac() -> anyhow::Result<()> {
     assert!(
         !shim_comps.is_empty(),
         "expected split.rs to detect at least one shim component; \
-         check the WAT — the shim sub-component must have no core module"
+         check the WAT — the shim sub-component must only re-export its imports"
     );
 
     let graph = parse_component(&bytes).expect("failed to parse composed binary");
@@ -1327,15 +1890,15 @@ let srv = new my:srv {
     ...
 };
 
-let middleware-b = new my:middleware-b {
+let middleware-b-site2 = new my:middleware-b {
     "wasi:http/handler@0.3.0-rc-2026-01-06": srv["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
 };
 
-let middleware-a = new my:middleware-a {
-    "wasi:http/handler@0.3.0-rc-2026-01-06": middleware-b["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
+let middleware-a-site2 = new my:middleware-a {
+    "wasi:http/handler@0.3.0-rc-2026-01-06": middleware-b-site2["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
 };
 
-export middleware-a["wasi:http/handler@0.3.0-rc-2026-01-06"];
+export middleware-a-site2["wasi:http/handler@0.3.0-rc-2026-01-06"];
         "#
     }
     fn yaml_before_noprov_on_long_exp() -> &'static str {
@@ -1359,28 +1922,28 @@ let srv-b = new my:srv-b {
     ...
 };
 
-let middleware-b = new my:middleware-b {
+let middleware-b-site2 = new my:middleware-b {
     "wasi:http/handler@0.3.0-rc-2026-01-06": srv-b["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
 };
 
-let middleware-a = new my:middleware-a {
-    "wasi:http/handler@0.3.0-rc-2026-01-06": middleware-b["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
+let middleware-a-site2 = new my:middleware-a {
+    "wasi:http/handler@0.3.0-rc-2026-01-06": middleware-b-site2["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
 };
 
 let srv = new my:srv {
-    "wasi:http/handler@0.3.0-rc-2026-01-06": middleware-a["wasi:http/handler@0.3.0-rc-2026-01-06"],
+    "wasi:http/handler@0.3.0-rc-2026-01-06": middleware-a-site2["wasi:http/handler@0.3.0-rc-2026-01-06"],
     ...
 };
 
-let middleware-b = new my:middleware-b {
+let middleware-b-site3 = new my:middleware-b {
     "wasi:http/handler@0.3.0-rc-2026-01-06": srv["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
 };
 
-let middleware-a = new my:middleware-a {
-    "wasi:http/handler@0.3.0-rc-2026-01-06": middleware-b["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
+let middleware-a-site3 = new my:middleware-a {
+    "wasi:http/handler@0.3.0-rc-2026-01-06": middleware-b-site3["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
 };
 
-export middleware-a["wasi:http/handler@0.3.0-rc-2026-01-06"];
+export middleware-a-site3["wasi:http/handler@0.3.0-rc-2026-01-06"];
         "#
     }
     pub fn yaml_before_noprov_all_exp() -> HashMap<String, String> {
@@ -1712,11 +2275,11 @@ let srv = new my:srv {
     ...
 };
 
-let middleware-a = new my:middleware-a {
+let middleware-a-site2 = new my:middleware-a {
     "wasi:http/handler@0.3.0-rc-2026-01-06": srv["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
 };
 
-export middleware-a["wasi:http/handler@0.3.0-rc-2026-01-06"];
+export middleware-a-site2["wasi:http/handler@0.3.0-rc-2026-01-06"];
         "#
     }
     fn yaml_multi_rule_on_long_exp() -> &'static str {
@@ -1752,20 +2315,20 @@ let middleware-b = new my:middleware-b {
     "wasi:http/handler@0.3.0-rc-2026-01-06": middleware-c["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
 };
 
-let middleware-a = new my:middleware-a {
+let middleware-a-site2 = new my:middleware-a {
     "wasi:http/handler@0.3.0-rc-2026-01-06": middleware-b["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
 };
 
 let srv = new my:srv {
-    "wasi:http/handler@0.3.0-rc-2026-01-06": middleware-a["wasi:http/handler@0.3.0-rc-2026-01-06"],
+    "wasi:http/handler@0.3.0-rc-2026-01-06": middleware-a-site2["wasi:http/handler@0.3.0-rc-2026-01-06"],
     ...
 };
 
-let middleware-a = new my:middleware-a {
+let middleware-a-site3 = new my:middleware-a {
     "wasi:http/handler@0.3.0-rc-2026-01-06": srv["wasi:http/handler@0.3.0-rc-2026-01-06"], ...
 };
 
-export middleware-a["wasi:http/handler@0.3.0-rc-2026-01-06"];
+export middleware-a-site3["wasi:http/handler@0.3.0-rc-2026-01-06"];
         "#
     }
     pub fn yaml_multi_rule_all_exp() -> HashMap<String, String> {
@@ -2300,4 +2863,263 @@ export other["wasi:http/handler@0.3.0-rc-2026-01-06"];
         }
         "#
     }
+
+    /// One `app` component instantiated twice, each instance wired to
+    /// a different config provider and exported on its own.
+    pub fn json_two_tenants() -> &'static str {
+        r#"
+        {
+          "version": 1,
+          "nodes": [
+            {
+              "id": 301,
+              "name": "config-a",
+              "component_index": 0,
+              "component_num": 0,
+              "imports": []
+            },
+            {
+              "id": 302,
+              "name": "config-b",
+              "component_index": 1,
+              "component_num": 1,
+              "imports": []
+            },
+            {
+              "id": 303,
+              "name": "tenant-a",
+              "component_index": 2,
+              "component_num": 2,
+              "imports": [
+                {
+                  "interface": "my:app/config@0.1.0",
+                  "short": "config",
+                  "source_instance": 301,
+                  "is_host_import": false
+                }
+              ]
+            },
+            {
+              "id": 304,
+              "name": "tenant-b",
+              "component_index": 2,
+              "component_num": 2,
+              "imports": [
+                {
+                  "interface": "my:app/config@0.1.0",
+                  "short": "config",
+                  "source_instance": 302,
+                  "is_host_import": false
+                }
+              ]
+            }
+          ],
+          "exports": [
+            {
+              "interface": "acme:tenants/a@0.1.0",
+              "source_instance": 303
+            },
+            {
+              "interface": "acme:tenants/b@0.1.0",
+              "source_instance": 304
+            }
+          ]
+        }
+        "#
+    }
+
+    /// One `app` component instantiated twice, each instance importing
+    /// config and logging from its own pair of providers — so each is
+    /// a fan-in consumer of two chains.
+    pub fn json_two_fan_in_tenants() -> &'static str {
+        r#"
+        {
+          "version": 1,
+          "nodes": [
+            { "id": 311, "name": "config-a", "component_index": 0, "component_num": 0, "imports": [] },
+            { "id": 312, "name": "log-a", "component_index": 1, "component_num": 1, "imports": [] },
+            { "id": 313, "name": "config-b", "component_index": 2, "component_num": 2, "imports": [] },
+            { "id": 314, "name": "log-b", "component_index": 3, "component_num": 3, "imports": [] },
+            {
+              "id": 315,
+              "name": "app",
+              "component_index": 4,
+              "component_num": 4,
+              "imports": [
+                { "interface": "my:app/config@0.1.0", "short": "config", "source_instance": 311, "is_host_import": false },
+                { "interface": "my:app/log@0.1.0", "short": "log", "source_instance": 312, "is_host_import": false }
+              ]
+            },
+            {
+              "id": 316,
+              "name": "app",
+              "component_index": 4,
+              "component_num": 4,
+              "imports": [
+                { "interface": "my:app/config@0.1.0", "short": "config", "source_instance": 313, "is_host_import": false },
+                { "interface": "my:app/log@0.1.0", "short": "log", "source_instance": 314, "is_host_import": false }
+              ]
+            }
+          ],
+          "exports": [
+            { "interface": "acme:tenants/a@0.1.0", "source_instance": 315 },
+            { "interface": "acme:tenants/b@0.1.0", "source_instance": 316 }
+          ]
+        }
+        "#
+    }
+
+    /// Two tenants importing config from the one provider.
+    pub fn json_shared_config() -> &'static str {
+        r#"
+        {
+          "version": 1,
+          "nodes": [
+            { "id": 321, "name": "config", "component_index": 0, "component_num": 0, "imports": [] },
+            {
+              "id": 322,
+              "name": "tenant-a",
+              "component_index": 1,
+              "component_num": 1,
+              "imports": [
+                { "interface": "my:app/config@0.1.0", "short": "config", "source_instance": 321, "is_host_import": false }
+              ]
+            },
+            {
+              "id": 323,
+              "name": "tenant-b",
+              "component_index": 2,
+              "component_num": 2,
+              "imports": [
+                { "interface": "my:app/config@0.1.0", "short": "config", "source_instance": 321, "is_host_import": false }
+              ]
+            }
+          ],
+          "exports": [
+            { "interface": "acme:tenants/a@0.1.0", "source_instance": 322 },
+            { "interface": "acme:tenants/b@0.1.0", "source_instance": 323 }
+          ]
+        }
+        "#
+    }
+
+    /// An app whose kv store is another component of the composition:
+    /// kv → srv, exporting `my:app/api@0.1.0`.
+    pub fn json_kv_app() -> &'static str {
+        r#"
+        {
+          "version": 1,
+          "nodes": [
+            {
+              "id": 401,
+              "name": "kv",
+              "component_index": 0,
+              "component_num": 0,
+              "imports": []
+            },
+            {
+              "id": 402,
+              "name": "srv",
+              "component_index": 1,
+              "component_num": 1,
+              "imports": [
+                {
+                  "interface": "wasi:keyvalue/store@0.2.0",
+                  "short": "store",
+                  "source_instance": 401,
+                  "is_host_import": false
+                }
+              ]
+            }
+          ],
+          "exports": [
+            {
+              "interface": "my:app/api@0.1.0",
+              "source_instance": 402
+            }
+          ]
+        }
+        "#
+    }
+
+    /// `srv` and an `admin` console both use `kv` (its store and its
+    /// atomics); the composition exports the app's API and the admin
+    /// interface.
+    pub fn json_admin_app() -> &'static str {
+        r#"
+        {
+          "version": 1,
+          "nodes": [
+            {
+              "id": 501,
+              "name": "kv",
+              "component_index": 0,
+              "component_num": 0,
+              "imports": []
+            },
+            {
+              "id": 502,
+              "name": "srv",
+              "component_index": 1,
+              "component_num": 1,
+              "imports": [
+                {
+                  "interface": "wasi:keyvalue/store@0.2.0",
+                  "short": "store",
+                  "source_instance": 501,
+                  "is_host_import": false
+                }
+              ]
+            },
+            {
+              "id": 503,
+              "name": "admin",
+              "component_index": 2,
+              "component_num": 2,
+              "imports": [
+                {
+                  "interface": "wasi:keyvalue/atomics@0.2.0",
+                  "short": "atomics",
+                  "source_instance": 501,
+                  "is_host_import": false
+                }
+              ]
+            }
+          ],
+          "exports": [
+            {
+              "interface": "my:app/api@0.1.0",
+              "source_instance": 502
+            },
+            {
+              "interface": "my:app/admin@0.1.0",
+              "source_instance": 503
+            }
+          ]
+        }
+        "#
+    }
+
+    /// Middleware wrapping `my:app/api@0.1.0` that also imports a kv store.
+    pub const WAT_CACHE: &str = r#"(component
+        (import "my:app/api@0.1.0" (instance $api
+            (export "get" (func (result u32)))
+        ))
+        (import "wasi:keyvalue/store@0.2.0" (instance $kv
+            (export "put" (func (param "x" u32)))
+        ))
+        (alias export $api "get" (func $f))
+        (instance $out (export "get" (func $f)))
+        (export "my:app/api@0.1.0" (instance $out))
+    )"#;
+
+    /// A kv store backed by the host's `host:db/conn`.
+    pub const WAT_REDIS: &str = r#"(component
+        (import "host:db/conn@0.1.0" (instance $conn
+            (export "put" (func (param "x" u32)))
+        ))
+        (alias export $conn "put" (func $f))
+        (instance $out (export "put" (func $f)))
+        (export "wasi:keyvalue/store@0.2.0" (instance $out))
+    )"#;
 }
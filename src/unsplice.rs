@@ -0,0 +1,443 @@
+//! Recognize and remove previously injected middleware in a
+//! composition graph.
+//!
+//! Splicing inserts middleware as ordinary instances on an interface
+//! edge: the consumer imports the interface from the middleware, which
+//! imports it in turn from the original provider. Un-splicing undoes
+//! that by rewiring every import (and every composition export) that
+//! sources from a removed instance to wherever that instance itself
+//! imported the interface from, then dropping the instance. The result
+//! is a graph [`crate::wac::generate_wac_in`] can emit as-is.
+//!
+//! Middleware is identified through the composition's embedded
+//! [`Provenance`] when it has one. Without provenance, only tier-1
+//! middleware can be recognized, through the `<name>-adapter-<iface>`
+//! instance names splicer gives the adapters wrapping it.
+
+use std::collections::BTreeSet;
+
+use anyhow::bail;
+use cviz::model::CompositionGraph;
+
+use crate::provenance::Provenance;
+use crate::wac::{ADAPTER_INFIX, SITE_INFIX};
+
+/// Which middleware [`crate::unsplice`] removes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MiddlewareSelector {
+    /// Every middleware splicer injected — restores the original
+    /// wiring.
+    All,
+    /// The injections with these names (their WAC instance names).
+    Named(Vec<String>),
+    /// Every injection resolved from this builtin. Requires the
+    /// composition to carry provenance.
+    Builtin(String),
+}
+
+/// Resolve `selector` to the middleware names to remove from `graph`.
+pub(crate) fn select(
+    graph: &CompositionGraph,
+    provenance: Option<&Provenance>,
+    selector: &MiddlewareSelector,
+) -> anyhow::Result<BTreeSet<String>> {
+    let names: BTreeSet<String> = match (selector, provenance) {
+        (MiddlewareSelector::All, Some(provenance)) => provenance
+            .middleware
+            .iter()
+            .map(|mw| mw.name.clone())
+            .collect(),
+        (MiddlewareSelector::All, None) => graph
+            .nodes
+            .values()
+            .filter_map(|node| {
+                let (name, _) = node.display_label().split_once(ADAPTER_INFIX)?;
+                Some(name.to_string())
+            })
+            .collect(),
+        (MiddlewareSelector::Named(names), _) => names.iter().cloned().collect(),
+        (MiddlewareSelector::Builtin(builtin), Some(provenance)) => provenance
+            .middleware
+            .iter()
+            .filter(|mw| mw.builtin.as_deref() == Some(builtin))
+            .map(|mw| mw.name.clone())
+            .collect(),
+        (MiddlewareSelector::Builtin(builtin), None) => bail!(
+            "cannot find injections of builtin '{builtin}': the composition carries no \
+             splicer provenance (splice with --provenance to record it)"
+        ),
+    };
+
+    if names.is_empty() {
+        bail!(
+            "no injected middleware found to remove{}",
+            if provenance.is_none() {
+                " (the composition carries no splicer provenance, so only tier-1 \
+                 middleware can be detected)"
+            } else {
+                ""
+            }
+        );
+    }
+    for name in &names {
+        if !graph
+            .nodes
+            .values()
+            .any(|node| is_instance_of(node.display_label(), name))
+        {
+            bail!("middleware '{name}' is not instantiated in the composition");
+        }
+    }
+    Ok(names)
+}
+
+/// Whether middleware `name` was already injected on `interface` by an
+/// earlier splice. Trusts `provenance` when the composition has one.
+/// Without it, only what splicer itself leaves behind counts: a tier-1
+/// adapter generated for `name`, or an instance of `name` sitting on an
+/// edge of `interface`.
+///
+/// Fails, without provenance, when an instance carries the name but
+/// neither shows it's the middleware — a component of the application
+/// that merely shares the name can't be told apart from it, and the
+/// two couldn't coexist in the WAC under one name anyway.
+pub(crate) fn is_injected(
+    graph: &CompositionGraph,
+    provenance: Option<&Provenance>,
+    name: &str,
+    interface: &str,
+) -> anyhow::Result<bool> {
+    if let Some(provenance) = provenance {
+        return Ok(provenance
+            .middleware
+            .iter()
+            .any(|mw| mw.name == name && mw.interface == interface));
+    }
+    let mut named = graph
+        .nodes
+        .iter()
+        .filter(|(_, node)| is_instance_of(node.display_label(), name))
+        .peekable();
+    if named.peek().is_none() {
+        return Ok(false);
+    }
+    if named.clone().any(|(id, node)| {
+        is_adapter_of(node.display_label(), name) || sits_on(graph, *id, interface)
+    }) {
+        return Ok(true);
+    }
+    bail!(
+        "the composition already has an instance named '{name}', which doesn't sit on \
+         `{interface}`; without splicer provenance it can't be told apart from the \
+         middleware (rename the injection, or splice with --provenance to record it)"
+    )
+}
+
+/// Whether the instance `id` of `graph` forwards `interface`: it
+/// imports it, and another instance — or the composition — takes it
+/// from there.
+fn sits_on(graph: &CompositionGraph, id: u32, interface: &str) -> bool {
+    graph.nodes[&id]
+        .imports
+        .iter()
+        .any(|conn| conn.interface_name == interface)
+        && (graph.nodes.values().any(|node| {
+            node.imports.iter().any(|conn| {
+                conn.interface_name == interface
+                    && !conn.is_host_import
+                    && conn.source_instance == Some(id)
+            })
+        }) || graph
+            .component_exports
+            .get(interface)
+            .is_some_and(|export| export.source_instance == id))
+}
+
+/// Remove every instance of the middleware in `names` from `graph` —
+/// the middleware itself and any tier-1 adapters wrapping it —
+/// rewiring its consumers to its own upstream.
+///
+/// Fails when something outside the removed set consumes an interface
+/// a removed instance doesn't pass through (i.e. the instance provides
+/// it rather than forwarding it), since there's nothing to rewire that
+/// consumer to.
+pub(crate) fn strip(graph: &mut CompositionGraph, names: &BTreeSet<String>) -> anyhow::Result<()> {
+    let targets: BTreeSet<u32> = graph
+        .nodes
+        .iter()
+        .filter(|(_, node)| {
+            names
+                .iter()
+                .any(|name| is_instance_of(node.display_label(), name))
+        })
+        .map(|(id, _)| *id)
+        .collect();
+
+    for &removed_id in &targets {
+        let removed = graph.nodes.remove(&removed_id).expect("target node exists");
+        let upstream = |iface: &str| {
+            removed
+                .imports
+                .iter()
+                .find(|conn| conn.interface_name == iface)
+                .map(|conn| (conn.source_instance, conn.is_host_import))
+        };
+
+        for (consumer_id, consumer) in graph.nodes.iter_mut() {
+            let mut dropped = Vec::new();
+            let consumer_label = consumer.display_label().to_string();
+            for (i, conn) in consumer.imports.iter_mut().enumerate() {
+                if conn.is_host_import || conn.source_instance != Some(removed_id) {
+                    continue;
+                }
+                match upstream(&conn.interface_name) {
+                    Some((source_instance, is_host_import)) => {
+                        conn.source_instance = source_instance;
+                        conn.is_host_import = is_host_import;
+                    }
+                    // Another instance being removed (e.g. a tier-1
+                    // adapter importing hooks from its middleware):
+                    // the edge goes away with it.
+                    None if targets.contains(consumer_id) => dropped.push(i),
+                    None => bail!(
+                        "cannot remove '{}': '{}' imports `{}` from it, and it doesn't \
+                         import that interface itself to rewire to",
+                        removed.display_label(),
+                        consumer_label,
+                        conn.interface_name
+                    ),
+                }
+            }
+            for i in dropped.into_iter().rev() {
+                consumer.imports.remove(i);
+            }
+        }
+
+        for (export_name, export) in graph.component_exports.iter_mut() {
+            if export.source_instance != removed_id {
+                continue;
+            }
+            match upstream(export_name) {
+                Some((Some(source_instance), false)) => export.source_instance = source_instance,
+                _ => bail!(
+                    "cannot remove '{}': the composition exports `{export_name}` from it, \
+                     and it doesn't import that interface from another instance",
+                    removed.display_label()
+                ),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Drop the removed middleware from `provenance`: their injections,
+/// any rule left with nothing to inject, and their adapters. Returns
+/// `None` once nothing splicer injected remains.
+pub(crate) fn retain_provenance(
+    mut provenance: Provenance,
+    removed: &BTreeSet<String>,
+) -> Option<Provenance> {
+    provenance
+        .middleware
+        .retain(|mw| !removed.contains(&mw.name));
+    for rule in &mut provenance.rules {
+        if let Some(inject) = rule.inject_mut() {
+            inject.retain(|inj| !removed.contains(&inj.name));
+        }
+    }
+    provenance
+        .rules
+        .retain(|rule| rule.on_exports() || !rule.inject().is_empty());
+    provenance
+        .adapters
+        .retain(|adapter| !removed.contains(&adapter.middleware_name));
+    (!provenance.middleware.is_empty()).then_some(provenance)
+}
+
+/// Whether the instance labelled `label` is a tier-1 adapter splicer
+/// generated for middleware `name`.
+fn is_adapter_of(label: &str, name: &str) -> bool {
+    label
+        .strip_prefix(name)
+        .is_some_and(|rest| rest.starts_with(ADAPTER_INFIX))
+}
+
+/// Whether the instance labelled `label` belongs to middleware `name`:
+/// the middleware itself (at any of its sites), or a tier-1 adapter
+/// splicer generated for it.
+fn is_instance_of(label: &str, name: &str) -> bool {
+    label.strip_prefix(name).is_some_and(|rest| {
+        rest.is_empty()
+            || rest.starts_with(ADAPTER_INFIX)
+            || rest
+                .strip_prefix(SITE_INFIX)
+                .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cviz::model::{ComponentNode, InterfaceConnection};
+
+    const API: &str = "my:pkg/api@0.1.0";
+    const HOOKS: &str = "splicer:tier1/before@0.1.0";
+
+    /// Build a graph of named nodes (id = position) with the given
+    /// `(consumer, interface, source)` import edges; a `None` source is
+    /// a host import.
+    fn graph(names: &[&str], edges: &[(u32, &str, Option<u32>)]) -> CompositionGraph {
+        let mut graph = CompositionGraph::new();
+        for (i, name) in names.iter().enumerate() {
+            let mut node = ComponentNode::new(format!("${name}"), i as u32, i as u32);
+            for (consumer, iface, src) in edges {
+                if *consumer == i as u32 {
+                    node.add_import(InterfaceConnection {
+                        interface_name: iface.to_string(),
+                        source_instance: *src,
+                        is_host_import: src.is_none(),
+                        fingerprint: None,
+                        interface_type: None,
+                    });
+                }
+            }
+            graph.add_node(i as u32, node);
+        }
+        graph
+    }
+
+    fn source_of(graph: &CompositionGraph, node: u32, iface: &str) -> Option<u32> {
+        graph.nodes[&node]
+            .imports
+            .iter()
+            .find(|conn| conn.interface_name == iface)
+            .and_then(|conn| conn.source_instance)
+    }
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn strip_rewires_consumer_to_provider() {
+        let mut g = graph(
+            &["provider", "mw", "consumer"],
+            &[(1, API, Some(0)), (2, API, Some(1))],
+        );
+        strip(&mut g, &names(&["mw"])).unwrap();
+        assert!(!g.nodes.contains_key(&1));
+        assert_eq!(source_of(&g, 2, API), Some(0));
+    }
+
+    #[test]
+    fn strip_chained_middleware() {
+        let mut g = graph(
+            &["provider", "a", "b", "consumer"],
+            &[(1, API, Some(0)), (2, API, Some(1)), (3, API, Some(2))],
+        );
+        strip(&mut g, &names(&["a", "b"])).unwrap();
+        assert_eq!(g.nodes.len(), 2);
+        assert_eq!(source_of(&g, 3, API), Some(0));
+    }
+
+    /// A tier-1 middleware goes together with its adapter; the
+    /// adapter's hook import from the middleware is dropped rather
+    /// than rewired.
+    #[test]
+    fn strip_tier1_middleware_and_adapter() {
+        let adapter = format!("mw{ADAPTER_INFIX}my-pkg-api");
+        let mut g = graph(
+            &["provider", "mw", &adapter, "consumer"],
+            &[(2, API, Some(0)), (2, HOOKS, Some(1)), (3, API, Some(2))],
+        );
+        let selected = select(&g, None, &MiddlewareSelector::All).unwrap();
+        assert_eq!(selected, names(&["mw"]));
+        strip(&mut g, &selected).unwrap();
+        assert_eq!(g.nodes.len(), 2);
+        assert_eq!(source_of(&g, 3, API), Some(0));
+    }
+
+    #[test]
+    fn strip_every_site_of_per_site_middleware() {
+        let second = format!("mw{SITE_INFIX}2");
+        let mut g = graph(
+            &[
+                "provider-a",
+                "mw",
+                "provider-b",
+                &second,
+                "a",
+                "b",
+                "mw-cache",
+            ],
+            &[
+                (1, API, Some(0)),
+                (3, API, Some(2)),
+                (4, API, Some(1)),
+                (5, API, Some(3)),
+                (6, API, None),
+            ],
+        );
+        strip(&mut g, &names(&["mw"])).unwrap();
+        assert_eq!(g.nodes.len(), 5);
+        assert_eq!(source_of(&g, 4, API), Some(0));
+        assert_eq!(source_of(&g, 5, API), Some(2));
+    }
+
+    #[test]
+    fn strip_rejects_middleware_that_provides_the_interface() {
+        let mut g = graph(&["mw", "consumer"], &[(1, API, Some(0))]);
+        let err = strip(&mut g, &names(&["mw"])).unwrap_err();
+        assert!(err.to_string().contains("consumer"), "{err}");
+    }
+
+    #[test]
+    fn select_rejects_unknown_names() {
+        let g = graph(&["provider", "consumer"], &[(1, API, Some(0))]);
+        let err = select(&g, None, &MiddlewareSelector::Named(vec!["nope".into()])).unwrap_err();
+        assert!(err.to_string().contains("'nope'"), "{err}");
+        assert!(select(&g, None, &MiddlewareSelector::All).is_err());
+        assert!(select(&g, None, &MiddlewareSelector::Builtin("x".into())).is_err());
+    }
+
+    #[test]
+    fn is_injected_prefers_provenance() {
+        let adapter = format!("mw{ADAPTER_INFIX}my-pkg-api");
+        let g = graph(&["provider", &adapter], &[(1, API, Some(0))]);
+        assert!(is_injected(&g, None, "mw", API).unwrap());
+        assert!(!is_injected(&g, None, "other", API).unwrap());
+
+        let provenance = Provenance {
+            splicer_version: "0.0.0".into(),
+            input_hash: None,
+            rules: Vec::new(),
+            middleware: vec![crate::provenance::InjectedMiddleware {
+                name: "other".into(),
+                interface: API.into(),
+                builtin: None,
+                digest: None,
+            }],
+            adapters: Vec::new(),
+        };
+        assert!(is_injected(&g, Some(&provenance), "other", API).unwrap());
+        assert!(!is_injected(&g, Some(&provenance), "other", HOOKS).unwrap());
+        assert!(!is_injected(&g, Some(&provenance), "mw", API).unwrap());
+    }
+
+    /// Without provenance, a same-named instance only counts when it
+    /// sits on the rule's interface; one that doesn't is an error, not
+    /// a skip.
+    #[test]
+    fn is_injected_without_provenance_needs_the_edge() {
+        const LOG: &str = "wasi:logging/log@0.1.0";
+        // provider -> logger -> consumer on API; logger also imports LOG
+        // from the host.
+        let g = graph(
+            &["provider", "logger", "consumer"],
+            &[(1, API, Some(0)), (1, LOG, None), (2, API, Some(1))],
+        );
+        assert!(is_injected(&g, None, "logger", API).unwrap());
+        let err = is_injected(&g, None, "logger", LOG).unwrap_err();
+        assert!(err.to_string().contains("'logger'"), "{err}");
+    }
+}
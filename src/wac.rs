@@ -1,7 +1,14 @@
-use crate::adapter::generate_tier1_adapter;
-use crate::contract::{validate_contract, ContractResult};
+use crate::adapter::{build_tier1_adapter, tier1_adapter_path, AdapterCache};
+use crate::artifacts::Artifacts;
+use crate::contract::{validate_contract_in, ContractResult};
+use crate::exports::apply_export_rules;
+use crate::middleware_deps::wire_middleware_deps;
+use crate::replace::apply_replacements;
+use anyhow::Context;
 use colored::Colorize;
 use cviz::model::{ComponentNode, CompositionGraph, ExportInfo, InterfaceConnection};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::cmp::Reverse;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
@@ -9,9 +16,20 @@ use wasmparser::collections::IndexSet;
 
 /// Package prefix used for WAC instance variables (e.g. `"my:srv-a"`).
 pub const INST_PREFIX: &str = "my";
-const PATH_PLACEHOLDER: &str = "/path/to/comp.wasm";
-use crate::parse::config::{AdapterInjectionInfo, Injection, SpliceRule};
-use crate::split::gen_split_path;
+/// Path recorded in `wac_deps` for middleware injected without a `path:`.
+pub(crate) const PATH_PLACEHOLDER: &str = "/path/to/comp.wasm";
+/// Separates the middleware name from the target interface in a tier-1
+/// adapter's WAC instance name (`<middleware>-adapter-<interface>`).
+pub(crate) const ADAPTER_INFIX: &str = "-adapter-";
+/// Separates a middleware's (or adapter's) WAC instance name from the
+/// number of the site it serves, for every site after the first of a
+/// `per-site` injection (`<middleware>-site<n>`).
+pub(crate) const SITE_INFIX: &str = "-site";
+use crate::parse::config::{AdapterInjectionInfo, Injection, InstanceMode, SpliceRule};
+use crate::plan::{
+    CompositionPlan, InstanceOrigin, PlanItem, PlannedArg, PlannedExport, PlannedInstance,
+};
+use crate::split::SplitPaths;
 
 // chain_idx -> set of middlewares to inject AFTER
 type InjectPlan = HashMap<usize, IndexSet<Injection>>;
@@ -22,25 +40,52 @@ struct Chain {
     aliases: HashMap<u32, Option<String>>,
     // middlewares to inject after the specified index in the chain
     inject_plan: InjectPlan,
+    // (chain_idx, middleware name) -> index of the rule that injected it
+    inject_rules: HashMap<(usize, String), usize>,
 }
 
 impl Chain {
+    /// The middlewares to inject after chain position `chain_idx - 1`,
+    /// each with the rule that asked for it, in the order they're
+    /// instantiated: reversed from declaration order, so that calls
+    /// pass through them in the order the configuration lists them.
+    fn injections_at(&self, chain_idx: usize) -> Option<Vec<(Injection, usize)>> {
+        let middlewares = self.inject_plan.get(&chain_idx)?;
+        let mut planned: Vec<(Injection, usize)> = middlewares
+            .iter()
+            .map(|mdl| {
+                let rule = self.inject_rules[&(chain_idx, mdl.name.clone())];
+                (mdl.clone(), rule)
+            })
+            .collect();
+        planned.reverse();
+        Some(planned)
+    }
+
     /// Returns the split path of the component that consumes the handler at
     /// the given chain position.
     ///
     /// The consumer is the component that IMPORTS the handler interface —
     /// the adapter copies its import structure to get the right types.
-    /// At `chain_idx`, the consumer is `chain[chain_idx]`.
+    /// At `chain_idx`, the consumer is `chain[chain_idx]`. In
+    /// multi-component mode (`node_paths` is `Some`) the consumer's
+    /// original `.wasm` stands in for its split.
     fn consumer_split_path(
         &self,
         chain_idx: usize,
         composition: &CompositionGraph,
-        splits_path: &str,
+        splits: &SplitPaths,
         shim_comps: &HashMap<usize, usize>,
+        node_paths: Option<&HashMap<u32, PathBuf>>,
     ) -> Option<String> {
         let consumer_id = *self.chain.get(chain_idx)?;
+        if let Some(paths) = node_paths {
+            return paths
+                .get(&consumer_id)
+                .map(|p| p.to_string_lossy().into_owned());
+        }
         let split_to_use = resolved_split_num(consumer_id, composition, shim_comps);
-        Some(gen_split_path(splits_path, split_to_use))
+        Some(splits.path(split_to_use))
     }
 }
 
@@ -51,10 +96,12 @@ struct Contract {
 }
 
 /// One entry in [`WacOutput::generated_adapters`] — a tier-1 adapter
-/// component that splicer wrote to disk while resolving an injection.
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// component that splicer generated while resolving an injection.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct GeneratedAdapter {
-    /// Path on disk to the generated adapter `.wasm` file.
+    /// Path to the generated adapter `.wasm` file. For
+    /// [`crate::splice_bytes`] runs this is only a key into the
+    /// in-memory package map and never exists on disk.
     pub adapter_path: String,
     /// Name of the middleware injection the adapter wraps.
     pub middleware_name: String,
@@ -68,8 +115,12 @@ pub struct GeneratedAdapter {
 
 /// Output of [`generate_wac`].
 pub struct WacOutput {
-    /// The generated WAC source text.
+    /// The generated WAC source text — [`WacOutput::plan`] rendered.
     pub wac: String,
+    /// The composition the WAC describes, in typed form; builds a
+    /// `wac-graph` composition directly with
+    /// [`CompositionPlan::build_graph`].
+    pub plan: CompositionPlan,
     /// Per-dependency `(package_key → wasm path)` map ready to feed to
     /// `wac-resolver::FileSystemPackageResolver::new` as the `overrides`
     /// argument, or to format into a `wac compose ... --dep <key>=<path>`
@@ -99,16 +150,47 @@ pub fn generate_wac(
     rules: &[SpliceRule],
     node_paths: Option<&HashMap<u32, PathBuf>>,
     pkg_name: &str,
+) -> anyhow::Result<WacOutput> {
+    generate_wac_in(
+        shim_comps,
+        splits_path,
+        composition,
+        rules,
+        node_paths,
+        pkg_name,
+        &mut Artifacts::on_disk(),
+    )
+}
+
+/// [`generate_wac`] against an explicit artifact store: splits and
+/// middleware are read through `artifacts`, and generated tier-1
+/// adapters are written through it.
+pub(crate) fn generate_wac_in(
+    shim_comps: HashMap<usize, usize>,
+    splits_path: &str,
+    composition: &CompositionGraph,
+    rules: &[SpliceRule],
+    node_paths: Option<&HashMap<u32, PathBuf>>,
+    pkg_name: &str,
+    artifacts: &mut Artifacts,
 ) -> anyhow::Result<WacOutput> {
     // Emit the "shim split defaulting" WARN(s) exactly once up-front.
     // Without this, the notice would fire twice — once from the adapter
     // generator's `consumer_split_path` lookup, once from the wac-dep
     // map — because both paths call `resolve_shim` for the same shim.
     log_shim_resolutions(&shim_comps);
+    let split_paths = SplitPaths::load(splits_path, artifacts)?;
 
-    let mut wac_lines = vec![format!("package {pkg_name};")];
+    let mut plan = CompositionPlan::new(pkg_name);
 
     let mut handled_interfaces = HashSet::new();
+    // (interface, innermost provider) of every chain built so far: an
+    // interface gets one chain per instance providing it, so repeated
+    // instances of a component each keep their own wiring.
+    let mut handled_chains: HashSet<(String, u32)> = HashSet::new();
+    // (interface, provider, consumer) of the last edge of every chain
+    // skipped for sharing its innermost provider with one already built.
+    let mut skipped_edges: Vec<(String, u32, u32)> = Vec::new();
 
     let mut chains = vec![];
     let mut ordered_node_ids = composition.nodes.keys().collect::<Vec<_>>();
@@ -149,8 +231,9 @@ pub fn generate_wac(
                 break;
             }
 
-            if !handled_interfaces.contains(interface_name) && chain.len() > 1 {
-                chain.reverse();
+            chain.reverse();
+            let provider = chain[chain.len() - 2];
+            if handled_chains.insert((interface_name.to_string(), chain[0])) {
                 chains.push(Chain {
                     interface: Contract {
                         name: interface_name.to_string(),
@@ -159,11 +242,27 @@ pub fn generate_wac(
                     chain,
                     aliases: HashMap::new(),
                     inject_plan: HashMap::new(),
+                    inject_rules: HashMap::new(),
                 });
+            } else {
+                skipped_edges.push((interface_name.to_string(), provider, *outer_node_id));
             }
             handled_interfaces.insert(interface_name.to_string());
         }
     }
+    // A skipped chain's last edge is usually inside a longer chain; the
+    // ones that aren't can't have middleware injected on them alone.
+    let unchained_edges: Vec<(String, u32, u32)> = skipped_edges
+        .into_iter()
+        .filter(|(interface, provider, consumer)| {
+            !chains.iter().any(|c| {
+                c.interface.name == *interface
+                    && c.chain
+                        .windows(2)
+                        .any(|edge| edge == [*provider, *consumer])
+            })
+        })
+        .collect();
 
     // handle standalone exported interfaces!
     for (
@@ -188,6 +287,7 @@ pub fn generate_wac(
             chain: vec![*source_inst],
             aliases: HashMap::new(),
             inject_plan: HashMap::new(),
+            inject_rules: HashMap::new(),
         });
     }
 
@@ -197,28 +297,39 @@ pub fn generate_wac(
     // Apply the rules in order of their declaration in the configuration.
     // This enforces an ordering semantic for the rule application.
     let mut diagnostics: Vec<ContractResult> = vec![];
-    let mut generated_adapters: Vec<GeneratedAdapter> = vec![];
+    let mut adapter_jobs: Vec<AdapterJob> = vec![];
     for (rule_idx, rule) in rules.iter().enumerate() {
+        // Replacements and export rules apply to the finished plan,
+        // below.
+        if matches!(rule, SpliceRule::Replace { .. }) || rule.on_exports() {
+            continue;
+        }
         let mut any_interface_matched = false;
         let mut any_full_match = false;
         for chain in chains.iter_mut() {
             let between = apply_rule_between(
                 rule,
+                rule_idx,
                 chain,
                 composition,
-                splits_path,
+                &split_paths,
                 &shim_comps,
+                node_paths,
                 &mut checked_middlewares,
-                &mut generated_adapters,
+                &mut adapter_jobs,
+                artifacts,
             )?;
             let before = apply_rule_before(
                 rule,
+                rule_idx,
                 chain,
                 composition,
-                splits_path,
+                &split_paths,
                 &shim_comps,
+                node_paths,
                 &mut checked_middlewares,
-                &mut generated_adapters,
+                &mut adapter_jobs,
+                artifacts,
             )?;
             any_interface_matched |= between.interface_matched | before.interface_matched;
             any_full_match |= between.full_match | before.full_match;
@@ -226,7 +337,23 @@ pub fn generate_wac(
             diagnostics.extend(before.contract_results);
         }
         if !any_full_match {
-            let iface = rule_interface(rule);
+            // Export rules were skipped above, so every rule here has one.
+            let Some(iface) = rule.interface() else {
+                continue;
+            };
+            if let Some((_, provider, consumer)) = unchained_edges
+                .iter()
+                .find(|edge| selects_edge(rule, edge, composition))
+            {
+                let consumer = get_name(&composition.nodes[consumer]);
+                anyhow::bail!(
+                    "rule {}: `{consumer}` imports `{iface}` from `{}`, as another instance \
+                     does; splicer keeps one chain per provider of an interface, so \
+                     middleware can't be injected on `{consumer}`'s edge alone",
+                    rule_idx + 1,
+                    get_name(&composition.nodes[provider]),
+                );
+            }
             if !any_interface_matched {
                 // Interface name itself wasn't found — suggest close matches.
                 let available: Vec<&str> =
@@ -286,23 +413,29 @@ pub fn generate_wac(
         }
     }
 
+    // Every adapter the rules call for is known now; build them all at
+    // once before the WAC pass below reads their bytes.
+    let generated_adapters = build_adapters(adapter_jobs, splits_path, artifacts)?;
+
     // Let's now generate WAC to handle the chains we've planned to emit
     let mut mdl_override = None;
     let mut last = String::new();
     let mut instance_vars: HashMap<u32, String> = HashMap::new();
-    // resolved_split_num -> wac var (dedup across same-file nodes).
-    let mut split_to_var: HashMap<usize, String> = HashMap::new();
+    // node -> the node whose instance it shares (see `instance_classes`).
+    let classes = instance_classes(composition, &shim_comps);
+    // class representative -> wac var.
+    let mut class_to_var: HashMap<u32, String> = HashMap::new();
     // orig_inst_id -> generated_outer_var
     let mut outer_instances: HashMap<u32, String> = HashMap::new();
     // inst_id -> used_name
     let mut used_comp_nodes: HashMap<u32, String> = HashMap::new();
     // (used_name, path)
     let mut used_middlewares: Vec<(String, String)> = Vec::new();
-    // Real-middleware wac vars already emitted via `let mdl = new
-    // my:mdl { ... };`. Multiple rules can inject the same
-    // middleware (different target interfaces share the same wrapped
-    // hooks), so we emit the `let` once and reuse the var.
-    let mut emitted_mdl_vars: std::collections::HashSet<String> = std::collections::HashSet::new();
+    // injection name -> wac var of its `shared` instance. A shared
+    // middleware injected at several sites (e.g. several target
+    // interfaces wrapping the same tier-1 hooks) is emitted once and
+    // its var reused.
+    let mut shared_mdl_vars: HashMap<String, String> = HashMap::new();
 
     // Pre-instantiation pass for fan-in topologies.
     //
@@ -319,6 +452,15 @@ pub fn generate_wac(
     // would be instantiated in the first chain it appears in, hardwiring the raw
     // provider before later chains have a chance to inject middleware.
     let fan_in_consumers: HashSet<u32>;
+    // Collect aliases assigned to nodes by any rule so that nodes instantiated
+    // outside their own chain (pure providers here, factored-types providers on
+    // demand) use the same name that the chain pass would assign.
+    let mut pre_pass_aliases: HashMap<u32, Option<String>> = HashMap::new();
+    for chain in &chains {
+        for (&id, alias) in &chain.aliases {
+            pre_pass_aliases.insert(id, alias.clone());
+        }
+    }
     {
         let mut node_positions: HashMap<u32, BTreeSet<usize>> = HashMap::new();
         for chain in &chains {
@@ -349,15 +491,6 @@ pub fn generate_wac(
             .collect();
         pure_providers.sort(); // ascending = topological order for synthetic graphs
 
-        // Collect aliases assigned to pure-provider nodes by any rule so that nodes
-        // pre-instantiated here use the same name that the chain pass would assign.
-        let mut pre_pass_aliases: HashMap<u32, Option<String>> = HashMap::new();
-        for chain in &chains {
-            for (&id, alias) in &chain.aliases {
-                pre_pass_aliases.insert(id, alias.clone());
-            }
-        }
-
         for node_id in pure_providers {
             let node = &composition.nodes[&node_id];
             get_or_create_inst(
@@ -367,12 +500,13 @@ pub fn generate_wac(
                 &mut WacState {
                     instance_vars: &mut instance_vars,
                     used_comp_nodes: &mut used_comp_nodes,
-                    wac_lines: &mut wac_lines,
+                    plan: &mut plan,
                 },
                 &mut ShimDedup {
                     composition,
                     shim_comps: &shim_comps,
-                    split_to_var: &mut split_to_var,
+                    classes: &classes,
+                    class_to_var: &mut class_to_var,
                 },
                 &None,
             );
@@ -392,13 +526,13 @@ pub fn generate_wac(
     // consumer's export.
     let mut export_overrides: HashMap<(u32, String), String> = HashMap::new();
 
-    for Chain {
-        interface: chain_interface,
-        chain,
-        aliases,
-        inject_plan,
-    } in chains.iter()
-    {
+    for planned in chains.iter() {
+        let Chain {
+            interface: chain_interface,
+            chain,
+            aliases,
+            ..
+        } = planned;
         for (i, id) in chain.iter().enumerate() {
             let is_fan_in_last = fan_in_consumers.contains(id) && i == chain.len() - 1;
 
@@ -406,11 +540,11 @@ pub fn generate_wac(
             // defer to the post-fan-in pass so the consumer var
             // exists when we wire the middleware.
             if chain.len() == 1 && is_fan_in_last {
-                if let Some(middlewares) = inject_plan.get(&(i + 1)) {
+                if let Some(middlewares) = planned.injections_at(i + 1) {
                     deferred_top_level_injects.push(DeferredTopLevelInject {
                         consumer_id: *id,
                         chain_interface: chain_interface.clone(),
-                        middlewares: middlewares.clone(),
+                        middlewares,
                     });
                 }
                 continue;
@@ -425,12 +559,13 @@ pub fn generate_wac(
                     &mut WacState {
                         instance_vars: &mut instance_vars,
                         used_comp_nodes: &mut used_comp_nodes,
-                        wac_lines: &mut wac_lines,
+                        plan: &mut plan,
                     },
                     &mut ShimDedup {
                         composition,
                         shim_comps: &shim_comps,
-                        split_to_var: &mut split_to_var,
+                        classes: &classes,
+                        class_to_var: &mut class_to_var,
                     },
                     &mdl_override,
                 );
@@ -439,31 +574,48 @@ pub fn generate_wac(
                 mdl_override = Some((chain_interface.clone(), last.clone()));
             }
 
-            if let Some(middlewares) = inject_plan.get(&(i + 1)) {
+            if let Some(middlewares) = planned.injections_at(i + 1) {
                 // if the NEXT node has a middleware BEFORE it, inject here!
-                // Reverse the list of items to inject (this keeps me from having to deal with this in the `wac` generation logic).
+                // `injections_at` reverses the list of items to inject (this keeps me from having to deal with this in the `wac` generation logic).
                 // Through doing this, the order of middlewares invoked will follow the order of declaration in the configuration.
-                let reversed_list = reverse_set(middlewares);
-                for mdl in reversed_list.iter() {
+                for (mdl, rule_idx) in middlewares.iter() {
                     if let Some(adapter_info) = &mdl.adapter_info {
                         // instantiate the middleware+adapter in wac script
                         let (adapter_var, extra_args) = create_tier1_mdl(
                             &last,
                             mdl,
+                            *rule_idx,
                             chain_interface,
                             adapter_info,
-                            composition,
-                            &shim_comps,
-                            &mut wac_lines,
-                            &mut emitted_mdl_vars,
+                            &mut WacState {
+                                instance_vars: &mut instance_vars,
+                                used_comp_nodes: &mut used_comp_nodes,
+                                plan: &mut plan,
+                            },
+                            &mut ShimDedup {
+                                composition,
+                                shim_comps: &shim_comps,
+                                classes: &classes,
+                                class_to_var: &mut class_to_var,
+                            },
+                            &pre_pass_aliases,
+                            &mut shared_mdl_vars,
+                            artifacts,
                         )?;
                         last = adapter_var;
                         used_middlewares.extend(extra_args);
                     } else {
                         // instantiate the middleware in wac script
-                        last = create_mdl(&last, &mdl.name, chain_interface, &mut wac_lines);
+                        last = create_mdl(
+                            &last,
+                            mdl,
+                            *rule_idx,
+                            chain_interface,
+                            &mut plan,
+                            &mut shared_mdl_vars,
+                        )?;
                         used_middlewares.push((
-                            last.clone(),
+                            mdl.name.clone(),
                             mdl.path
                                 .as_ref()
                                 .cloned()
@@ -494,23 +646,36 @@ pub fn generate_wac(
     //
     // Now that every per-interface middleware has been created, we can instantiate
     // each fan-in consumer once with all of its imports wired correctly.
-    for (consumer_id, iface_vars) in fan_in_iface_vars.iter() {
+    let mut fan_in_ids: Vec<u32> = fan_in_iface_vars.keys().copied().collect();
+    fan_in_ids.sort();
+    for consumer_id in &fan_in_ids {
+        let iface_vars = &fan_in_iface_vars[consumer_id];
         let consumer_node = &composition.nodes[consumer_id];
-        let aliases = fan_in_aliases.get(consumer_id).unwrap();
-
-        let alias = aliases.get(consumer_id).cloned();
-        let pkg = if let Some(Some(a)) = alias {
-            a
-        } else {
-            sanitize_wac_id(get_name(consumer_node))
+        let claimed = claim_inst_var(
+            *consumer_id,
+            &fan_in_aliases[consumer_id],
+            consumer_node,
+            &mut WacState {
+                instance_vars: &mut instance_vars,
+                used_comp_nodes: &mut used_comp_nodes,
+                plan: &mut plan,
+            },
+            &mut ShimDedup {
+                composition,
+                shim_comps: &shim_comps,
+                classes: &classes,
+                class_to_var: &mut class_to_var,
+            },
+        );
+        let (node_var, pkg) = match claimed {
+            ClaimedVar::Existing(var) => {
+                outer_instances.insert(*consumer_id, var);
+                continue;
+            }
+            ClaimedVar::New { var, pkg } => (var, pkg),
         };
-        used_comp_nodes.insert(*consumer_id, pkg.clone());
-        let node_var = instance_vars
-            .entry(*consumer_id)
-            .or_insert_with(|| pkg.clone())
-            .clone();
 
-        let mut line = format!("let {node_var} = new {INST_PREFIX}:{pkg} {{");
+        let mut args = vec![];
         for conn in &consumer_node.imports {
             if !conn.is_host_import {
                 let iface = &conn.interface_name;
@@ -521,11 +686,20 @@ pub fn generate_wac(
                 } else {
                     continue;
                 };
-                line.push_str(&format!("\n    \"{iface}\": {src_var}[\"{iface}\"],"));
+                args.push(PlannedArg {
+                    name: iface.clone(),
+                    source: src_var,
+                });
             }
         }
-        line.push_str("\n    ...\n};");
-        wac_lines.push(line);
+        plan.items.push(PlanItem::Instance(PlannedInstance {
+            var: node_var.clone(),
+            package: format!("{INST_PREFIX}:{pkg}"),
+            args,
+            origin: InstanceOrigin::Component { node: *consumer_id },
+            // Filled in for every component at the end.
+            note: String::new(),
+        }));
 
         outer_instances.insert(*consumer_id, node_var.clone());
     }
@@ -540,29 +714,42 @@ pub fn generate_wac(
             );
         };
         let mut current_provider = consumer_var;
-        for mdl in reverse_set(&deferred.middlewares).iter() {
+        for (mdl, rule_idx) in deferred.middlewares.iter() {
             if let Some(adapter_info) = &mdl.adapter_info {
                 let (adapter_var, extra_args) = create_tier1_mdl(
                     &current_provider,
                     mdl,
+                    *rule_idx,
                     &deferred.chain_interface,
                     adapter_info,
-                    composition,
-                    &shim_comps,
-                    &mut wac_lines,
-                    &mut emitted_mdl_vars,
+                    &mut WacState {
+                        instance_vars: &mut instance_vars,
+                        used_comp_nodes: &mut used_comp_nodes,
+                        plan: &mut plan,
+                    },
+                    &mut ShimDedup {
+                        composition,
+                        shim_comps: &shim_comps,
+                        classes: &classes,
+                        class_to_var: &mut class_to_var,
+                    },
+                    &pre_pass_aliases,
+                    &mut shared_mdl_vars,
+                    artifacts,
                 )?;
                 current_provider = adapter_var;
                 used_middlewares.extend(extra_args);
             } else {
                 current_provider = create_mdl(
                     &current_provider,
-                    &mdl.name,
+                    mdl,
+                    *rule_idx,
                     &deferred.chain_interface,
-                    &mut wac_lines,
-                );
+                    &mut plan,
+                    &mut shared_mdl_vars,
+                )?;
                 used_middlewares.push((
-                    current_provider.clone(),
+                    mdl.name.clone(),
                     mdl.path
                         .as_ref()
                         .cloned()
@@ -627,25 +814,67 @@ pub fn generate_wac(
                 &mut WacState {
                     instance_vars: &mut instance_vars,
                     used_comp_nodes: &mut used_comp_nodes,
-                    wac_lines: &mut wac_lines,
+                    plan: &mut plan,
                 },
                 &mut ShimDedup {
                     composition,
                     shim_comps: &shim_comps,
-                    split_to_var: &mut split_to_var,
+                    classes: &classes,
+                    class_to_var: &mut class_to_var,
                 },
                 &None,
             )
         };
 
-        let export_line = format!("export {node_var}[\"{export_name}\"];");
-        wac_lines.push(export_line);
+        plan.items.push(PlanItem::Export(PlannedExport {
+            source: node_var,
+            name: export_name.clone(),
+            rename: None,
+        }));
+    }
+
+    diagnostics.extend(apply_replacements(
+        &mut plan,
+        rules,
+        composition,
+        &instance_vars,
+        &mut used_comp_nodes,
+        &mut used_middlewares,
+        &mut checked_middlewares,
+        artifacts,
+    )?);
+
+    // Wire what the middleware imports besides its target interface.
+    wire_middleware_deps(
+        &mut plan,
+        rules,
+        &interface_providers(composition, &instance_vars),
+        &mut used_middlewares,
+        artifacts,
+    )?;
+
+    apply_export_rules(&mut plan, rules, &instance_vars, &mut used_comp_nodes)?;
+
+    // A shared middleware's later sites, and middleware wired to its
+    // dependencies, may take imports from instances planned after it.
+    plan.order_by_wiring();
+
+    // Say where each component instance came from.
+    for item in plan.items.iter_mut() {
+        if let PlanItem::Instance(PlannedInstance {
+            origin: InstanceOrigin::Component { node },
+            note,
+            ..
+        }) = item
+        {
+            *note = component_note(*node, composition, &shim_comps, &split_paths, node_paths);
+        }
     }
 
     // Create the wac command arguments!
     let args = gen_wac_args(
         shim_comps,
-        splits_path,
+        &split_paths,
         composition,
         &used_comp_nodes,
         &used_middlewares,
@@ -653,20 +882,49 @@ pub fn generate_wac(
     );
 
     Ok(WacOutput {
-        wac: wac_lines.join("\n\n"),
+        wac: plan.to_wac(),
+        plan,
         wac_deps: args,
         diagnostics,
         generated_adapters,
     })
 }
 
+/// Interface -> vars of the composition's instances exporting it, to
+/// other instances or out of the composition.
+fn interface_providers(
+    composition: &CompositionGraph,
+    instance_vars: &HashMap<u32, String>,
+) -> BTreeMap<String, BTreeSet<String>> {
+    let imported = composition.nodes.values().flat_map(|node| {
+        node.imports
+            .iter()
+            .filter(|conn| !conn.is_host_import)
+            .filter_map(|conn| Some((&conn.interface_name, conn.source_instance?)))
+    });
+    let exported = composition
+        .component_exports
+        .iter()
+        .map(|(name, export)| (name, export.source_instance));
+    let mut providers: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for (name, node) in imported.chain(exported) {
+        if let Some(var) = instance_vars.get(&node) {
+            providers
+                .entry(name.clone())
+                .or_default()
+                .insert(var.clone());
+        }
+    }
+    providers
+}
+
 /// Build the dependency map: a `BTreeMap` keyed by the fully-qualified
 /// WAC package key (e.g. `"my:srv-a"`) so the result is directly
 /// consumable by `wac-resolver::FileSystemPackageResolver`. Sorted
 /// for deterministic shell-command formatting.
 fn gen_wac_args(
     shim_comps: HashMap<usize, usize>,
-    splits_path: &str,
+    splits: &SplitPaths,
     graph: &CompositionGraph,
     used_comps: &HashMap<u32, String>,
     used_mdls: &Vec<(String, String)>,
@@ -675,17 +933,7 @@ fn gen_wac_args(
     let mut deps: BTreeMap<String, PathBuf> = BTreeMap::new();
 
     for (inst_id, name) in used_comps.iter() {
-        let comp_path: PathBuf = if let Some(paths) = node_paths {
-            // Multi-component mode: use the original wasm path directly.
-            paths
-                .get(inst_id)
-                .cloned()
-                .unwrap_or_else(|| PathBuf::from(PATH_PLACEHOLDER))
-        } else {
-            // Single-component mode: derive path from the split directory.
-            let split_to_use = resolved_split_num(*inst_id, graph, &shim_comps);
-            PathBuf::from(gen_split_path(splits_path, split_to_use))
-        };
+        let comp_path = component_path(*inst_id, graph, &shim_comps, splits, node_paths);
         deps.insert(format!("{INST_PREFIX}:{name}"), comp_path);
     }
 
@@ -696,6 +944,58 @@ fn gen_wac_args(
 
     deps
 }
+
+/// The package a component instance is created from: its original
+/// `.wasm` in multi-component mode, its (shim-resolved) split otherwise.
+fn component_path(
+    inst_id: u32,
+    graph: &CompositionGraph,
+    shim_comps: &HashMap<usize, usize>,
+    splits: &SplitPaths,
+    node_paths: Option<&HashMap<u32, PathBuf>>,
+) -> PathBuf {
+    if let Some(paths) = node_paths {
+        // Multi-component mode: use the original wasm path directly.
+        paths
+            .get(&inst_id)
+            .cloned()
+            .unwrap_or_else(|| PathBuf::from(PATH_PLACEHOLDER))
+    } else {
+        // Single-component mode: derive path from the split directory.
+        let split_to_use = resolved_split_num(inst_id, graph, shim_comps);
+        PathBuf::from(splits.path(split_to_use))
+    }
+}
+
+/// [`PlannedInstance::note`] for a component instance: the node, the
+/// package it's created from, and the shim resolution and shared split
+/// file that led there.
+fn component_note(
+    inst_id: u32,
+    graph: &CompositionGraph,
+    shim_comps: &HashMap<usize, usize>,
+    splits: &SplitPaths,
+    node_paths: Option<&HashMap<u32, PathBuf>>,
+) -> String {
+    let path = component_path(inst_id, graph, shim_comps, splits, node_paths);
+    let mut note = format!(
+        "node {inst_id} `{}`, from {}",
+        get_name(&graph.nodes[&inst_id]),
+        path.display()
+    );
+    let split = node_split_num(inst_id, graph);
+    let resolved = resolve_shim(split, shim_comps);
+    if node_paths.is_none() && resolved != split {
+        note.push_str(&format!(" (shim split{split} resolved to split{resolved})"));
+    }
+    if let Some(stored) = splits.same_as(resolved).filter(|_| node_paths.is_none()) {
+        note.push_str(&format!(
+            " (split{resolved} has the same bytes as split{stored})"
+        ));
+    }
+    note
+}
+
 /// Pure: follow the shim chain until landing on a non-shim split.
 /// See [`log_shim_resolutions`] for the debug-level notice that
 /// fires once per non-trivial resolution at the top of [`generate_wac`].
@@ -708,7 +1008,7 @@ fn resolve_shim(mut component_num: usize, shim_comps: &HashMap<usize, usize>) ->
 
 /// Convert a graph node id to its split number (split0 is the root;
 /// nodes are offset by -1 in the split keyspace).
-fn node_split_num(node_id: u32, composition: &CompositionGraph) -> usize {
+pub(crate) fn node_split_num(node_id: u32, composition: &CompositionGraph) -> usize {
     (composition.nodes[&node_id].component_num + 1) as usize
 }
 
@@ -724,11 +1024,12 @@ fn resolved_split_num(
 
 /// Emit one debug-level note per non-trivial `shim → resolved` mapping in
 /// `shim_comps`. Called once at the start of [`generate_wac`] so the
-/// same assumption isn't announced twice when both the adapter-gen and
+/// same substitution isn't announced twice when both the adapter-gen and
 /// wac-dep paths later call [`resolve_shim`] for the same shim.
 ///
-/// Hidden behind `RUST_LOG` (default off) since the heuristic is reliable
-/// in the common case; opt in via `RUST_LOG=splicer=debug` to see it.
+/// Hidden behind `RUST_LOG` (default off); opt in via
+/// `RUST_LOG=splicer=debug` to see it. Why each split was taken for a
+/// shim is recorded in the splits dir's [`crate::split::SPLIT_MANIFEST`].
 fn log_shim_resolutions(shim_comps: &HashMap<usize, usize>) {
     let mut shim_keys: Vec<usize> = shim_comps.keys().copied().collect();
     shim_keys.sort();
@@ -736,9 +1037,8 @@ fn log_shim_resolutions(shim_comps: &HashMap<usize, usize>) {
         let resolved = resolve_shim(shim_num, shim_comps);
         if resolved != shim_num {
             tracing::debug!(
-                "Assumption made: split{shim_num} appears to be a shim component, \
-                 defaulting to split{resolved} in the generated wac command. \
-                 If this is incorrect, modify the generated wac command."
+                "split{shim_num} is a shim component; using split{resolved}, which \
+                 instantiates it, in the generated wac."
             );
         }
     }
@@ -749,7 +1049,8 @@ fn log_shim_resolutions(shim_comps: &HashMap<usize, usize>) {
 struct DeferredTopLevelInject {
     consumer_id: u32,
     chain_interface: Contract,
-    middlewares: IndexSet<Injection>,
+    /// As returned by [`Chain::injections_at`].
+    middlewares: Vec<(Injection, usize)>,
 }
 
 /// Return value from rule application functions.
@@ -767,12 +1068,15 @@ struct RuleApplyResult {
 #[allow(clippy::too_many_arguments)]
 fn apply_rule_between(
     rule: &SpliceRule,
+    rule_idx: usize,
     chain: &mut Chain,
     composition: &CompositionGraph,
-    splits_path: &str,
+    splits: &SplitPaths,
     shim_comps: &HashMap<usize, usize>,
+    node_paths: Option<&HashMap<u32, PathBuf>>,
     checked_middlewares: &mut HashMap<String, BTreeMap<String, ExportInfo>>,
-    generated_adapters: &mut Vec<GeneratedAdapter>,
+    adapter_jobs: &mut Vec<AdapterJob>,
+    artifacts: &mut Artifacts,
 ) -> anyhow::Result<RuleApplyResult> {
     let mut contract_results = vec![];
     let mut interface_matched = false;
@@ -805,7 +1109,7 @@ fn apply_rule_between(
                     (outer_id, outer_alias.clone()),
                 ];
                 let consumer_path =
-                    chain.consumer_split_path(i + 1, composition, splits_path, shim_comps);
+                    chain.consumer_split_path(i + 1, composition, splits, shim_comps, node_paths);
                 contract_results.extend(add_to_inject_plan(
                     interface,
                     inject,
@@ -813,11 +1117,13 @@ fn apply_rule_between(
                     &new_aliases,
                     &mut chain.aliases,
                     &mut chain.inject_plan,
+                    (rule_idx, &mut chain.inject_rules),
                     &chain.interface.ty_fingerprint,
-                    splits_path,
+                    splits.dir(),
                     consumer_path,
                     checked_middlewares,
-                    generated_adapters,
+                    adapter_jobs,
+                    artifacts,
                 )?);
             }
         }
@@ -832,12 +1138,15 @@ fn apply_rule_between(
 #[allow(clippy::too_many_arguments)]
 fn apply_rule_before(
     rule: &SpliceRule,
+    rule_idx: usize,
     chain: &mut Chain,
     composition: &CompositionGraph,
-    splits_path: &str,
+    splits: &SplitPaths,
     shim_comps: &HashMap<usize, usize>,
+    node_paths: Option<&HashMap<u32, PathBuf>>,
     checked_middlewares: &mut HashMap<String, BTreeMap<String, ExportInfo>>,
-    generated_adapters: &mut Vec<GeneratedAdapter>,
+    adapter_jobs: &mut Vec<AdapterJob>,
+    artifacts: &mut Artifacts,
 ) -> anyhow::Result<RuleApplyResult> {
     let mut contract_results = vec![];
     let mut interface_matched = false;
@@ -845,6 +1154,8 @@ fn apply_rule_before(
     if let SpliceRule::Before {
         interface,
         provider_name,
+        provider_instance,
+        consumer_name,
         provider_alias,
         inject,
     } = rule
@@ -854,12 +1165,31 @@ fn apply_rule_before(
                 continue;
             }
             interface_matched = true;
+            // The outermost node only provides the interface if it's
+            // exported; otherwise it's just the last consumer.
+            let provides = i + 1 < chain.chain.len()
+                || composition
+                    .component_exports
+                    .get(interface)
+                    .is_some_and(|export| export.source_instance == *id);
+            if !provides {
+                continue;
+            }
             let outer_node = &composition.nodes[id];
             if let Some(provider) = provider_name {
                 if get_name(outer_node) != *provider {
                     continue;
                 }
             }
+            if provider_instance.is_some_and(|instance| instance != *id) {
+                continue;
+            }
+            if let Some(consumer) = consumer_name {
+                let consumed_by = chain.chain.get(i + 1);
+                if consumed_by.is_none_or(|c| get_name(&composition.nodes[c]) != *consumer) {
+                    continue;
+                }
+            }
             full_match = true;
             let new_aliases = vec![(*id, provider_alias.clone())];
             // Prefer the consumer's split (i+1) so the adapter copies
@@ -868,8 +1198,10 @@ fn apply_rule_before(
             // split (i) — the adapter mirrors the provider's full
             // import topology.
             let consumer_path = chain
-                .consumer_split_path(i + 1, composition, splits_path, shim_comps)
-                .or_else(|| chain.consumer_split_path(i, composition, splits_path, shim_comps));
+                .consumer_split_path(i + 1, composition, splits, shim_comps, node_paths)
+                .or_else(|| {
+                    chain.consumer_split_path(i, composition, splits, shim_comps, node_paths)
+                });
             contract_results.extend(add_to_inject_plan(
                 interface,
                 inject,
@@ -877,11 +1209,13 @@ fn apply_rule_before(
                 &new_aliases,
                 &mut chain.aliases,
                 &mut chain.inject_plan,
+                (rule_idx, &mut chain.inject_rules),
                 &chain.interface.ty_fingerprint,
-                splits_path,
+                splits.dir(),
                 consumer_path,
                 checked_middlewares,
-                generated_adapters,
+                adapter_jobs,
+                artifacts,
             )?);
         }
     }
@@ -900,19 +1234,22 @@ fn add_to_inject_plan(
     new_aliases: &[(u32, Option<String>)],
     aliases: &mut HashMap<u32, Option<String>>,
     inject_plan: &mut InjectPlan,
+    (rule_idx, inject_rules): (usize, &mut HashMap<(usize, String), usize>),
     contract_fingerprint: &Option<String>,
     splits_path: &str,
     consumer_split: Option<String>,
     checked_middlewares: &mut HashMap<String, BTreeMap<String, ExportInfo>>,
-    generated_adapters: &mut Vec<GeneratedAdapter>,
+    adapter_jobs: &mut Vec<AdapterJob>,
+    artifacts: &mut Artifacts,
 ) -> anyhow::Result<Vec<ContractResult>> {
     // Check that the import/export contract is upheld by this plan and return results
     // to the caller — logging and error-handling is the caller's responsibility.
-    let contract_results = validate_contract(
+    let contract_results = validate_contract_in(
         to_inject,
         interface_name,
         contract_fingerprint,
         checked_middlewares,
+        artifacts,
     );
 
     // For tier-1 compatible middleware, generate a adapter component and substitute
@@ -937,24 +1274,26 @@ fn add_to_inject_plan(
                         injection.name
                     )
                 })?;
-                let adapter_path = generate_tier1_adapter(
-                    &injection.name,
-                    interface_name,
-                    &matched_interfaces,
-                    splits_path,
-                    consumer_split_path,
-                )?;
-                generated_adapters.push(GeneratedAdapter {
-                    adapter_path: adapter_path.clone(),
-                    middleware_name: injection.name.clone(),
-                    target_interface: interface_name.to_string(),
-                    tier1_interfaces: matched_interfaces.clone(),
+                // The adapter itself is built later, alongside every
+                // other one the rules call for (see `build_adapters`);
+                // its path is all the plan needs.
+                let adapter_path = tier1_adapter_path(splits_path, &injection.name, interface_name);
+                adapter_jobs.push(AdapterJob {
+                    adapter: GeneratedAdapter {
+                        adapter_path: adapter_path.clone(),
+                        middleware_name: injection.name.clone(),
+                        target_interface: interface_name.to_string(),
+                        tier1_interfaces: matched_interfaces.clone(),
+                    },
+                    consumer_split: consumer_split_path.to_string(),
                 });
                 resolved.push(Injection {
                     name: injection.name.clone(),
                     // Keep the original middleware path; adapter_path goes in adapter_info.
                     path: injection.path.clone(),
                     builtin: injection.builtin.clone(),
+                    instance: injection.instance,
+                    deps: injection.deps.clone(),
                     adapter_info: Some(AdapterInjectionInfo {
                         adapter_path,
                         tier1_interfaces: matched_interfaces,
@@ -969,6 +1308,11 @@ fn add_to_inject_plan(
         }
     }
 
+    for injection in &resolved {
+        inject_rules
+            .entry((chain_idx, injection.name.clone()))
+            .or_insert(rule_idx);
+    }
     let middlewares = inject_plan
         .entry(chain_idx)
         .or_insert(IndexSet::from_iter(resolved.iter().cloned()));
@@ -990,189 +1334,431 @@ fn add_to_inject_plan(
     Ok(final_results)
 }
 
+/// A tier-1 adapter the rules call for, queued by [`add_to_inject_plan`]
+/// and built by [`build_adapters`].
+struct AdapterJob {
+    adapter: GeneratedAdapter,
+    /// Split the adapter inherits its import preamble from.
+    consumer_split: String,
+}
+
+/// Build every queued adapter concurrently — each `(middleware,
+/// interface)` pair is independent — then write them through
+/// `artifacts`. Output is deterministic: the returned list follows the
+/// queue (i.e. rule) order, and the first failure in that order is the
+/// one reported.
+fn build_adapters(
+    jobs: Vec<AdapterJob>,
+    splits_path: &str,
+    artifacts: &mut Artifacts,
+) -> anyhow::Result<Vec<GeneratedAdapter>> {
+    // In-memory runs promise not to touch the disk.
+    let cache = (!artifacts.is_in_memory())
+        .then(AdapterCache::open)
+        .flatten();
+    let store: &Artifacts = artifacts;
+    let built: Vec<anyhow::Result<(String, Vec<u8>)>> = jobs
+        .par_iter()
+        .map(|job| {
+            let split_bytes = store
+                .read(&job.consumer_split)
+                .with_context(|| format!("Failed to read split at '{}'", job.consumer_split))?;
+            // Adapters on the same split share one decode of its WIT.
+            let split_wit = store.parsed().wit(&split_bytes)?;
+            build_tier1_adapter(
+                &job.adapter.middleware_name,
+                &job.adapter.target_interface,
+                &job.adapter.tier1_interfaces,
+                splits_path,
+                &split_wit.0,
+                cache.as_ref(),
+            )
+        })
+        .collect();
+    let files = built.into_iter().collect::<anyhow::Result<Vec<_>>>()?;
+    artifacts
+        .write_all(files)
+        .context("Failed to write adapter component")?;
+    Ok(jobs.into_iter().map(|job| job.adapter).collect())
+}
+
 /// Shim-resolution context (compose graph + shim map + dedup map).
 struct ShimDedup<'a> {
     composition: &'a CompositionGraph,
     shim_comps: &'a HashMap<usize, usize>,
-    /// resolved_split_num -> wac instance var.
-    split_to_var: &'a mut HashMap<usize, String>,
+    /// node -> representative of its instance class.
+    classes: &'a HashMap<u32, u32>,
+    /// class representative -> wac instance var.
+    class_to_var: &'a mut HashMap<u32, String>,
 }
 
 /// Mutable wac-builder state shared across instance creation.
 struct WacState<'a> {
     instance_vars: &'a mut HashMap<u32, String>,
     used_comp_nodes: &'a mut HashMap<u32, String>,
-    wac_lines: &'a mut Vec<String>,
+    plan: &'a mut CompositionPlan,
 }
 
-fn get_or_create_inst(
+/// The WAC var of a component instance, from [`claim_inst_var`].
+enum ClaimedVar {
+    /// The instance, or another of its instance class, already has
+    /// this var.
+    Existing(String),
+    /// A fresh var; the caller plans the instance of package `pkg`.
+    New { var: String, pkg: String },
+}
+
+/// Find or name the var of instance `inst_id`. A fresh var is named
+/// after the instance's alias in `aliases`, or else its component,
+/// suffixed (`-2`, `-3`, ...) when another instance already has it.
+fn claim_inst_var(
     inst_id: u32,
     aliases: &HashMap<u32, Option<String>>,
     node: &ComponentNode,
     state: &mut WacState,
     dedup: &mut ShimDedup,
-    with_override: &Option<(Contract, String)>,
-) -> String {
+) -> ClaimedVar {
     if let Some(var) = state.instance_vars.get(&inst_id) {
-        return var.clone();
+        return ClaimedVar::Existing(var.clone());
     }
-    // Dedup nodes that resolve to the same split file: separate `new`
-    // invocations would create independent runtime instances with
-    // diverged resource type identities.
-    let resolved_split = resolved_split_num(inst_id, dedup.composition, dedup.shim_comps);
-    if let Some(existing_var) = dedup.split_to_var.get(&resolved_split) {
+    // Dedup nodes that share an instance class (a shim and its outer,
+    // or the same split wired identically): separate `new` invocations
+    // would create independent runtime instances with diverged
+    // resource type identities. Splits with the same bytes only share
+    // a file, not a class.
+    let class = dedup.classes.get(&inst_id).copied().unwrap_or(inst_id);
+    if let Some(existing_var) = dedup.class_to_var.get(&class) {
         state.instance_vars.insert(inst_id, existing_var.clone());
-        return existing_var.clone();
+        return ClaimedVar::Existing(existing_var.clone());
     }
 
-    let alias = aliases.get(&inst_id).cloned();
-
-    // it hasn't been instantiated yet! do so here
-    let pkg = if let Some(Some(alias)) = alias {
-        alias.clone()
-    } else {
-        sanitize_wac_id(get_name(node))
+    let pkg = match aliases.get(&inst_id) {
+        Some(Some(alias)) => alias.clone(),
+        _ => sanitize_wac_id(get_name(node)),
     };
     state.used_comp_nodes.insert(inst_id, pkg.clone());
-    let node_var = state
+    // Distinct instances of one component can carry the same name.
+    let mut var = pkg.clone();
+    let mut n = 2;
+    while state
         .instance_vars
-        .entry(inst_id)
-        .or_insert_with(|| pkg.clone())
-        .clone();
-    dedup.split_to_var.insert(resolved_split, node_var.clone());
-
-    let mut line = format!("let {node_var} = new {INST_PREFIX}:{pkg} {{");
-    for conn in &node.imports {
-        if !conn.is_host_import {
-            let src_id = conn.source_instance;
-            if let Some((
-                Contract {
-                    name: override_interface,
-                    ..
-                },
-                override_var,
-            )) = &with_override
-            {
-                let src_var = if conn.interface_name == *override_interface {
-                    override_var.clone()
-                } else if let Some(src_var) = state.instance_vars.get(&src_id.unwrap()) {
-                    // could be an import from the host!
-                    // only do this if it's not
-                    src_var.clone()
-                } else {
-                    continue;
-                };
-                line.push_str(&format!(
-                    "\n    \"{iface}\": {src}[\"{iface}\"],",
-                    iface = conn.interface_name,
-                    src = src_var
-                ));
+        .values()
+        .any(|existing| *existing == var)
+    {
+        var = sanitize_wac_id(&format!("{pkg}-{n}"));
+        n += 1;
+    }
+    state.instance_vars.insert(inst_id, var.clone());
+    dedup.class_to_var.insert(class, var.clone());
+    ClaimedVar::New { var, pkg }
+}
+
+fn get_or_create_inst(
+    inst_id: u32,
+    aliases: &HashMap<u32, Option<String>>,
+    node: &ComponentNode,
+    state: &mut WacState,
+    dedup: &mut ShimDedup,
+    with_override: &Option<(Contract, String)>,
+) -> String {
+    let (node_var, pkg) = match claim_inst_var(inst_id, aliases, node, state, dedup) {
+        ClaimedVar::Existing(var) => return var,
+        ClaimedVar::New { var, pkg } => (var, pkg),
+    };
+
+    let mut args = vec![];
+    for conn in node.imports.iter().filter(|conn| !conn.is_host_import) {
+        let src_var = match with_override {
+            Some((Contract { name, .. }, override_var)) if conn.interface_name == *name => {
+                override_var.clone()
             }
-        }
+            _ => match conn
+                .source_instance
+                .and_then(|id| state.instance_vars.get(&id))
+            {
+                Some(src_var) => src_var.clone(),
+                None => continue,
+            },
+        };
+        args.push(PlannedArg {
+            name: conn.interface_name.clone(),
+            source: src_var,
+        });
     }
-    line.push_str("\n    ...\n};");
-    state.wac_lines.push(line);
+    state.plan.items.push(PlanItem::Instance(PlannedInstance {
+        var: node_var.clone(),
+        package: format!("{INST_PREFIX}:{pkg}"),
+        args,
+        origin: InstanceOrigin::Component { node: inst_id },
+        // Filled in for every component at the end.
+        note: String::new(),
+    }));
 
     node_var
 }
 
+/// Instantiate the provider `inst_id` outside its chain, after (and
+/// wired to) whatever it imports from, unless it already exists.
+fn get_or_create_provider(
+    inst_id: u32,
+    aliases: &HashMap<u32, Option<String>>,
+    state: &mut WacState,
+    dedup: &mut ShimDedup,
+) -> String {
+    let composition = dedup.composition;
+    let node = &composition.nodes[&inst_id];
+    if !state.instance_vars.contains_key(&inst_id) {
+        for conn in node.imports.iter().filter(|conn| !conn.is_host_import) {
+            if let Some(src_id) = conn.source_instance {
+                get_or_create_provider(src_id, aliases, state, dedup);
+            }
+        }
+    }
+    get_or_create_inst(inst_id, aliases, node, state, dedup, &None)
+}
+
+/// Emit WAC for a middleware injection wrapping `input_inst`: a new
+/// instance per site, or for a `shared` middleware, its one instance
+/// with this site's interface wired in too.
 fn create_mdl(
-    input_inst: &String,
-    mw: &String,
+    input_inst: &str,
+    mdl: &Injection,
+    rule_idx: usize,
     interface: &Contract,
-    wac_lines: &mut Vec<String>,
-) -> String {
-    let mw_line = format!(
-        "let {mw} = new {INST_PREFIX}:{mw} {{\n    \"{interface}\": {input_inst}[\"{interface}\"], ...\n}};",
-        interface = interface.name,
-    );
-    wac_lines.push(mw_line);
+    plan: &mut CompositionPlan,
+    shared_vars: &mut HashMap<String, String>,
+) -> anyhow::Result<String> {
+    let mw = &mdl.name;
+    let arg = PlannedArg {
+        name: interface.name.clone(),
+        source: input_inst.to_string(),
+    };
+    let shared = mdl.instance_mode() == InstanceMode::Shared;
+    if let Some(var) = shared_vars.get(mw).filter(|_| shared) {
+        let instance = plan
+            .items
+            .iter_mut()
+            .find_map(|item| match item {
+                PlanItem::Instance(inst) if inst.var == *var => Some(inst),
+                _ => None,
+            })
+            .expect("a shared middleware's instance is planned with its var");
+        match instance.args.iter().find(|wired| wired.name == arg.name) {
+            Some(wired) if wired.source != arg.source => anyhow::bail!(
+                "rule {}: `{mw}` is shared, but it already wraps `{}` from `{}`, not `{}`; \
+                 a shared middleware takes each interface from one provider (use \
+                 `instance: per-site` to give each site its own)",
+                rule_idx + 1,
+                arg.name,
+                wired.source,
+                arg.source
+            ),
+            Some(_) => {}
+            None => instance.args.push(arg),
+        }
+        return Ok(var.clone());
+    }
+
+    let var = site_var(plan, mw);
+    if shared {
+        shared_vars.insert(mw.clone(), var.clone());
+    }
+    plan.items.push(PlanItem::Instance(PlannedInstance {
+        var: var.clone(),
+        package: format!("{INST_PREFIX}:{mw}"),
+        args: vec![arg],
+        origin: InstanceOrigin::Middleware { rule: rule_idx },
+        note: format!(
+            "rule {}: inject `{mw}` on `{}`, from {}",
+            rule_idx + 1,
+            interface.name,
+            injection_source(mdl)
+        ),
+    }));
+
+    Ok(var)
+}
+
+/// A WAC var for another site of `base`: `base` itself the first time,
+/// then `base-site2`, `base-site3`, ...
+fn site_var(plan: &CompositionPlan, base: &str) -> String {
+    let taken = |var: &str| {
+        plan.items
+            .iter()
+            .any(|item| matches!(item, PlanItem::Instance(inst) if inst.var == var))
+    };
+    if !taken(base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| format!("{base}{SITE_INFIX}{n}"))
+        .find(|var| !taken(var))
+        .expect("some site suffix is free")
+}
 
-    mw.clone()
+/// Where an injection's package comes from, for instance notes.
+fn injection_source(mdl: &Injection) -> String {
+    match (&mdl.builtin, &mdl.path) {
+        (Some(builtin), _) => format!("builtin `{builtin}`"),
+        (None, Some(path)) => path.clone(),
+        (None, None) => "a package resolved by name".to_string(),
+    }
 }
 
 /// Emit WAC for a tier-1 adapter injection: two instances — the real middleware
 /// (host-imports only) and the generated adapter wrapper that wires both.
 ///
+/// Resource-bearing types interfaces the adapter imports are wired from
+/// whichever component provides them (see [`factored_types_to_wire`]),
+/// instantiating it through `state` if it hasn't been yet.
+///
 /// Returns `(adapter_var_name, [(pkg_name, path), ...])` where the vec has two
 /// entries: one for the real middleware and one for the adapter component.
 #[allow(clippy::too_many_arguments)]
 fn create_tier1_mdl(
     downstream_inst: &str,
     mdl: &Injection,
+    rule_idx: usize,
     interface: &Contract,
     adapter_info: &AdapterInjectionInfo,
-    composition: &CompositionGraph,
-    shim_comps: &HashMap<usize, usize>,
-    wac_lines: &mut Vec<String>,
-    emitted_mdl_vars: &mut std::collections::HashSet<String>,
+    state: &mut WacState,
+    dedup: &mut ShimDedup,
+    aliases: &HashMap<u32, Option<String>>,
+    shared_vars: &mut HashMap<String, String>,
+    artifacts: &Artifacts,
 ) -> anyhow::Result<(String, Vec<(String, String)>)> {
-    let real_var = mdl.name.clone();
     // The adapter's core-wasm signature is specialized per target
     // interface, so a single middleware injected on multiple rules
     // must produce distinct adapter packages — one per interface —
     // or the generated wac's `deps` map collides under one pkg name
     // and only the last-generated adapter wasm reaches wac compose.
-    let adapter_var = format!("{}-adapter-{}", mdl.name, sanitize_wac_id(&interface.name));
-
-    // Real middleware — only has host imports, so no explicit wiring needed.
-    // Emit the `let` once per mdl.name; adapters on later rules reuse
-    // the same var.
-    if emitted_mdl_vars.insert(real_var.clone()) {
-        wac_lines.push(format!(
-            "let {real_var} = new {INST_PREFIX}:{real_var} {{ ... }};"
-        ));
-    }
+    let adapter_pkg = format!(
+        "{}{ADAPTER_INFIX}{}",
+        mdl.name,
+        sanitize_wac_id(&interface.name)
+    );
+    // Every site gets its own adapter, even on the same interface.
+    let adapter_var = site_var(state.plan, &adapter_pkg);
+
+    // Real middleware — its target interface reaches it through the
+    // adapter; `wire_middleware_deps` wires whatever else it imports.
+    // A shared one is emitted once; adapters at later sites reuse its var.
+    let shared = mdl.instance_mode() == InstanceMode::Shared;
+    let real_var = match shared_vars.get(&mdl.name).filter(|_| shared) {
+        Some(var) => var.clone(),
+        None => {
+            let real_var = site_var(state.plan, &mdl.name);
+            if shared {
+                shared_vars.insert(mdl.name.clone(), real_var.clone());
+            }
+            state.plan.items.push(PlanItem::Instance(PlannedInstance {
+                var: real_var.clone(),
+                package: format!("{INST_PREFIX}:{}", mdl.name),
+                args: vec![],
+                origin: InstanceOrigin::Middleware { rule: rule_idx },
+                note: format!(
+                    "rule {}: inject tier-1 middleware `{real_var}`, from {}",
+                    rule_idx + 1,
+                    injection_source(mdl)
+                ),
+            }));
+            real_var
+        }
+    };
 
     // Proxy — wires the downstream target interface and the tier-1 hook interfaces
     // from the real middleware instance. The adapter's hook imports are versioned,
     // so the WAC lines use the versioned names to match both sides.
     use crate::contract::{versioned_interface, TIER1_VERSION};
-    let mut adapter_line = format!(
-        "let {adapter_var} = new {INST_PREFIX}:{adapter_var} {{\n    \"{iface}\": {downstream_inst}[\"{iface}\"],",
-        iface = interface.name,
-    );
+    let mut args = vec![PlannedArg {
+        name: interface.name.clone(),
+        source: downstream_inst.to_string(),
+    }];
     for tier1_iface in &adapter_info.tier1_interfaces {
-        let versioned = versioned_interface(tier1_iface, TIER1_VERSION);
-        adapter_line.push_str(&format!(
-            "\n    \"{versioned}\": {real_var}[\"{versioned}\"],"
-        ));
+        args.push(PlannedArg {
+            name: versioned_interface(tier1_iface, TIER1_VERSION),
+            source: real_var.clone(),
+        });
     }
     // Wire resource-bearing factored-types imports (e.g. `my:shape/types`)
     // explicitly — `...` doesn't unify resource type identity across
     // separately-imported instances from a non-host component.
-    if let Ok(adapter_bytes) = std::fs::read(&adapter_info.adapter_path) {
-        for extra in factored_types_to_wire(
-            &resource_bearing_imports(&adapter_bytes),
+    if let Ok(adapter_bytes) = artifacts.read(&adapter_info.adapter_path) {
+        for (extra, types_source) in factored_types_to_wire(
+            &resource_bearing_imports(artifacts, &adapter_bytes),
             &interface.name,
-            composition,
-            shim_comps,
+            dedup.composition,
+            dedup.shim_comps,
         )? {
-            adapter_line.push_str(&format!(
-                "\n    \"{extra}\": {downstream_inst}[\"{extra}\"],"
-            ));
+            let source = match types_source {
+                TypesSource::Downstream => downstream_inst.to_string(),
+                // Usually a pure provider, instantiated up front.
+                TypesSource::Node(id) => get_or_create_provider(id, aliases, state, dedup),
+            };
+            args.push(PlannedArg {
+                name: extra,
+                source,
+            });
         }
     }
-    adapter_line.push_str("\n    ...\n};");
-    wac_lines.push(adapter_line);
+    state.plan.items.push(PlanItem::Instance(PlannedInstance {
+        var: adapter_var.clone(),
+        package: format!("{INST_PREFIX}:{adapter_pkg}"),
+        args,
+        origin: InstanceOrigin::Adapter { rule: rule_idx },
+        note: format!(
+            "rule {}: adapter fronting `{real_var}` on `{}`, generated at {}",
+            rule_idx + 1,
+            interface.name,
+            adapter_info.adapter_path
+        ),
+    }));
 
     let used = vec![
         (
-            real_var,
+            mdl.name.clone(),
             mdl.path
                 .as_ref()
                 .cloned()
                 .unwrap_or(PATH_PLACEHOLDER.to_string()),
         ),
-        (adapter_var.clone(), adapter_info.adapter_path.clone()),
+        (adapter_pkg, adapter_info.adapter_path.clone()),
     ];
     Ok((adapter_var, used))
 }
 
-fn rule_interface(rule: &SpliceRule) -> &str {
+/// Whether `rule` selects the consumer edge `(interface, provider,
+/// consumer)` by the consumer's name.
+fn selects_edge(
+    rule: &SpliceRule,
+    (interface, provider, consumer): &(String, u32, u32),
+    composition: &CompositionGraph,
+) -> bool {
+    let name = |id: &u32| get_name(&composition.nodes[id]);
     match rule {
-        SpliceRule::Before { interface, .. } => interface,
-        SpliceRule::Between { interface, .. } => interface,
+        SpliceRule::Before {
+            interface: rule_interface,
+            provider_name,
+            provider_instance,
+            consumer_name: Some(consumer_name),
+            ..
+        } => {
+            rule_interface == interface
+                && provider_name.as_ref().is_none_or(|p| p == name(provider))
+                && provider_instance.is_none_or(|i| i == *provider)
+                && consumer_name == name(consumer)
+        }
+        SpliceRule::Between {
+            interface: rule_interface,
+            inner_name,
+            outer_name,
+            ..
+        } => {
+            rule_interface == interface
+                && inner_name == name(provider)
+                && outer_name == name(consumer)
+        }
+        _ => false,
     }
 }
 
@@ -1181,6 +1767,17 @@ fn get_name(node: &ComponentNode) -> &str {
     node.display_label()
 }
 
+/// Where an adapter's factored-types import is wired from.
+#[derive(Debug, PartialEq)]
+enum TypesSource {
+    /// The adapter's downstream, which provides the target interface
+    /// and, through the same split, this one.
+    Downstream,
+    /// The instance of this node: the component exporting the types
+    /// interface when it isn't the target's provider.
+    Node(u32),
+}
+
 /// Decide which adapter imports to wire as factored-types, given the
 /// adapter's resource-bearing imports, the splice target, and the
 /// composition graph. Three cases per import:
@@ -1191,29 +1788,32 @@ fn get_name(node: &ComponentNode) -> &str {
 ///      sources from a node that resolves to the same split as the
 ///      target's provider): wire from the downstream.
 ///   3. **Different provider than the target**: multi-provider
-///      factored types. Bail — the downstream doesn't actually export
-///      this interface, and wiring from a sibling provider needs
-///      plumbing this code path doesn't yet have.
+///      factored types. Wire from the one component providing the
+///      types interface, so the adapter shares its resources with
+///      every other consumer. Bail when several components provide
+///      it — there's no telling whose resources the target's
+///      functions use.
 fn factored_types_to_wire(
     resource_imports: &[String],
     target_iface: &str,
     composition: &CompositionGraph,
     shim_comps: &HashMap<usize, usize>,
-) -> anyhow::Result<Vec<String>> {
-    // Resolved-shim source split numbers that provide `iface`:
+) -> anyhow::Result<Vec<(String, TypesSource)>> {
+    // Resolved-shim source split numbers that provide `iface`, each
+    // with a (shim-resolved) node of that split:
     //   - Top-level component exports (when `iface` is a leaf export
     //     of the composition, e.g. when splicing a lone provider).
     //   - Plus every non-host import edge sourcing `iface` (when
     //     `iface` is consumed internally, e.g. consumer → provider).
-    // Empty set = host-provided.
-    let providers = |iface: &str| -> std::collections::HashSet<usize> {
-        let mut out = std::collections::HashSet::new();
+    // Empty map = host-provided.
+    let providers = |iface: &str| -> BTreeMap<usize, u32> {
+        let mut out = BTreeMap::new();
+        let mut add = |node: u32| {
+            out.entry(resolved_split_num(node, composition, shim_comps))
+                .or_insert_with(|| resolve_shim_node(node, composition, shim_comps));
+        };
         if let Some(info) = composition.component_exports.get(iface) {
-            out.insert(resolved_split_num(
-                info.source_instance,
-                composition,
-                shim_comps,
-            ));
+            add(info.source_instance);
         }
         for node in composition.nodes.values() {
             for conn in &node.imports {
@@ -1221,7 +1821,7 @@ fn factored_types_to_wire(
                     continue;
                 }
                 if let Some(src) = conn.source_instance {
-                    out.insert(resolved_split_num(src, composition, shim_comps));
+                    add(src);
                 }
             }
         }
@@ -1237,16 +1837,32 @@ fn factored_types_to_wire(
         if extra_providers.is_empty() {
             continue; // host-provided
         }
-        if extra_providers != target_providers {
-            anyhow::bail!(
-                "splicer can't yet wire factored-types interface `{extra}` for adapter \
-                 on `{target_iface}`: the resource-bearing types interface is exported \
-                 by a different component than the target. Splicer's tier-1 wiring \
-                 currently assumes both interfaces come from the same provider. \
-                 Workaround: have one component export both interfaces."
-            );
+        if extra_providers.keys().eq(target_providers.keys()) {
+            out.push((extra.clone(), TypesSource::Downstream));
+            continue;
+        }
+        let mut nodes = extra_providers.values();
+        match (nodes.next(), nodes.next()) {
+            (Some(&node), None) => out.push((extra.clone(), TypesSource::Node(node))),
+            _ => {
+                let names: Vec<String> = extra_providers
+                    .values()
+                    .map(|id| {
+                        composition
+                            .nodes
+                            .get(id)
+                            .map_or_else(|| id.to_string(), |node| get_name(node).to_string())
+                    })
+                    .collect();
+                anyhow::bail!(
+                    "splicer can't wire factored-types interface `{extra}` for adapter \
+                     on `{target_iface}`: it's exported by more than one component \
+                     ({}), so there's no telling whose resources `{target_iface}` \
+                     uses.",
+                    names.join(", ")
+                );
+            }
         }
-        out.push(extra.clone());
     }
     Ok(out)
 }
@@ -1254,16 +1870,12 @@ fn factored_types_to_wire(
 /// Qualified names of the component's interface imports whose
 /// instance type contains at least one resource. Best-effort: empty
 /// on decode errors.
-fn resource_bearing_imports(bytes: &[u8]) -> Vec<String> {
-    let Ok(decoded) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-        wit_component::decode(bytes)
-    })) else {
-        return Vec::new();
-    };
-    let Ok(wit_component::DecodedWasm::Component(resolve, world_id)) = decoded else {
+fn resource_bearing_imports(artifacts: &Artifacts, bytes: &[u8]) -> Vec<String> {
+    let Ok(decoded) = artifacts.parsed().wit(bytes) else {
         return Vec::new();
     };
-    let world = &resolve.worlds[world_id];
+    let (resolve, world_id) = &*decoded;
+    let world = &resolve.worlds[*world_id];
     let mut result = Vec::new();
     for (_key, item) in &world.imports {
         let wit_parser::WorldItem::Interface { id, .. } = item else {
@@ -1292,6 +1904,71 @@ fn is_shim_split_num(split_num: usize, shim_comps: &HashMap<usize, usize>) -> bo
     shim_comps.contains_key(&split_num)
 }
 
+/// Group the composition's nodes into instance classes, mapping each
+/// node to the representative whose WAC instance it shares.
+///
+/// A shim shares its resolved outer's instance (or, when the outer
+/// isn't a node itself, that of every other shim resolving to it).
+/// Otherwise two nodes share one only when they're created from the
+/// same split and every import is wired from the same class (or the
+/// host), so a component instantiated several times keeps one instance
+/// per distinct wiring.
+fn instance_classes(
+    composition: &CompositionGraph,
+    shim_comps: &HashMap<usize, usize>,
+) -> HashMap<u32, u32> {
+    /// `None` for a shim: its wiring doesn't set it apart.
+    type Wiring = Option<Vec<(String, Option<u32>)>>;
+
+    fn class_of(
+        id: u32,
+        composition: &CompositionGraph,
+        shim_comps: &HashMap<usize, usize>,
+        classes: &mut HashMap<u32, u32>,
+        reps: &mut HashMap<(usize, Wiring), u32>,
+    ) -> u32 {
+        if let Some(class) = classes.get(&id) {
+            return *class;
+        }
+        let Some(node) = composition.nodes.get(&id) else {
+            return id;
+        };
+        // Provisional, so a shim wired into its own outer terminates.
+        classes.insert(id, id);
+        let split = node_split_num(id, composition);
+        let resolved = resolve_shim(split, shim_comps);
+        let outer = resolve_shim_node(id, composition, shim_comps);
+        let class = if outer != id {
+            class_of(outer, composition, shim_comps, classes, reps)
+        } else if resolved != split {
+            *reps.entry((resolved, None)).or_insert(id)
+        } else {
+            let mut wiring: Vec<_> = node
+                .imports
+                .iter()
+                .map(|conn| {
+                    let source = conn
+                        .source_instance
+                        .filter(|_| !conn.is_host_import)
+                        .map(|src| class_of(src, composition, shim_comps, classes, reps));
+                    (conn.interface_name.clone(), source)
+                })
+                .collect();
+            wiring.sort();
+            *reps.entry((split, Some(wiring))).or_insert(id)
+        };
+        classes.insert(id, class);
+        class
+    }
+
+    let mut classes = HashMap::new();
+    let mut reps = HashMap::new();
+    for id in composition.nodes.keys() {
+        class_of(*id, composition, shim_comps, &mut classes, &mut reps);
+    }
+    classes
+}
+
 /// If `inst_id` is a shim node, return its resolved-outer node id.
 fn resolve_shim_node(
     inst_id: u32,
@@ -1339,14 +2016,6 @@ fn sanitize_wac_id(raw: &str) -> String {
         .join("-")
 }
 
-fn reverse_set(set: &IndexSet<Injection>) -> Vec<Injection> {
-    let mut res = vec![];
-    for item in set.iter() {
-        res.insert(0, item.clone());
-    }
-    res
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1376,6 +2045,86 @@ mod tests {
         graph
     }
 
+    /// Adapters are built concurrently but come back in queue order,
+    /// each written through the store.
+    #[test]
+    fn build_adapters_preserves_queue_order() {
+        let split = wat::parse_str(
+            r#"(component
+                (import "my:pkg/api" (instance (export "get" (func (result u32)))))
+            )"#,
+        )
+        .unwrap();
+        let mut artifacts = Artifacts::in_memory();
+        artifacts.write("splits/split1.wasm", split).unwrap();
+        let job = |name: &str| AdapterJob {
+            adapter: GeneratedAdapter {
+                adapter_path: tier1_adapter_path("splits", name, "my:pkg/api"),
+                middleware_name: name.to_string(),
+                target_interface: "my:pkg/api".to_string(),
+                tier1_interfaces: vec!["splicer:tier1/before".to_string()],
+            },
+            consumer_split: "splits/split1.wasm".to_string(),
+        };
+
+        let names = ["zeta", "alpha", "mid"];
+        let built = build_adapters(names.map(job).into(), "splits", &mut artifacts).unwrap();
+        let built_names: Vec<_> = built.iter().map(|a| a.middleware_name.as_str()).collect();
+        assert_eq!(built_names, names);
+        for adapter in &built {
+            assert!(artifacts
+                .read(&adapter.adapter_path)
+                .unwrap()
+                .starts_with(b"\0asm"));
+        }
+    }
+
+    /// A component note names the node and its package, and the shim
+    /// resolution that picked the package.
+    #[test]
+    fn component_note_names_node_split_and_shim() {
+        let graph = synth_graph(3, &[]);
+        // node 1 is split2, a shim that resolves to split3 (node 2).
+        let shim_comps = HashMap::from([(2, 3)]);
+        let splits = SplitPaths::new("splits", &[]);
+        assert_eq!(
+            component_note(0, &graph, &shim_comps, &splits, None),
+            "node 0 `node-0`, from splits/split1.wasm"
+        );
+        assert_eq!(
+            component_note(1, &graph, &shim_comps, &splits, None),
+            "node 1 `node-1`, from splits/split3.wasm (shim split2 resolved to split3)"
+        );
+        let paths = HashMap::from([(0, PathBuf::from("in/a.wasm"))]);
+        assert_eq!(
+            component_note(0, &graph, &HashMap::new(), &splits, Some(&paths)),
+            "node 0 `node-0`, from in/a.wasm"
+        );
+    }
+
+    /// Instances of one component share a class only when wired
+    /// alike; a shim shares its outer's.
+    #[test]
+    fn instance_classes_split_repeated_components_by_wiring() {
+        let mut graph = synth_graph(
+            6,
+            &[
+                (2, "my:app/config", Some(0), false),
+                (3, "my:app/config", Some(0), false),
+                (4, "my:app/config", Some(1), false),
+            ],
+        );
+        // Nodes 2-4 instantiate the same component.
+        for id in [3, 4] {
+            graph.nodes.get_mut(&id).unwrap().component_num = 2;
+        }
+        // node 5 is split6, a shim that resolves to split2 (node 1).
+        let classes = instance_classes(&graph, &HashMap::from([(6, 2)]));
+        assert_eq!(classes[&3], 2);
+        assert_eq!(classes[&4], 4);
+        assert_eq!(classes[&5], 1);
+    }
+
     /// Single-provider factored types: a single provider node is the
     /// non-host source of both api and types. Wire types from
     /// downstream.
@@ -1397,7 +2146,10 @@ mod tests {
             &HashMap::new(),
         )
         .expect("same-provider factored types should wire");
-        assert_eq!(extras, vec!["my:shape/types@1.0.0".to_string()]);
+        assert_eq!(
+            extras,
+            vec![("my:shape/types@1.0.0".to_string(), TypesSource::Downstream)]
+        );
     }
 
     /// Host-provided types: every import edge is `is_host_import`. The
@@ -1423,11 +2175,12 @@ mod tests {
         assert!(extras.is_empty());
     }
 
-    /// Pathological multi-provider case: api is provided by node 1,
-    /// types is provided by node 0. Adapter on api can't safely wire
-    /// types from its downstream (node 1), so splicer bails.
+    /// Multi-provider factored types: api is provided by node 1,
+    /// types by node 0. The adapter's downstream (node 1) doesn't
+    /// export types, so it's wired from node 0 — the instance every
+    /// other consumer of types shares.
     #[test]
-    fn factored_types_multi_provider_bails() {
+    fn factored_types_multi_provider_wires_from_types_provider() {
         // node 2 = consumer. consumer imports api from node 1
         // (api-provider), types from node 0 (types-provider).
         let graph = synth_graph(
@@ -1437,21 +2190,186 @@ mod tests {
                 (2, "my:shape/types@1.0.0", Some(0), false),
             ],
         );
+        let extras = factored_types_to_wire(
+            &["my:shape/types@1.0.0".to_string()],
+            "my:shape/api@1.0.0",
+            &graph,
+            &HashMap::new(),
+        )
+        .expect("multi-provider factored types should wire");
+        assert_eq!(
+            extras,
+            vec![("my:shape/types@1.0.0".to_string(), TypesSource::Node(0))]
+        );
+    }
+
+    /// Plan a tier-1 adapter on `my:shape/api@1.0.0` fronting
+    /// `node-v1` in `graph`, whose adapter also imports
+    /// `my:shape/types@1.0.0`. Returns the plan, the instance vars it
+    /// created, and the adapter's args.
+    fn plan_types_adapter(
+        graph: &CompositionGraph,
+        aliases: &HashMap<u32, Option<String>>,
+    ) -> (CompositionPlan, HashMap<u32, String>, Vec<(String, String)>) {
+        let adapter = wat::parse_str(
+            r#"(component
+                (import "my:shape/types@1.0.0" (instance
+                    (export "shape" (type (sub resource)))
+                ))
+                (import "my:shape/api@1.0.0" (instance
+                    (export "get" (func (result u32)))
+                ))
+            )"#,
+        )
+        .unwrap();
+        let mut artifacts = Artifacts::in_memory();
+        artifacts.write("splits/adapter.wasm", adapter).unwrap();
+        let adapter_info = AdapterInjectionInfo {
+            adapter_path: "splits/adapter.wasm".into(),
+            tier1_interfaces: vec![],
+        };
+        let mdl = Injection {
+            name: "mw".into(),
+            path: Some("mw.wasm".into()),
+            builtin: None,
+            instance: None,
+            deps: Vec::new(),
+            adapter_info: Some(adapter_info.clone()),
+        };
+
+        let mut plan = CompositionPlan::new("test:pkg");
+        let (mut instance_vars, mut used_comp_nodes) = (HashMap::new(), HashMap::new());
+        let (adapter_var, _) = create_tier1_mdl(
+            "node-v1",
+            &mdl,
+            0,
+            &Contract {
+                name: "my:shape/api@1.0.0".into(),
+                ty_fingerprint: None,
+            },
+            &adapter_info,
+            &mut WacState {
+                instance_vars: &mut instance_vars,
+                used_comp_nodes: &mut used_comp_nodes,
+                plan: &mut plan,
+            },
+            &mut ShimDedup {
+                composition: graph,
+                shim_comps: &HashMap::new(),
+                classes: &HashMap::new(),
+                class_to_var: &mut HashMap::new(),
+            },
+            aliases,
+            &mut HashMap::new(),
+            &artifacts,
+        )
+        .unwrap();
+        let args = planned_args(&plan, &adapter_var);
+        (plan, instance_vars, args)
+    }
+
+    /// The `(name, source)` args of the planned instance `var`.
+    fn planned_args(plan: &CompositionPlan, var: &str) -> Vec<(String, String)> {
+        plan.items
+            .iter()
+            .find_map(|item| match item {
+                PlanItem::Instance(inst) if inst.var == var => Some(inst),
+                _ => None,
+            })
+            .unwrap()
+            .args
+            .iter()
+            .map(|arg| (arg.name.clone(), arg.source.clone()))
+            .collect()
+    }
+
+    /// An adapter on `api` whose downstream doesn't export `types` is
+    /// wired to the component that does, instantiated on demand.
+    #[test]
+    fn tier1_adapter_wires_types_from_their_provider() {
+        let graph = synth_graph(
+            3,
+            &[
+                (2, "my:shape/api@1.0.0", Some(1), false),
+                (2, "my:shape/types@1.0.0", Some(0), false),
+            ],
+        );
+        let (_, instance_vars, args) = plan_types_adapter(&graph, &HashMap::new());
+
+        assert_eq!(instance_vars[&0], "node-v0");
+        assert_eq!(
+            args,
+            [
+                ("my:shape/api@1.0.0".to_string(), "node-v1".to_string()),
+                ("my:shape/types@1.0.0".to_string(), "node-v0".to_string()),
+            ]
+        );
+    }
+
+    /// A types provider instantiated on demand keeps the alias its
+    /// rule gave it, and its own imports are wired to their provider,
+    /// instantiated first, rather than left to the composition.
+    #[test]
+    fn on_demand_types_provider_wires_its_own_imports() {
+        // node 0 (types provider) imports base from node 3.
+        let graph = synth_graph(
+            4,
+            &[
+                (2, "my:shape/api@1.0.0", Some(1), false),
+                (2, "my:shape/types@1.0.0", Some(0), false),
+                (0, "my:shape/base@1.0.0", Some(3), false),
+            ],
+        );
+        let aliases = HashMap::from([(0, Some("shapes".to_string()))]);
+        let (plan, instance_vars, args) = plan_types_adapter(&graph, &aliases);
+
+        assert_eq!(instance_vars[&0], "shapes");
+        assert!(args.contains(&("my:shape/types@1.0.0".to_string(), "shapes".to_string())));
+        assert_eq!(
+            planned_args(&plan, "shapes"),
+            [("my:shape/base@1.0.0".to_string(), instance_vars[&3].clone())]
+        );
+        let order: Vec<&str> = plan
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                PlanItem::Instance(inst) => Some(inst.var.as_str()),
+                _ => None,
+            })
+            .collect();
+        let pos = |var: &str| order.iter().position(|v| *v == var).unwrap();
+        assert!(pos(&instance_vars[&3]) < pos("shapes"), "{order:?}");
+    }
+
+    /// Two components both provide types: whose resources the target
+    /// uses is ambiguous, so splicer bails naming both.
+    #[test]
+    fn factored_types_ambiguous_provider_bails() {
+        // node 3 = consumer of api (node 2) and types (node 1); node 2
+        // imports types from node 0.
+        let graph = synth_graph(
+            4,
+            &[
+                (3, "my:shape/api@1.0.0", Some(2), false),
+                (3, "my:shape/types@1.0.0", Some(1), false),
+                (2, "my:shape/types@1.0.0", Some(0), false),
+            ],
+        );
         let err = factored_types_to_wire(
             &["my:shape/types@1.0.0".to_string()],
             "my:shape/api@1.0.0",
             &graph,
             &HashMap::new(),
         )
-        .expect_err("multi-provider factored types should bail");
+        .expect_err("ambiguous factored types should bail");
         let msg = err.to_string();
         assert!(
             msg.contains("my:shape/types@1.0.0"),
             "error should name the offending interface; got: {msg}"
         );
         assert!(
-            msg.contains("different component"),
-            "error should explain the multi-provider problem; got: {msg}"
+            msg.contains("more than one component (node-0, node-1)"),
+            "error should name the providers; got: {msg}"
         );
     }
 }
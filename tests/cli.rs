@@ -40,6 +40,17 @@ const WAT_CONSUMER: &str = r#"(component
     (export "my:consumer/app@0.1.0" (instance $out))
 )"#;
 
+/// Pass-through middleware for `compose --rules`: imports and
+/// re-exports the provider interface unchanged.
+const WAT_MIDDLEWARE: &str = r#"(component
+    (import "my:providers/a@0.1.0" (instance $a
+        (export "get" (func (result u32)))
+    ))
+    (alias export $a "get" (func $f))
+    (instance $out (export "get" (func $f)))
+    (export "my:providers/a@0.1.0" (instance $out))
+)"#;
+
 /// Compile both WATs to wasm, write them next to each other in `dir`,
 /// and return their paths (`provider.wasm`, `consumer.wasm`).
 fn write_compose_components(dir: &Path) -> (PathBuf, PathBuf) {
@@ -204,6 +215,491 @@ fn compose_output_path_override() {
     assert!(!dir.path().join("composed.wasm").exists());
 }
 
+/// `--rules` splices middleware in while composing: the emitted WAC
+/// instantiates the middleware and the composed bytes validate.
+#[test]
+fn compose_with_rules_injects_middleware() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = write_compose_components(dir.path());
+    std::fs::write(
+        dir.path().join("mw.wasm"),
+        wat::parse_str(WAT_MIDDLEWARE).expect("compile middleware"),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("splice.yaml"),
+        r#"version: 1
+rules:
+  - between:
+      interface: my:providers/a@0.1.0
+      inner:
+        name: provider
+      outer:
+        name: consumer
+    inject:
+      - name: mw
+        path: ./mw.wasm
+"#,
+    )
+    .unwrap();
+
+    let out = splicer_in(dir.path())
+        .arg("compose")
+        .arg(&a)
+        .arg(&b)
+        .args(["--rules", "splice.yaml", "--emit-wac"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "splicer compose --rules failed: stderr={}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let wac = std::fs::read_to_string(dir.path().join("output.wac")).unwrap();
+    assert!(wac.contains("let mw = new"), "wac:\n{wac}");
+    assert_valid_wasm(&std::fs::read(dir.path().join("composed.wasm")).unwrap());
+}
+
+/// `--annotate-wac` comments the emitted WAC with the rule behind
+/// each middleware and the node behind each component, without
+/// changing what gets composed.
+#[test]
+fn compose_annotate_wac_names_rules_and_nodes() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = write_compose_components(dir.path());
+    std::fs::write(
+        dir.path().join("mw.wasm"),
+        wat::parse_str(WAT_MIDDLEWARE).expect("compile middleware"),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("splice.yaml"),
+        r#"version: 1
+rules:
+  - between:
+      interface: my:providers/a@0.1.0
+      inner:
+        name: provider
+      outer:
+        name: consumer
+    inject:
+      - name: mw
+        path: ./mw.wasm
+"#,
+    )
+    .unwrap();
+
+    let out = splicer_in(dir.path())
+        .arg("compose")
+        .arg(&a)
+        .arg(&b)
+        .args(["--rules", "splice.yaml", "--emit-wac", "--annotate-wac"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "splicer compose --annotate-wac failed: stderr={}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let wac = std::fs::read_to_string(dir.path().join("output.wac")).unwrap();
+    assert!(
+        wac.starts_with(&format!(
+            "// Generated by splicer {}\n// Input: ",
+            env!("CARGO_PKG_VERSION")
+        )),
+        "wac:\n{wac}"
+    );
+    assert!(
+        wac.contains(
+            "// rule 1: inject `mw` on `my:providers/a@0.1.0`, from ./mw.wasm\nlet mw = new"
+        ),
+        "wac:\n{wac}"
+    );
+    assert!(wac.contains("`provider`, from "), "wac:\n{wac}");
+    assert_valid_wasm(&std::fs::read(dir.path().join("composed.wasm")).unwrap());
+}
+
+/// Middleware whose `get` returns the wrong type: past the contract
+/// check with `--skip-type-check`, it fails at compose time.
+const WAT_MISTYPED_MIDDLEWARE: &str = r#"(component
+    (import "my:providers/a@0.1.0" (instance $a
+        (export "get" (func (result u64)))
+    ))
+    (alias export $a "get" (func $f))
+    (instance $out (export "get" (func $f)))
+    (export "my:providers/a@0.1.0" (instance $out))
+)"#;
+
+/// The WAC preserved when compose fails is the one `--annotate-wac`
+/// asked for, comments included.
+#[test]
+fn compose_failure_preserves_the_annotated_wac() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = write_compose_components(dir.path());
+    std::fs::write(
+        dir.path().join("mw.wasm"),
+        wat::parse_str(WAT_MISTYPED_MIDDLEWARE).expect("compile middleware"),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("splice.yaml"),
+        r#"version: 1
+rules:
+  - between:
+      interface: my:providers/a@0.1.0
+      inner:
+        name: provider
+      outer:
+        name: consumer
+    inject:
+      - name: mw
+        path: ./mw.wasm
+"#,
+    )
+    .unwrap();
+
+    let out = splicer_in(dir.path())
+        .arg("compose")
+        .arg(&a)
+        .arg(&b)
+        .args([
+            "--rules",
+            "splice.yaml",
+            "--annotate-wac",
+            "--skip-type-check",
+        ])
+        .output()
+        .unwrap();
+    assert!(!out.status.success(), "mistyped middleware should fail");
+
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    let wac_path = stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("WAC preserved at: "))
+        .unwrap_or_else(|| panic!("error should name the persisted WAC: {stderr}"));
+    let wac = std::fs::read_to_string(wac_path).unwrap();
+    assert!(
+        wac.contains("// rule 1: inject `mw` on `my:providers/a@0.1.0`"),
+        "wac:\n{wac}"
+    );
+    let _ = std::fs::remove_dir_all(Path::new(wac_path).parent().unwrap());
+}
+
+/// `--strict-wiring` lists every import in the WAC: the provider's
+/// host import becomes a top-level `import`, nothing is left to `...`,
+/// and the composed component imports exactly that.
+#[test]
+fn compose_strict_wiring_imports_explicitly() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = write_compose_components(dir.path());
+    std::fs::write(
+        dir.path().join("mw.wasm"),
+        wat::parse_str(WAT_MIDDLEWARE).expect("compile middleware"),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("splice.yaml"),
+        r#"version: 1
+rules:
+  - between:
+      interface: my:providers/a@0.1.0
+      inner:
+        name: provider
+      outer:
+        name: consumer
+    inject:
+      - name: mw
+        path: ./mw.wasm
+"#,
+    )
+    .unwrap();
+
+    let out = splicer_in(dir.path())
+        .arg("compose")
+        .arg(&a)
+        .arg(&b)
+        .args(["--rules", "splice.yaml", "--emit-wac", "--strict-wiring"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "splicer compose --strict-wiring failed: stderr={}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let wac = std::fs::read_to_string(dir.path().join("output.wac")).unwrap();
+    assert!(
+        wac.contains("import host-env-dep: host:env/dep@0.1.0;"),
+        "wac:\n{wac}"
+    );
+    assert!(
+        wac.contains("\"host:env/dep@0.1.0\": host-env-dep,"),
+        "wac:\n{wac}"
+    );
+    assert!(!wac.contains("..."), "wac:\n{wac}");
+
+    let bytes = std::fs::read(dir.path().join("composed.wasm")).unwrap();
+    assert_valid_wasm(&bytes);
+    assert_eq!(component_imports(&bytes), ["host:env/dep@0.1.0"]);
+}
+
+/// Names of a component's top-level imports.
+fn component_imports(bytes: &[u8]) -> Vec<String> {
+    let mut imports = Vec::new();
+    let mut depth = 0;
+    for payload in wasmparser::Parser::new(0).parse_all(bytes) {
+        match payload.unwrap() {
+            wasmparser::Payload::ModuleSection { .. }
+            | wasmparser::Payload::ComponentSection { .. } => depth += 1,
+            wasmparser::Payload::End(_) => depth -= 1,
+            wasmparser::Payload::ComponentImportSection(reader) if depth == 0 => {
+                for import in reader {
+                    imports.push(import.unwrap().name.0.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    imports
+}
+
+/// Middleware injected without a `path:` is looked up under
+/// `--deps-dir` when composing.
+#[test]
+fn compose_finds_pathless_middleware_in_deps_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = write_compose_components(dir.path());
+    std::fs::create_dir_all(dir.path().join("deps/my")).unwrap();
+    std::fs::write(
+        dir.path().join("deps/my/mw.wasm"),
+        wat::parse_str(WAT_MIDDLEWARE).expect("compile middleware"),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("splice.yaml"),
+        r#"version: 1
+rules:
+  - between:
+      interface: my:providers/a@0.1.0
+      inner:
+        name: provider
+      outer:
+        name: consumer
+    inject:
+      - name: mw
+"#,
+    )
+    .unwrap();
+
+    let compose = |deps_dir: bool| {
+        let mut cmd = splicer_in(dir.path());
+        cmd.arg("compose")
+            .arg(&a)
+            .arg(&b)
+            .args(["--rules", "splice.yaml"]);
+        if deps_dir {
+            cmd.args(["--deps-dir", "deps"]);
+        }
+        cmd.output().unwrap()
+    };
+
+    let out = compose(false);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("my:mw"), "stderr={stderr}");
+
+    let out = compose(true);
+    assert!(
+        out.status.success(),
+        "splicer compose --deps-dir failed: stderr={}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_valid_wasm(&std::fs::read(dir.path().join("composed.wasm")).unwrap());
+}
+
+/// `--provenance` embeds a record that `inspect` decodes; a
+/// component without one makes `inspect` fail.
+#[test]
+fn inspect_reads_embedded_provenance() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = write_compose_components(dir.path());
+    std::fs::write(
+        dir.path().join("mw.wasm"),
+        wat::parse_str(WAT_MIDDLEWARE).expect("compile middleware"),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("splice.yaml"),
+        r#"version: 1
+rules:
+  - between:
+      interface: my:providers/a@0.1.0
+      inner:
+        name: provider
+      outer:
+        name: consumer
+    inject:
+      - name: mw
+        path: ./mw.wasm
+"#,
+    )
+    .unwrap();
+
+    let out = splicer_in(dir.path())
+        .arg("compose")
+        .arg(&a)
+        .arg(&b)
+        .args(["--rules", "splice.yaml", "--provenance"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "splicer compose --provenance failed: stderr={}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let out = splicer_in(dir.path())
+        .args(["inspect", "composed.wasm", "--json"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let provenance: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(provenance["middleware"][0]["name"], "mw");
+    assert!(provenance["middleware"][0]["digest"]
+        .as_str()
+        .unwrap()
+        .starts_with("sha256:"));
+
+    let out = splicer_in(dir.path())
+        .arg("inspect")
+        .arg(&a)
+        .output()
+        .unwrap();
+    assert!(!out.status.success(), "plain component has no provenance");
+}
+
+// ── Unsplice subcommand ────────────────────────────────────────────────────
+
+/// `unsplice --all` strips the middleware a provenance-carrying
+/// composition records, leaving a component with no provenance left.
+#[test]
+fn unsplice_removes_recorded_middleware() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = write_compose_components(dir.path());
+    std::fs::write(
+        dir.path().join("mw.wasm"),
+        wat::parse_str(WAT_MIDDLEWARE).expect("compile middleware"),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("splice.yaml"),
+        r#"version: 1
+rules:
+  - between:
+      interface: my:providers/a@0.1.0
+      inner:
+        name: provider
+      outer:
+        name: consumer
+    inject:
+      - name: mw
+        path: ./mw.wasm
+"#,
+    )
+    .unwrap();
+
+    let out = splicer_in(dir.path())
+        .arg("compose")
+        .arg(&a)
+        .arg(&b)
+        .args(["--rules", "splice.yaml", "--provenance"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+
+    let out = splicer_in(dir.path())
+        .args([
+            "unsplice",
+            "composed.wasm",
+            "--all",
+            "-o",
+            "unspliced.wasm",
+            "--emit-wac",
+        ])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "splicer unsplice failed: stderr={}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let wac = std::fs::read_to_string(dir.path().join("output.wac")).unwrap();
+    assert!(
+        !wac.contains("let mw ="),
+        "middleware still instantiated:\n{wac}"
+    );
+
+    let out = splicer_in(dir.path())
+        .args(["inspect", "unspliced.wasm"])
+        .output()
+        .unwrap();
+    assert!(!out.status.success(), "provenance should be gone");
+
+    // A selector is required.
+    let out = splicer_in(dir.path())
+        .args(["unsplice", "composed.wasm"])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+}
+
+// ── Apply subcommand ───────────────────────────────────────────────────────
+
+/// `--emit-manifest` saves the bundle; `apply` composes it later, and
+/// refuses once a referenced package has changed.
+#[test]
+fn apply_composes_saved_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = write_compose_components(dir.path());
+
+    let out = splicer_in(dir.path())
+        .arg("compose")
+        .arg(&a)
+        .arg(&b)
+        .args(["--plan", "--emit-manifest"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "splicer compose --emit-manifest failed: stderr={}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(dir.path().join("bundle.json").exists());
+    assert!(!dir.path().join("composed.wasm").exists());
+
+    let out = splicer_in(dir.path())
+        .args(["apply", "bundle.json", "-o", "applied.wasm"])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "splicer apply failed: stderr={}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_valid_wasm(&std::fs::read(dir.path().join("applied.wasm")).unwrap());
+
+    std::fs::write(&a, wat::parse_str(WAT_MIDDLEWARE).unwrap()).unwrap();
+    let out = splicer_in(dir.path())
+        .args(["apply", "bundle.json"])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("stale package"), "stderr: {stderr}");
+}
+
 // ── Splice subcommand: failure path ────────────────────────────────────────
 //
 // We don't ship a hand-rolled splice-able fixture in the repo (a
@@ -25,7 +25,7 @@
 
 use anyhow::Context;
 use arbitrary::Arbitrary;
-use splicer::{compose, splice, ComponentInput, ComposeRequest, SpliceRequest};
+use splicer::{compose, parse_yaml, splice, ComponentInput, ComposeRequest, SpliceRequest};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -2270,21 +2270,19 @@ const ALL_PIPELINE_KINDS: &[PipelineKind] = &[PipelineKind::Between, PipelineKin
 fn compose_provider_consumer(root: &Path, provider_comp: &Path, consumer_comp: &Path) -> PathBuf {
     let compose_wac = root.join("compose.wac");
     let composed_path = root.join("composed.wasm");
-    let out = compose(ComposeRequest {
-        components: vec![
-            ComponentInput {
-                alias: None,
-                path: provider_comp.to_path_buf(),
-            },
-            ComponentInput {
-                alias: None,
-                path: consumer_comp.to_path_buf(),
-            },
-        ],
-        package_name: WAC_PACKAGE_NAME.to_string(),
-    })
-    .expect("splicer::compose");
-    std::fs::write(&compose_wac, &out.wac).expect("write compose.wac");
+    let mut request = ComposeRequest::new(vec![
+        ComponentInput {
+            alias: None,
+            path: provider_comp.to_path_buf(),
+        },
+        ComponentInput {
+            alias: None,
+            path: consumer_comp.to_path_buf(),
+        },
+    ]);
+    request.package_name = WAC_PACKAGE_NAME.to_string();
+    let out = compose(request).expect("splicer::compose");
+    std::fs::write(&compose_wac, out.wac()).expect("write compose.wac");
     run_wac_command(
         &out.wac_compose_cmd(compose_wac.to_str().unwrap()),
         &composed_path,
@@ -2301,15 +2299,13 @@ fn splice_between(root: &Path, composed_path: &Path, middleware_comp: &Path) ->
     let splits_dir = root.join("splits");
     std::fs::create_dir_all(&splits_dir).unwrap();
 
-    let out = splice(SpliceRequest {
-        composition_wasm: composed_path.to_path_buf(),
-        rules_yaml: splice_yaml(splice_yaml_between_tmpl(), middleware_comp),
-        package_name: WAC_PACKAGE_NAME.to_string(),
-        splits_dir: splits_dir.clone(),
-        skip_type_check: false,
-    })
-    .expect("splicer::splice (between)");
-    std::fs::write(&spliced_wac, &out.wac).expect("write spliced.wac");
+    let rules = parse_yaml(&splice_yaml(splice_yaml_between_tmpl(), middleware_comp))
+        .expect("parse between rules");
+    let mut request = SpliceRequest::new(composed_path.to_path_buf(), rules);
+    request.package_name = WAC_PACKAGE_NAME.to_string();
+    request.splits_dir = splits_dir.clone();
+    let out = splice(request).expect("splicer::splice (between)");
+    std::fs::write(&spliced_wac, out.wac()).expect("write spliced.wac");
     let final_path = root.join("final.wasm");
     run_wac_command(
         &out.wac_compose_cmd(spliced_wac.to_str().unwrap()),
@@ -2333,15 +2329,13 @@ fn splice_before_and_compose(
     let splits_dir = root.join("splits_before");
     std::fs::create_dir_all(&splits_dir).unwrap();
 
-    let splice_out = splice(SpliceRequest {
-        composition_wasm: provider_comp.to_path_buf(),
-        rules_yaml: splice_yaml(splice_yaml_before_tmpl(), middleware_comp),
-        package_name: WAC_PACKAGE_NAME.to_string(),
-        splits_dir: splits_dir.clone(),
-        skip_type_check: false,
-    })
-    .expect("splicer::splice (before)");
-    std::fs::write(&before_wac, &splice_out.wac).expect("write before_splice.wac");
+    let rules = parse_yaml(&splice_yaml(splice_yaml_before_tmpl(), middleware_comp))
+        .expect("parse before rules");
+    let mut request = SpliceRequest::new(provider_comp.to_path_buf(), rules);
+    request.package_name = WAC_PACKAGE_NAME.to_string();
+    request.splits_dir = splits_dir.clone();
+    let splice_out = splice(request).expect("splicer::splice (before)");
+    std::fs::write(&before_wac, splice_out.wac()).expect("write before_splice.wac");
     let spliced_provider = root.join("spliced_provider.wasm");
     run_wac_command(
         &splice_out.wac_compose_cmd(before_wac.to_str().unwrap()),
@@ -2352,21 +2346,19 @@ fn splice_before_and_compose(
 
     // Step 2: compose the spliced provider with the consumer.
     let compose_wac = root.join("final_compose.wac");
-    let compose_out = compose(ComposeRequest {
-        components: vec![
-            ComponentInput {
-                alias: None,
-                path: spliced_provider.clone(),
-            },
-            ComponentInput {
-                alias: None,
-                path: consumer_comp.to_path_buf(),
-            },
-        ],
-        package_name: WAC_PACKAGE_NAME.to_string(),
-    })
-    .expect("splicer::compose (spliced_provider+consumer)");
-    std::fs::write(&compose_wac, &compose_out.wac).expect("write final_compose.wac");
+    let mut request = ComposeRequest::new(vec![
+        ComponentInput {
+            alias: None,
+            path: spliced_provider.clone(),
+        },
+        ComponentInput {
+            alias: None,
+            path: consumer_comp.to_path_buf(),
+        },
+    ]);
+    request.package_name = WAC_PACKAGE_NAME.to_string();
+    let compose_out = compose(request).expect("splicer::compose (spliced_provider+consumer)");
+    std::fs::write(&compose_wac, compose_out.wac()).expect("write final_compose.wac");
     let final_path = root.join("final.wasm");
     run_wac_command(
         &compose_out.wac_compose_cmd(compose_wac.to_str().unwrap()),